@@ -0,0 +1,130 @@
+//! Compares parsing a 50-keyword enum whose discriminants are dispatched on by their first character against the
+//! same enum with `#[peg(no_dispatch)]`, which tries each keyword in turn.
+//!
+//! Run with `cargo bench -p peggle-derive --bench keywords`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use peggle::Parse;
+use peggle_derive::Parse;
+
+macro_rules! keywords {
+    ($($name:ident => $text:literal,)*) => {
+        #[derive(Clone, Copy, Debug, PartialEq, Parse)]
+        pub enum Keyword {
+            $(
+                #[peg($text)]
+                $name,
+            )*
+        }
+
+        #[derive(Clone, Copy, Debug, PartialEq, Parse)]
+        #[peg(no_dispatch)]
+        pub enum UndispatchedKeyword {
+            $(
+                #[peg($text)]
+                $name,
+            )*
+        }
+
+        const KEYWORDS: &[&str] = &[$($text),*];
+
+        impl From<UndispatchedKeyword> for Keyword {
+            fn from(keyword: UndispatchedKeyword) -> Self {
+                match keyword {
+                    $(UndispatchedKeyword::$name => Keyword::$name,)*
+                }
+            }
+        }
+    };
+}
+
+keywords! {
+    Abstract => "abstract",
+    Async => "async",
+    As => "as",
+    Await => "await",
+    Become => "become",
+    Box => "box",
+    Break => "break",
+    Const => "const",
+    Continue => "continue",
+    Crate => "crate",
+    Do => "do",
+    Dyn => "dyn",
+    Else => "else",
+    Enum => "enum",
+    Extern => "extern",
+    False => "false",
+    Final => "final",
+    Fn => "fn",
+    For => "for",
+    If => "if",
+    Impl => "impl",
+    In => "in",
+    Let => "let",
+    Loop => "loop",
+    Macro => "macro",
+    Match => "match",
+    Mod => "mod",
+    Move => "move",
+    Mut => "mut",
+    Override => "override",
+    Priv => "priv",
+    Pub => "pub",
+    Ref => "ref",
+    Return => "return",
+    SelfValue => "self",
+    Static => "static",
+    Struct => "struct",
+    Super => "super",
+    Trait => "trait",
+    True => "true",
+    Try => "try",
+    Typeof => "typeof",
+    Type => "type",
+    Unsafe => "unsafe",
+    Unsized => "unsized",
+    Use => "use",
+    Virtual => "virtual",
+    Where => "where",
+    While => "while",
+    Yield => "yield",
+}
+
+const ITERATIONS: usize = 20_000;
+
+/// Parses every keyword (and a non-keyword) `ITERATIONS` times, returning the average time taken per parse.
+fn measure(parse: impl Fn(&str) -> Option<Keyword>) -> Duration {
+    let inputs: Vec<&str> = KEYWORDS.iter().copied().chain(["identifier"]).collect();
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        for input in &inputs {
+            black_box(parse(black_box(input)));
+        }
+    }
+
+    start.elapsed() / (ITERATIONS * inputs.len()) as u32
+}
+
+fn main() {
+    assert_eq!(KEYWORDS.len(), 50);
+    for &keyword in KEYWORDS {
+        assert_eq!(
+            Keyword::parse(keyword).ok(),
+            UndispatchedKeyword::parse(keyword).ok().map(Keyword::from)
+        );
+    }
+
+    let sequential = measure(|input| UndispatchedKeyword::parse(input).ok().map(Keyword::from));
+    let dispatched = measure(|input| Keyword::parse(input).ok());
+
+    println!("sequential: {:?} per parse", sequential);
+    println!("dispatched: {:?} per parse", dispatched);
+    println!(
+        "speedup:    {:.1}x",
+        sequential.as_secs_f64() / dispatched.as_secs_f64()
+    );
+}
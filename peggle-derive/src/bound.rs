@@ -0,0 +1,156 @@
+use std::collections::HashSet;
+
+use crate::structure::{
+    Conversion, ConvertedInfo, DeriveInfo, FieldCardinality, FieldInfo, PegAttributes,
+};
+
+/// Adds the bounds that the derived implementation requires of the type's generic parameters to `generics`.
+///
+/// Unless overridden with `#[peg(bound = "...")]`, each parsed field whose type mentions a generic type parameter is
+/// required to implement `Parse` (and `Default`, if it may be recovered from). Overriding bounds name the input type
+/// as `PeggleInput` unless the implementation is restricted to an `input` type.
+pub fn add_bounds(
+    generics: &mut syn::Generics,
+    ast: &syn::DeriveInput,
+    derive_info: &DeriveInfo,
+    input_ty: &proc_macro2::TokenStream,
+) {
+    if let Some(bound) = PegAttributes::from_attrs(&ast.attrs).bound {
+        generics.make_where_clause().predicates.extend(bound);
+        return;
+    }
+
+    let params: HashSet<String> = ast
+        .generics
+        .type_params()
+        .map(|param| param.ident.to_string())
+        .collect();
+    if params.is_empty() {
+        return;
+    }
+
+    let mut predicates: Vec<syn::WherePredicate> = Vec::new();
+    let mut seen = HashSet::new();
+    let mut add_predicate = |ty: &syn::Type, bound: proc_macro2::TokenStream| {
+        if mentions_params(quote::ToTokens::to_token_stream(ty), &params) {
+            let predicate = quote::quote! { #ty: #bound };
+            if seen.insert(predicate.to_string()) {
+                predicates.push(syn::parse_quote! { #predicate });
+            }
+        }
+    };
+
+    let parse_bound = quote::quote! { peggle::Parse<#input_ty> };
+    let mut add_field_predicates = |field: &FieldInfo, in_recovering_discriminant: bool| {
+        if field.default.is_some() {
+            return;
+        }
+
+        match &field.conversion {
+            None => add_predicate(&field.inner_ty, parse_bound.clone()),
+            Some(Conversion::TryFrom(raw_ty)) => add_predicate(raw_ty, parse_bound.clone()),
+            // The type parsed is only known from the mapping function
+            Some(Conversion::Map(_)) => (),
+        }
+
+        // Fields that are recovered from (or left missing by a recovered discriminant) take their default value
+        let default_bound = quote::quote! { ::std::default::Default };
+        if field.recover.is_some() {
+            add_predicate(&field.inner_ty, default_bound.clone());
+        }
+        if in_recovering_discriminant && field.cardinality == FieldCardinality::Single {
+            add_predicate(&field.ty, default_bound);
+        }
+    };
+
+    match derive_info {
+        DeriveInfo::NamedStruct(collection) | DeriveInfo::UnnamedStruct(collection) => {
+            for field in &collection.fields {
+                add_field_predicates(field, false);
+            }
+        }
+        DeriveInfo::Enum(e) => {
+            for discriminant in &e.discriminants {
+                for field in &discriminant.fields {
+                    add_field_predicates(field, discriminant.recover.is_some());
+                }
+            }
+        }
+        DeriveInfo::Converted(converted) => {
+            if let Conversion::TryFrom(raw_ty) = &converted.conversion {
+                add_predicate(raw_ty, parse_bound.clone());
+            }
+        }
+    }
+
+    generics.make_where_clause().predicates.extend(predicates);
+}
+
+/// Determines whether any of the given generic type parameters are named within `tokens`.
+fn mentions_params(tokens: proc_macro2::TokenStream, params: &HashSet<String>) -> bool {
+    tokens.into_iter().any(|token| match token {
+        proc_macro2::TokenTree::Ident(ident) => params.contains(&ident.to_string()),
+        proc_macro2::TokenTree::Group(group) => mentions_params(group.stream(), params),
+        _ => false,
+    })
+}
+
+/// Adds the bounds that the derived `Grammar` implementation requires of the type's generic parameters to `generics`.
+///
+/// Each type whose rule is referred to (that of each parsed field without its own peggle expression) and that
+/// mentions a generic type parameter is required to implement `Grammar`.
+pub fn add_grammar_bounds(
+    generics: &mut syn::Generics,
+    ast: &syn::DeriveInput,
+    derive_info: &DeriveInfo,
+) {
+    let params: HashSet<String> = ast
+        .generics
+        .type_params()
+        .map(|param| param.ident.to_string())
+        .collect();
+    if params.is_empty() {
+        return;
+    }
+
+    let fields: Vec<&FieldInfo> = match derive_info {
+        DeriveInfo::NamedStruct(collection) | DeriveInfo::UnnamedStruct(collection) => {
+            collection.fields.iter().collect()
+        }
+        DeriveInfo::Enum(e) => e.discriminants.iter().flat_map(|d| &d.fields).collect(),
+        DeriveInfo::Converted(_) => Vec::new(),
+    };
+
+    let mut referenced: Vec<&syn::Type> = Vec::new();
+    for field in fields {
+        if field.default.is_some() || field.pegex.is_some() {
+            continue;
+        }
+        match &field.conversion {
+            None => referenced.push(&field.inner_ty),
+            Some(Conversion::TryFrom(raw_ty)) => referenced.push(raw_ty),
+            // A mapped field is described only by the name of its type
+            Some(Conversion::Map(_)) => (),
+        }
+    }
+    if let DeriveInfo::Converted(ConvertedInfo {
+        conversion: Conversion::TryFrom(raw_ty),
+        ..
+    }) = derive_info
+    {
+        referenced.push(raw_ty);
+    }
+
+    let mut seen = HashSet::new();
+    for ty in referenced {
+        if mentions_params(quote::ToTokens::to_token_stream(ty), &params) {
+            let predicate = quote::quote! { #ty: peggle::grammar::Grammar };
+            if seen.insert(predicate.to_string()) {
+                generics
+                    .make_where_clause()
+                    .predicates
+                    .push(syn::parse_quote! { #predicate });
+            }
+        }
+    }
+}
@@ -0,0 +1,439 @@
+/// A set of characters, stored as a sorted list of disjoint, non-adjacent inclusive ranges.
+///
+/// Bracketed expressions (`[...]`) and backslash classes (`\w`, `\d`, ...) are both resolved into a
+/// [`CharClass`] while the pattern is being read, so that set operations (negation, intersection and
+/// subtraction) can be computed once at compile time rather than in the generated code.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CharClass {
+    ranges: Vec<(char, char)>,
+}
+
+impl CharClass {
+    /// Creates an empty character class.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a character class containing every character from `start` to `end` (inclusive).
+    #[inline]
+    pub fn from_range(start: char, end: char) -> Self {
+        Self {
+            ranges: vec![(start, end)],
+        }
+    }
+
+    /// Creates a character class containing each of the characters in the given string.
+    pub fn from_chars(chars: &str) -> Self {
+        let mut class = Self::new();
+        for c in chars.chars() {
+            class.add_range(c, c);
+        }
+        class
+    }
+
+    /// Creates a character class from a list of (possibly overlapping) ranges.
+    pub fn from_ranges(ranges: &[(char, char)]) -> Self {
+        let mut class = Self::new();
+        for &(start, end) in ranges {
+            class.add_range(start, end);
+        }
+        class
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// The disjoint ranges of characters in this class, in ascending order.
+    #[inline]
+    pub fn ranges(&self) -> &[(char, char)] {
+        &self.ranges
+    }
+
+    pub fn contains(&self, c: char) -> bool {
+        self.ranges
+            .iter()
+            .any(|&(start, end)| start <= c && c <= end)
+    }
+
+    pub fn add_range(&mut self, start: char, end: char) {
+        self.ranges.push((start, end));
+        self.normalize();
+    }
+
+    /// Adds all characters of `other` to this class.
+    pub fn union(&mut self, other: &CharClass) {
+        self.ranges.extend_from_slice(&other.ranges);
+        self.normalize();
+    }
+
+    /// Removes any character from this class that is not also contained in `other`.
+    pub fn intersect(&mut self, other: &CharClass) {
+        let mut ranges = Vec::new();
+        for &(a_start, a_end) in &self.ranges {
+            for &(b_start, b_end) in &other.ranges {
+                let start = std::cmp::max(a_start, b_start);
+                let end = std::cmp::min(a_end, b_end);
+                if start <= end {
+                    ranges.push((start, end));
+                }
+            }
+        }
+        self.ranges = ranges;
+        self.normalize();
+    }
+
+    /// Removes all characters of `other` from this class.
+    pub fn subtract(&mut self, other: &CharClass) {
+        let mut complement = other.clone();
+        complement.negate();
+        self.intersect(&complement);
+    }
+
+    /// Replaces this class with every character not currently contained in it.
+    pub fn negate(&mut self) {
+        let mut ranges = Vec::new();
+        let mut next_start = Some('\0');
+
+        for &(start, end) in &self.ranges {
+            if let Some(gap_start) = next_start {
+                if gap_start < start {
+                    ranges.push((gap_start, char_before(start)));
+                }
+            }
+            next_start = char_after(end);
+        }
+
+        if let Some(gap_start) = next_start {
+            ranges.push((gap_start, char::MAX));
+        }
+
+        self.ranges = ranges;
+    }
+
+    /// Generates a boolean expression that checks whether the character bound to `ident` is within this class.
+    pub fn to_tokens(&self, ident: &syn::Ident) -> proc_macro2::TokenStream {
+        if self.ranges.is_empty() {
+            return quote::quote! { false };
+        }
+
+        let patterns = self.ranges.iter().map(|&(start, end)| {
+            if start == end {
+                quote::quote! { #start }
+            } else {
+                quote::quote! { #start..=#end }
+            }
+        });
+
+        quote::quote! { matches!(#ident, #(#patterns)|*) }
+    }
+
+    /// Sorts ranges and merges any that overlap or are adjacent to one another.
+    fn normalize(&mut self) {
+        self.ranges.sort_unstable();
+
+        let mut merged: Vec<(char, char)> = Vec::with_capacity(self.ranges.len());
+        for &(start, end) in &self.ranges {
+            match merged.last_mut() {
+                Some((_, last_end)) if char_after(*last_end).is_none_or(|c| c >= start) => {
+                    *last_end = std::cmp::max(*last_end, end);
+                }
+                _ => merged.push((start, end)),
+            }
+        }
+
+        self.ranges = merged;
+    }
+}
+
+/// The character immediately following `c`, skipping over the surrogate range.
+pub fn char_after(c: char) -> Option<char> {
+    match c {
+        '\u{d7ff}' => Some('\u{e000}'),
+        char::MAX => None,
+        _ => char::from_u32(c as u32 + 1),
+    }
+}
+
+/// The character immediately preceding `c`, skipping over the surrogate range (`c` must not be `'\0'`).
+pub fn char_before(c: char) -> char {
+    match c {
+        '\u{e000}' => '\u{d7ff}',
+        _ => char::from_u32(c as u32 - 1).expect("character range underflow"),
+    }
+}
+
+/// Returns the class matched by the backslash-escaped character `c` (such as `\d` or `\W`), if any.
+pub fn backslash_class(c: char) -> Option<CharClass> {
+    let mut class = match c.to_ascii_lowercase() {
+        'w' => CharClass::from_ranges(&[('a', 'z'), ('A', 'Z'), ('_', '_')]),
+        'd' => CharClass::from_range('0', '9'),
+        's' => CharClass::from_chars(" \t\n\r\x0b\x0c"),
+        _ => return None,
+    };
+
+    if c.is_ascii_uppercase() {
+        class.negate();
+    }
+
+    Some(class)
+}
+
+/// Reads the character denoted by an escape sequence such as `\n`, `\x7f` or `\u{1F600}`.
+///
+/// `c` is the character immediately following the backslash; if `c` does not begin one of these sequences, `None`
+/// is returned and `index` is left untouched. Note that `\xNN` may name any codepoint up to `\xff`.
+///
+/// Each digit must be hexadecimal:
+///
+/// ```compile_fail
+/// #[derive(peggle_derive::Parse)]
+/// #[peg("[\\x+f]")]
+/// pub struct Signed {}
+/// ```
+///
+/// ```compile_fail
+/// #[derive(peggle_derive::Parse)]
+/// #[peg("[\\u{+f}]")]
+/// pub struct SignedUnicode {}
+/// ```
+pub fn escape_sequence(c: char, index: &mut peggle::Index) -> Option<char> {
+    match c {
+        'n' => Some('\n'),
+        'r' => Some('\r'),
+        't' => Some('\t'),
+        '0' => Some('\0'),
+        'x' => {
+            let Some(digits) = index.next_multiple::<2>() else {
+                panic!("expected two hexadecimal digits after `\\x`")
+            };
+            let digits: String = digits.iter().collect();
+            // `from_str_radix` would also accept a sign, such as in `\x+f`
+            if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                panic!("invalid hexadecimal escape `\\x{}`", digits)
+            }
+            let value = u8::from_str_radix(&digits, 16).expect("two hexadecimal digits");
+            Some(char::from(value))
+        }
+        'u' => {
+            let Some('{') = index.next() else {
+                panic!("expected opening curly brace after `\\u`")
+            };
+
+            let mut digits = String::new();
+            loop {
+                match index.next() {
+                    Some('}') => break,
+                    Some(c) if c.is_ascii_hexdigit() && digits.len() < 6 => digits.push(c),
+                    _ => panic!("invalid unicode escape `\\u{{{}`: expected 1 to 6 hexadecimal digits followed by `}}`", digits),
+                }
+            }
+
+            let codepoint = u32::from_str_radix(&digits, 16)
+                .unwrap_or_else(|_| panic!("invalid unicode escape `\\u{{{}}}`", digits));
+            Some(char::from_u32(codepoint).unwrap_or_else(|| {
+                panic!(
+                    "unicode escape `\\u{{{}}}` is not a valid character",
+                    digits
+                )
+            }))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the class represented by the given POSIX character class name (such as `alpha` in `[[:alpha:]]`).
+pub fn posix_class(label: &str) -> Option<CharClass> {
+    Some(match label {
+        "upper" => CharClass::from_range('A', 'Z'),
+        "lower" => CharClass::from_range('a', 'z'),
+        "alpha" => CharClass::from_ranges(&[('a', 'z'), ('A', 'Z')]),
+        "digit" => CharClass::from_range('0', '9'),
+        "xdigit" => CharClass::from_ranges(&[('0', '9'), ('a', 'f'), ('A', 'F')]),
+        "alnum" => CharClass::from_ranges(&[('0', '9'), ('a', 'z'), ('A', 'Z')]),
+        "punct" => CharClass::from_chars("][!\"#$%&'()*+,./:;<=>?@\\^_`{|}~-"),
+        "blank" => CharClass::from_chars(" \t"),
+        "space" => CharClass::from_chars(" \t\n\r\x0c\x0b"),
+        "cntrl" => CharClass::from_ranges(&[('\x00', '\x1f'), ('\x7f', '\x7f')]),
+        "graph" => CharClass::from_range('\x21', '\x7e'),
+        "print" => CharClass::from_range('\x20', '\x7e'),
+        _ => return None,
+    })
+}
+
+/// Reads a bracketed character class from `index`, which is expected to be positioned just after the opening `[`.
+///
+/// Besides single characters, ranges (`a-z`), POSIX classes (`[:alpha:]`) and backslash classes (`\w`), a class
+/// may contain nested classes (`[a-c[x-z]]`), intersections (`[\w&&[^_]]`) and subtractions (`[[:alpha:]--[aeiou]]`).
+/// Intersection and subtraction share the same precedence and are applied from left to right; a leading `^`
+/// negates the result of the entire class.
+pub fn parse_bracket_class(index: &mut peggle::Index) -> CharClass {
+    let inverted = Some('^') == index.peek();
+    if inverted {
+        index.next();
+    }
+
+    let mut class = parse_class_operand(index, true);
+
+    loop {
+        match index.peek_multiple::<2>() {
+            Some(['&', '&']) => {
+                index.next_multiple::<2>();
+                class.intersect(&parse_class_operand(index, false));
+            }
+            Some(['-', '-']) => {
+                index.next_multiple::<2>();
+                class.subtract(&parse_class_operand(index, false));
+            }
+            _ => {
+                let Some(']') = index.next() else {
+                    panic!("missing closing bracket (`]`) character in regex")
+                };
+                break;
+            }
+        }
+    }
+
+    if inverted {
+        class.negate();
+    }
+
+    class
+}
+
+/// Reads the union of characters, ranges and classes up to (but not including) the next set operator or closing `]`.
+fn parse_class_operand(index: &mut peggle::Index, class_start: bool) -> CharClass {
+    let mut class = CharClass::new();
+
+    if class_start {
+        // A leading `]` or `-` is treated as a literal rather than as the end of the class or a range
+        if let Some(lit @ (']' | '-')) = index.peek() {
+            index.next();
+            add_char_or_range(lit, index, &mut class);
+        }
+    }
+
+    loop {
+        if is_set_operator(index) {
+            assert!(
+                !class.is_empty(),
+                "character class set operator missing left-hand operand"
+            );
+            return class;
+        }
+
+        match index.peek() {
+            Some(']') => return class,
+            None => panic!("missing closing bracket (`]`) character in regex"),
+            _ => (),
+        }
+
+        let c = index.next().unwrap();
+        match (c, index.peek()) {
+            ('-', _) => panic!("dash must be preceded by starting value that is not also an ending value for another dash"),
+            ('[', Some(':')) => {
+                index.next(); // Consume ':'
+                class.union(&parse_posix_class(index));
+            }
+            ('[', _) => class.union(&parse_bracket_class(index)),
+            ('\\', _) => {
+                let escaped = index
+                    .next()
+                    .expect("expected character after backslash");
+                match backslash_class(escaped) {
+                    Some(escaped_class) => class.union(&escaped_class),
+                    None => {
+                        let c = escape_sequence(escaped, index).unwrap_or(escaped);
+                        add_char_or_range(c, index, &mut class)
+                    }
+                }
+            }
+            _ => add_char_or_range(c, index, &mut class),
+        }
+    }
+}
+
+/// Adds either the literal `c` or a range starting at `c` (if followed by a dash) to `class`.
+fn add_char_or_range(c: char, index: &mut peggle::Index, class: &mut CharClass) {
+    if index.peek() != Some('-') || is_set_operator(index) {
+        class.add_range(c, c);
+        return;
+    }
+
+    // Handle "a-z" case (using a dash to indicate a range of values)
+    index.next();
+    match index
+        .peek()
+        .expect("missing closing bracket (`]`) character in regex")
+    {
+        ']' => {
+            // A trailing dash is a literal (as in `[#-]`)
+            class.add_range(c, c);
+            class.add_range('-', '-');
+        }
+        '\\' => {
+            index.next();
+            let escaped = index.next().expect("expected character after backslash");
+            assert!(
+                backslash_class(escaped).is_none(),
+                "class `\\{}` cannot be used as the end of a range",
+                escaped
+            );
+            let end_char = escape_sequence(escaped, index).unwrap_or(escaped);
+            assert!(
+                end_char >= c,
+                "range {}-{} invalid: {} comes before {} in ordering",
+                c.escape_debug(),
+                end_char.escape_debug(),
+                end_char.escape_debug(),
+                c.escape_debug()
+            );
+            class.add_range(c, end_char);
+        }
+        end_char if end_char < c => panic!(
+            "range {}-{} invalid: {} comes before {} in ordering",
+            c, end_char, end_char, c
+        ),
+        end_char => {
+            index.next();
+            class.add_range(c, end_char);
+        }
+    }
+}
+
+/// Determines whether the index is positioned at a `&&` or `--` set operator.
+///
+/// A `--` immediately followed by the closing bracket is instead treated as a range ending in `-` (as in `[#--]`).
+fn is_set_operator(index: &peggle::Index) -> bool {
+    match index.peek_multiple::<3>() {
+        Some(['&', '&', _]) => true,
+        Some(['-', '-', next]) => next != ']',
+        _ => false,
+    }
+}
+
+fn parse_posix_class(index: &mut peggle::Index) -> CharClass {
+    let mut label = String::new();
+    loop {
+        match index.next() {
+            Some(':') => break,
+            Some(c) if c.is_ascii_lowercase() => label.push(c),
+            _ => panic!("unrecognized POSIX character class '{}'", label),
+        }
+    }
+
+    let Some(class) = posix_class(&label) else {
+        panic!("unrecognized POSIX character class '{}'", label);
+    };
+
+    let Some(']') = index.next() else {
+        panic!(
+            "POSIX character class {} missing closing ']' bracket",
+            label
+        );
+    };
+
+    class
+}
@@ -0,0 +1,140 @@
+use crate::pattern::{parse_pattern, Choice, Expr, PatternOptions, Sequence};
+use crate::structure::{CollectionInfo, Conversion, DeriveInfo, FieldInfo};
+
+/// Generates an expression building the `peggle::grammar::Node` that describes what the derived type matches.
+pub fn derive_node(derive_info: &DeriveInfo) -> proc_macro2::TokenStream {
+    match derive_info {
+        DeriveInfo::NamedStruct(collection) | DeriveInfo::UnnamedStruct(collection) => {
+            collection_node(collection)
+        }
+        DeriveInfo::Enum(e) => {
+            let discriminants = e.discriminants.iter().map(collection_node);
+            quote::quote! { peggle::grammar::Node::Choice(vec![#(#discriminants),*]) }
+        }
+        DeriveInfo::Converted(converted) => match &converted.conversion {
+            Conversion::TryFrom(raw_ty) => reference_node(raw_ty),
+            Conversion::Map(_) => panic!(
+                "a type with 'map' is parsed as a type only known from its mapping function, so its grammar can't be derived"
+            ),
+        },
+    }
+}
+
+fn collection_node(collection: &CollectionInfo) -> proc_macro2::TokenStream {
+    let choice = parse_pattern(
+        &collection.pegex,
+        PatternOptions {
+            fields: true,
+            backtrack: collection.backtrack,
+        },
+    );
+    choice_node(&choice, &collection.fields)
+}
+
+/// Describes the type of a field, or the pattern it's restricted to if it has one.
+fn field_node(field: &FieldInfo) -> proc_macro2::TokenStream {
+    if let Some(pegex) = &field.pegex {
+        let choice = parse_pattern(
+            pegex,
+            PatternOptions {
+                fields: false,
+                backtrack: field.backtrack,
+            },
+        );
+        return choice_node(&choice, &[]);
+    }
+
+    match &field.conversion {
+        None => reference_node(&field.inner_ty),
+        Some(Conversion::TryFrom(raw_ty)) => reference_node(raw_ty),
+        Some(Conversion::Map(_)) => {
+            let name = quote::ToTokens::to_token_stream(&field.inner_ty)
+                .to_string()
+                .replace(' ', "");
+            quote::quote! { peggle::grammar::Node::External(#name.to_string()) }
+        }
+    }
+}
+
+fn reference_node(ty: &syn::Type) -> proc_macro2::TokenStream {
+    quote::quote! { peggle::grammar::Node::Reference(<#ty as peggle::grammar::Grammar>::rule) }
+}
+
+fn choice_node(choice: &Choice, fields: &[FieldInfo]) -> proc_macro2::TokenStream {
+    if let [sequence] = choice.as_slice() {
+        return sequence_node(sequence, fields);
+    }
+
+    let sequences = choice
+        .iter()
+        .map(|sequence| sequence_node(sequence, fields));
+    quote::quote! { peggle::grammar::Node::Choice(vec![#(#sequences),*]) }
+}
+
+fn sequence_node(sequence: &Sequence, fields: &[FieldInfo]) -> proc_macro2::TokenStream {
+    let mut nodes = Vec::new();
+
+    let mut idx = 0;
+    while idx < sequence.len() {
+        let expr = &sequence[idx];
+        idx += 1;
+
+        // Runs of literal characters are described as a single piece of text
+        if let Expr::Char(first) = expr {
+            let mut literal = first.to_string();
+            while let Some(Expr::Char(c)) = sequence.get(idx) {
+                literal.push(*c);
+                idx += 1;
+            }
+            nodes.push(quote::quote! { peggle::grammar::Node::Literal(#literal.to_string()) });
+            continue;
+        }
+
+        nodes.push(expr_node(expr, fields));
+    }
+
+    match nodes.as_slice() {
+        [node] => node.clone(),
+        _ => quote::quote! { peggle::grammar::Node::Sequence(vec![#(#nodes),*]) },
+    }
+}
+
+fn expr_node(expr: &Expr, fields: &[FieldInfo]) -> proc_macro2::TokenStream {
+    match expr {
+        Expr::Char(c) => {
+            let literal = c.to_string();
+            quote::quote! { peggle::grammar::Node::Literal(#literal.to_string()) }
+        }
+        Expr::Any => quote::quote! { peggle::grammar::Node::Any },
+        Expr::Class(class) => {
+            let ranges = class
+                .ranges()
+                .iter()
+                .map(|(start, end)| quote::quote! { (#start, #end) });
+            quote::quote! { peggle::grammar::Node::Class(vec![#(#ranges),*]) }
+        }
+        Expr::Field { name, .. } => {
+            let Some(field) = fields.iter().find(|f| &f.ident == name) else {
+                panic!("Unrecognized field {}", name)
+            };
+            field_node(field)
+        }
+        Expr::Group(choice) => choice_node(choice, fields),
+        Expr::Repeat(inner, repetition) => {
+            let inner = expr_node(inner, fields);
+            let min = repetition.min;
+            let max = match repetition.max {
+                usize::MAX => quote::quote! { None },
+                max => quote::quote! { Some(#max) },
+            };
+            quote::quote! {
+                peggle::grammar::Node::Repeat {
+                    node: Box::new(#inner),
+                    min: #min,
+                    max: #max,
+                }
+            }
+        }
+        Expr::Cut => quote::quote! { peggle::grammar::Node::Cut },
+    }
+}
@@ -0,0 +1,295 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::lint::leftmost_fields;
+use crate::pattern::{parse_pattern, PatternOptions};
+
+/// A rule read from a grammar file, which becomes a struct (or an enum, if it lists alternatives).
+struct Rule {
+    attrs: Vec<syn::Attribute>,
+    header: syn::Variant,
+    pegex: Option<String>,
+    variants: Option<Vec<Variant>>,
+}
+
+/// An alternative of an enum rule (`| Name(Type) = pattern`), which becomes a discriminant of the enum.
+struct Variant {
+    attrs: Vec<syn::Attribute>,
+    header: syn::Variant,
+    pegex: Option<String>,
+}
+
+/// Generates the types defined by the grammar file at `path` (relative to the crate's manifest directory).
+///
+/// Each rule of the file is given on a line of its own as `Name = pattern`, `Name(Type, ...) = pattern` or
+/// `Name { field: Type, ... } = pattern`, where the pattern is the rest of the line (with surrounding whitespace
+/// removed). A rule with nothing following the `=` is an enum, whose discriminants are listed on the lines after it
+/// in the same way but starting with `|` (a unit discriminant may leave out `= pattern` to match its own name).
+/// Lines of the form `#[...]` give attributes for the rule or discriminant that follows them, and any other line
+/// beginning with `#` is a comment.
+pub fn expand(path: &syn::LitStr) -> proc_macro2::TokenStream {
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+    let full_path = PathBuf::from(manifest_dir).join(path.value());
+    let grammar = std::fs::read_to_string(&full_path)
+        .unwrap_or_else(|e| panic!("failed to read grammar file {}: {}", full_path.display(), e));
+
+    let rules = parse_grammar(&grammar, &path.value());
+    check_left_recursion(&rules, &path.value());
+    let items = rules.into_iter().map(rule_tokens);
+
+    // Including the file makes the compiler rebuild the types whenever the grammar changes
+    let full_path = full_path.display().to_string();
+    quote::quote! {
+        const _: &str = include_str!(#full_path);
+
+        #(#items)*
+    }
+}
+
+fn parse_grammar(grammar: &str, path: &str) -> Vec<Rule> {
+    let mut rules: Vec<Rule> = Vec::new();
+    let mut attrs = Vec::new();
+
+    for (lineno, line) in grammar.lines().enumerate() {
+        let line = line.trim();
+        let location = format!("{}:{}", path, lineno + 1);
+
+        if line.starts_with("#[") {
+            let parsed = syn::parse::Parser::parse_str(syn::Attribute::parse_outer, line)
+                .unwrap_or_else(|e| panic!("{}: invalid attribute: {}", location, e));
+            attrs.extend(parsed);
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(alternative) = line.strip_prefix('|') {
+            let (header, pegex) = parse_definition(alternative, &location);
+            let Some(variants) = rules.last_mut().and_then(|r| r.variants.as_mut()) else {
+                panic!(
+                    "{}: alternative `{}` doesn't follow an enum rule (`Name =`)",
+                    location, line
+                )
+            };
+            variants.push(Variant {
+                attrs: std::mem::take(&mut attrs),
+                header,
+                pegex,
+            });
+            continue;
+        }
+
+        let (header, pegex) = parse_definition(line, &location);
+        let variants = match (&pegex, &header.fields) {
+            (None, syn::Fields::Unit) => Some(Vec::new()),
+            (None, _) => panic!(
+                "{}: rule `{}` is missing its pattern",
+                location, header.ident
+            ),
+            (Some(_), _) => None,
+        };
+        rules.push(Rule {
+            attrs: std::mem::take(&mut attrs),
+            header,
+            pegex,
+            variants,
+        });
+    }
+
+    assert!(
+        attrs.is_empty(),
+        "{}: attributes at the end of the file don't apply to any rule",
+        path
+    );
+    if let Some(rule) = rules
+        .iter()
+        .find(|r| matches!(&r.variants, Some(v) if v.is_empty()))
+    {
+        panic!(
+            "{}: enum rule `{}` has no alternatives (listed on the following lines as `| Name = pattern`)",
+            path, rule.header.ident
+        );
+    }
+    rules
+}
+
+/// Splits a definition such as `Name(Type) = pattern` into its name and fields, and its pattern (if not empty).
+fn parse_definition(definition: &str, location: &str) -> (syn::Variant, Option<String>) {
+    // The pattern begins after the first `=` that isn't within brackets or a string in the field list
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut split = None;
+    for (idx, c) in definition.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '(' | '[' | '{' | '<' if !in_string => depth += 1,
+            ')' | ']' | '}' | '>' if !in_string => depth = depth.saturating_sub(1),
+            '=' if !in_string && depth == 0 => {
+                split = Some(idx);
+                break;
+            }
+            _ => (),
+        }
+    }
+
+    let (header, pegex) = match split {
+        Some(idx) => (&definition[..idx], Some(definition[idx + 1..].trim())),
+        None => (definition, None),
+    };
+    let header = syn::parse_str::<syn::Variant>(header.trim())
+        .unwrap_or_else(|e| panic!("{}: invalid rule name or fields: {}", location, e));
+
+    let pegex = pegex.filter(|p| !p.is_empty()).map(str::to_string);
+    (header, pegex)
+}
+
+/// Panics if a rule can be reached again from itself through other rules before any input is consumed, as parsing
+/// it would then recurse forever. (Rules referring directly to themselves are reported by the derive.)
+fn check_left_recursion(rules: &[Rule], path: &str) {
+    let mut leftmost: HashMap<String, Vec<String>> = HashMap::new();
+    for rule in rules {
+        let mut definitions = vec![(&rule.header.fields, rule.pegex.as_deref())];
+        for variant in rule.variants.iter().flatten() {
+            definitions.push((&variant.header.fields, variant.pegex.as_deref()));
+        }
+
+        let referenced = leftmost.entry(rule.header.ident.to_string()).or_default();
+        for (fields, pegex) in definitions {
+            // A unit discriminant without a pattern matches its own name, and so never refers to a rule
+            let Some(pegex) = pegex else { continue };
+            let choice = parse_pattern(
+                pegex,
+                PatternOptions {
+                    fields: true,
+                    backtrack: false,
+                },
+            );
+
+            for name in leftmost_fields(&choice) {
+                let field = fields
+                    .iter()
+                    .enumerate()
+                    .find(|(idx, field)| match &field.ident {
+                        Some(ident) => ident == name,
+                        None => idx.to_string() == name,
+                    });
+                // Fields restricted to a pattern of their own don't parse their type
+                let Some((_, field)) = field.filter(|(_, f)| f.attrs.is_empty()) else {
+                    continue;
+                };
+                if let Some(ty) = rule_type(&field.ty) {
+                    referenced.push(ty);
+                }
+            }
+        }
+    }
+
+    for rule in rules {
+        let start = rule.header.ident.to_string();
+        let mut chain = vec![start.clone()];
+        if let Some(cycle) = find_cycle(&leftmost, &start, &mut chain) {
+            panic!(
+                "{}: rules {} refer to each other before any input is consumed, so parsing would recurse forever \
+                 (left recursion); rewrite one of them so that input is matched first",
+                path,
+                cycle.join(" -> ")
+            );
+        }
+    }
+}
+
+/// Returns the chain of rules leading from the last rule of `chain` back to `start`, if there is one.
+fn find_cycle(
+    leftmost: &HashMap<String, Vec<String>>,
+    start: &str,
+    chain: &mut Vec<String>,
+) -> Option<Vec<String>> {
+    let current = chain.last().expect("chain starts with a rule").clone();
+    for next in leftmost.get(&current).into_iter().flatten() {
+        if next == start && chain.len() > 1 {
+            let mut cycle = chain.clone();
+            cycle.push(next.clone());
+            return Some(cycle);
+        }
+        if chain.contains(next) || !leftmost.contains_key(next) {
+            continue;
+        }
+
+        chain.push(next.clone());
+        if let Some(cycle) = find_cycle(leftmost, start, chain) {
+            return Some(cycle);
+        }
+        chain.pop();
+    }
+    None
+}
+
+/// Returns the name of the type a field is parsed as, looking within any `Box`, `Option` or `Vec`.
+fn rule_type(ty: &syn::Type) -> Option<String> {
+    let syn::Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    match (segment.ident.to_string().as_str(), &segment.arguments) {
+        ("Box" | "Option" | "Vec", syn::PathArguments::AngleBracketed(args)) => {
+            args.args.iter().find_map(|arg| match arg {
+                syn::GenericArgument::Type(ty) => rule_type(ty),
+                _ => None,
+            })
+        }
+        (name, _) => Some(name.to_string()),
+    }
+}
+
+fn rule_tokens(rule: Rule) -> proc_macro2::TokenStream {
+    let Rule {
+        attrs,
+        header,
+        pegex,
+        variants,
+    } = rule;
+    let name = &header.ident;
+
+    if let Some(variants) = variants {
+        let variants = variants.into_iter().map(|variant| {
+            let Variant {
+                attrs,
+                header,
+                pegex,
+            } = variant;
+            let pegex = pegex.map(|pegex| quote::quote! { #[peg(#pegex)] });
+            quote::quote! {
+                #(#attrs)*
+                #pegex
+                #header
+            }
+        });
+
+        return quote::quote! {
+            #(#attrs)*
+            #[derive(::peggle_derive::Parse)]
+            pub enum #name {
+                #(#variants,)*
+            }
+        };
+    }
+
+    let mut fields = header.fields;
+    for field in fields.iter_mut() {
+        if matches!(field.vis, syn::Visibility::Inherited) {
+            field.vis = syn::parse_quote! { pub };
+        }
+    }
+    let body = match &fields {
+        syn::Fields::Named(_) => quote::quote! { #fields },
+        _ => quote::quote! { #fields; },
+    };
+
+    quote::quote! {
+        #(#attrs)*
+        #[derive(::peggle_derive::Parse)]
+        #[peg(#pegex)]
+        pub struct #name #body
+    }
+}
@@ -1,5 +1,12 @@
+mod bound;
+mod class;
+mod describe;
+mod grammar;
+mod lint;
 mod parse;
+mod pattern;
 mod structure;
+mod vm;
 
 use structure::DeriveInfo;
 
@@ -7,21 +14,127 @@ use structure::DeriveInfo;
 pub fn derive_peg_parse(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let ast = syn::parse_macro_input!(item as syn::DeriveInput);
     let derive_info = DeriveInfo::new(&ast);
-    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
-    let ident = ast.ident;
+    let ident = ast.ident.clone();
+
+    // The implementation is generic over any type of input unless an `input` type is given. The parameter is named
+    // so that `#[peg(bound = "...")]` predicates can refer to it (such as `T: peggle::Parse<PeggleInput>`).
+    let mut generics = ast.generics.clone();
+    let input_ty = match derive_info.input() {
+        Some(ty) => quote::quote! { #ty },
+        None => {
+            generics
+                .params
+                .push(syn::parse_quote! { PeggleInput: ?Sized + peggle::Input });
+            quote::quote! { PeggleInput }
+        }
+    };
+    bound::add_bounds(&mut generics, &ast, &derive_info, &input_ty);
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = ast.generics.split_for_impl();
+
+    // A type that requires a context can't be parsed correctly without one, so fails without trying any alternatives
+    let ctx_check = derive_info.ctx().map(|ctx| {
+        let message = format!(
+            "`{}` must be parsed with a context of type `{}` (see `Parse::parse_with`)",
+            ident,
+            quote::ToTokens::to_token_stream(ctx)
+        );
+        quote::quote! {
+            if __peggle_index.context::<#ctx>().is_none() {
+                return Err(peggle::ParseError::from_index(__peggle_index)
+                    .with_kind(peggle::ErrorKind::MissingContext)
+                    .with_message(#message)
+                    .with_cut());
+            }
+        }
+    });
+
+    let validate = derive_info.validate().cloned();
+    let ctx = derive_info.ctx().cloned();
+    let input = derive_info.input().cloned();
+
+    let warnings = lint::lint(&derive_info, &ident);
+
+    // Traced as the type's name along with its expression (or the expressions of its discriminants)
+    let rule = ident.to_string();
+    let pattern = match &derive_info {
+        DeriveInfo::NamedStruct(st) | DeriveInfo::UnnamedStruct(st) => st.pegex.clone(),
+        DeriveInfo::Enum(e) => e
+            .discriminants
+            .iter()
+            .map(|d| d.pegex.as_str())
+            .collect::<Vec<_>>()
+            .join(" | "),
+        DeriveInfo::Converted(_) => String::new(),
+    };
 
     let parse_tokens = match derive_info {
         DeriveInfo::NamedStruct(st) => parse::derive_named_struct(st),
         DeriveInfo::UnnamedStruct(st) => parse::derive_unnamed_struct(st),
         DeriveInfo::Enum(e) => parse::derive_enum(e),
+        DeriveInfo::Converted(c) => parse::derive_converted(c),
     };
 
+    // Values that fail the type's validation are rejected as though they failed to parse
+    let parse_tokens = match &validate {
+        Some(validate) => {
+            parse::derive_validated(parse_tokens, validate, ctx.as_ref(), input.as_ref())
+        }
+        None => parse_tokens,
+    };
+
+    proc_macro::TokenStream::from(quote::quote! {
+        #warnings
+
+        #[automatically_derived]
+        impl #impl_generics peggle::Parse<#input_ty> for #ident #ty_generics #where_clause {
+            fn parse_at<'a>(__peggle_index: peggle::Index<'a, #input_ty>) -> Result<(Self, peggle::Index<'a, #input_ty>), peggle::ParseError> {
+                #ctx_check
+                let __peggle_index = __peggle_index.descend()?;
+                __peggle_index.step()?;
+                peggle::__trace!(enter, #rule, #pattern, __peggle_index);
+                let __peggle_result: Result<(Self, peggle::Index<'a, #input_ty>), peggle::ParseError> = (|| {
+                    #parse_tokens
+                })();
+                peggle::__trace!(exit, #rule, __peggle_index, __peggle_result);
+                // Running out of budget partway through can look like any other failure (or even a match)
+                __peggle_index.check_budget()?;
+                __peggle_result.map(|(__peggle_out, __peggle_end_idx)| (__peggle_out, __peggle_end_idx.ascend()))
+            }
+        }
+    })
+}
+
+#[proc_macro_derive(Grammar, attributes(peg))]
+pub fn derive_peg_grammar(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let ast = syn::parse_macro_input!(item as syn::DeriveInput);
+    let derive_info = DeriveInfo::new(&ast);
+    let ident = &ast.ident;
+    let name = ident.to_string();
+
+    let mut generics = ast.generics.clone();
+    bound::add_grammar_bounds(&mut generics, &ast, &derive_info);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let node = describe::derive_node(&derive_info);
+
     proc_macro::TokenStream::from(quote::quote! {
         #[automatically_derived]
-        impl #impl_generics peggle::Parse for #ident #ty_generics #where_clause {
-            fn parse_at<'a>(__peggle_index: peggle::Index<'a>) -> Result<(Self, peggle::Index<'a>), peggle::ParseError> {
-                #parse_tokens
+        impl #impl_generics peggle::grammar::Grammar for #ident #ty_generics #where_clause {
+            fn rule() -> peggle::grammar::Rule {
+                peggle::grammar::Rule {
+                    name: #name.to_string(),
+                    node: #node,
+                }
             }
         }
     })
 }
+
+/// Generates a `#[derive(Parse)]` struct or enum for each rule of a grammar file, given by its path relative to the
+/// crate's manifest directory (e.g. `grammar!("src/config.peg")`).
+#[proc_macro]
+pub fn grammar(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let path = syn::parse_macro_input!(item as syn::LitStr);
+    proc_macro::TokenStream::from(grammar::expand(&path))
+}
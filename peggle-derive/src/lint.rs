@@ -0,0 +1,344 @@
+use crate::pattern::{parse_pattern, Choice, Expr, PatternOptions, Sequence};
+use crate::structure::{CollectionInfo, DeriveInfo, EnumInfo, FieldInfo};
+
+/// Checks the peggle expressions of a derived type for likely mistakes, returning a warning for each one found.
+///
+/// Alternatives that can never be reached (as an earlier alternative always matches first), and cuts that end an
+/// alternative (where they have no effect), are warned about. A repetition without an upper bound of an expression that
+/// can match empty input, or a field that refers back to the type being derived before any input is consumed (which
+/// would recurse until the stack overflows), is an error. Fields are assumed to consume input, so that only mistakes in
+/// the patterns themselves are reported.
+///
+/// Procedural macros can't emit warnings directly, so each warning is raised by using a deprecated constant. These
+/// are generated outside of the (automatically derived) implementation, within which deprecations aren't reported.
+///
+/// Warnings can therefore be made errors by denying `deprecated`:
+///
+/// ```compile_fail
+/// #![deny(deprecated)]
+///
+/// #[derive(peggle_derive::Parse)]
+/// #[peg("a|ab")]
+/// pub struct Shadowed {}
+/// ```
+///
+/// ```compile_fail
+/// #![deny(deprecated)]
+///
+/// #[derive(peggle_derive::Parse)]
+/// #[peg("a~|b")]
+/// pub struct TrailingCut {}
+/// ```
+///
+/// ```compile_fail
+/// #![deny(deprecated)]
+///
+/// #[derive(peggle_derive::Parse)]
+/// pub enum Comparison {
+///     #[peg("\\<")]
+///     Less,
+///     #[peg("\\<=")]
+///     LessEqual,
+/// }
+/// ```
+///
+/// While left recursion is always an error:
+///
+/// ```compile_fail
+/// #[derive(peggle_derive::Parse)]
+/// #[peg("<0>\\+<1>")]
+/// pub struct Sum(Box<Sum>, u32);
+/// ```
+///
+/// As is an unbounded repetition of something that can match empty input, which compiled before this was checked:
+///
+/// ```compile_fail
+/// #[derive(peggle_derive::Parse)]
+/// #[peg("(a?)*b")]
+/// pub struct Repeated {}
+/// ```
+pub fn lint(derive_info: &DeriveInfo, ident: &syn::Ident) -> proc_macro2::TokenStream {
+    let mut warnings = Vec::new();
+
+    match derive_info {
+        DeriveInfo::NamedStruct(collection) | DeriveInfo::UnnamedStruct(collection) => {
+            lint_collection(collection, ident, &mut warnings);
+        }
+        DeriveInfo::Enum(e) => {
+            for discriminant in &e.discriminants {
+                lint_collection(discriminant, ident, &mut warnings);
+            }
+            shadowed_discriminant_warnings(e, &mut warnings);
+        }
+        DeriveInfo::Converted(_) => (),
+    }
+
+    quote::quote! { #(#warnings)* }
+}
+
+/// Generates a warning with the given note, reported at `span`.
+fn warning(name: &str, note: String, span: proc_macro2::Span) -> proc_macro2::TokenStream {
+    let constant = syn::Ident::new(name, proc_macro2::Span::call_site());
+    let usage = syn::Ident::new(name, span);
+    quote::quote! {
+        const _: () = {
+            #[deprecated(note = #note)]
+            #[allow(non_upper_case_globals)]
+            const #constant: () = ();
+            #usage
+        };
+    }
+}
+
+fn lint_collection(
+    collection: &CollectionInfo,
+    ident: &syn::Ident,
+    warnings: &mut Vec<proc_macro2::TokenStream>,
+) {
+    let name = &collection.name;
+    let choice = parse_pattern(
+        &collection.pegex,
+        PatternOptions {
+            fields: true,
+            backtrack: collection.backtrack,
+        },
+    );
+    lint_choice(&choice, &format!("`{}`", name), name.span(), warnings);
+
+    for field in &collection.fields {
+        if let Some(pegex) = &field.pegex {
+            let choice = parse_pattern(
+                pegex,
+                PatternOptions {
+                    fields: false,
+                    backtrack: field.backtrack,
+                },
+            );
+            let owner = format!("field `{}` of `{}`", field.ident, name);
+            lint_choice(&choice, &owner, name.span(), warnings);
+        }
+    }
+
+    // A field parsed as the type itself before any input is consumed would recurse forever
+    for field_name in leftmost_fields(&choice) {
+        let field = collection
+            .fields
+            .iter()
+            .find(|f| f.ident == field_name)
+            .expect("field referenced in pattern");
+        if field.pegex.is_none() && field.conversion.is_none() && is_type(field, ident) {
+            panic!(
+                "field `{}` of `{}` is parsed as `{}` before any input is consumed, so parsing would recurse forever \
+                 (left recursion); rewrite the expression so that input is matched first, such as with a repetition",
+                field.ident, name, ident
+            );
+        }
+    }
+}
+
+/// Whether the type of a field (within any `Box`, `Option` or `Vec`) is `ident` or `Self`. Only a bare name refers to
+/// the type being derived for certain; a path such as `other::Expr` may name another type of the same name.
+fn is_type(field: &FieldInfo, ident: &syn::Ident) -> bool {
+    match &field.inner_ty {
+        syn::Type::Path(path) if path.qself.is_none() => path
+            .path
+            .get_ident()
+            .is_some_and(|name| name == ident || name == "Self"),
+        _ => false,
+    }
+}
+
+/// Lints every choice and repetition within a peggle expression belonging to `owner`.
+fn lint_choice(
+    choice: &Choice,
+    owner: &str,
+    span: proc_macro2::Span,
+    warnings: &mut Vec<proc_macro2::TokenStream>,
+) {
+    for (later_idx, later) in choice.iter().enumerate() {
+        let shadowing = choice[..later_idx]
+            .iter()
+            .position(|earlier| shadows(earlier, later));
+        if let Some(earlier_idx) = shadowing {
+            let note = format!(
+                "alternative {} of a choice in {} can never match, as alternative {} always matches first",
+                later_idx + 1,
+                owner,
+                earlier_idx + 1
+            );
+            warnings.push(warning("__peggle_shadowed_alternative", note, span));
+        }
+    }
+
+    for sequence in choice {
+        // Before cuts were introduced a bare `~` matched a literal tilde, which is most likely what one ending an
+        // alternative (where it has nothing to commit to) was written for
+        if let Some(Expr::Cut) = sequence.last() {
+            let note = format!(
+                "a cut (`~`) ending an alternative in {} has no effect; use `\\~` to match a literal tilde",
+                owner
+            );
+            warnings.push(warning("__peggle_trailing_cut", note, span));
+        }
+
+        for expr in sequence {
+            lint_expr(expr, owner, span, warnings);
+        }
+    }
+}
+
+fn lint_expr(
+    expr: &Expr,
+    owner: &str,
+    span: proc_macro2::Span,
+    warnings: &mut Vec<proc_macro2::TokenStream>,
+) {
+    match expr {
+        Expr::Group(choice) => lint_choice(choice, owner, span, warnings),
+        Expr::Repeat(inner, repetition) => {
+            if repetition.max == usize::MAX && nullable(std::slice::from_ref(&**inner)) {
+                panic!(
+                    "a repetition in {} repeats an expression that can match empty input, which would stop repeating \
+                     after its first empty match; make the repeated expression consume input",
+                    owner
+                );
+            }
+            lint_expr(inner, owner, span, warnings);
+        }
+        _ => (),
+    }
+}
+
+/// Whether `earlier` matches whenever `later` would, so that `later` can never be reached when tried after it: either
+/// `earlier` can't fail, or it matches only literal text that `later` begins with.
+fn shadows(earlier: &Sequence, later: &Sequence) -> bool {
+    if always_matches(earlier) {
+        return true;
+    }
+
+    let Some(literal) = literal_text(earlier) else {
+        return false;
+    };
+    let later_prefix: String = later
+        .iter()
+        .map_while(|expr| match expr {
+            Expr::Char(c) => Some(*c),
+            _ => None,
+        })
+        .collect();
+    later_prefix.starts_with(&literal)
+}
+
+/// Returns the text matched by a sequence consisting only of literal characters.
+fn literal_text(sequence: &Sequence) -> Option<String> {
+    sequence
+        .iter()
+        .map(|expr| match expr {
+            Expr::Char(c) => Some(*c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Whether a sequence matches any input, as each of its expressions is optional.
+fn always_matches(sequence: &Sequence) -> bool {
+    sequence.iter().all(|expr| match expr {
+        Expr::Repeat(_, repetition) => repetition.min == 0,
+        Expr::Group(choice) => choice.iter().any(always_matches),
+        _ => false,
+    })
+}
+
+/// Whether a sequence may match empty input. Fields are assumed to consume input.
+fn nullable(sequence: &[Expr]) -> bool {
+    sequence.iter().all(|expr| match expr {
+        Expr::Char(_) | Expr::Any | Expr::Class(_) | Expr::Field { .. } => false,
+        Expr::Cut => true,
+        Expr::Group(choice) => choice.iter().any(|sequence| nullable(sequence)),
+        Expr::Repeat(inner, repetition) => {
+            repetition.min == 0 || nullable(std::slice::from_ref(&**inner))
+        }
+    })
+}
+
+/// Returns the names of the fields that may be parsed before any input has been consumed.
+pub fn leftmost_fields(choice: &Choice) -> Vec<&str> {
+    let mut fields = Vec::new();
+    for sequence in choice {
+        for expr in sequence {
+            leftmost_expr_fields(expr, &mut fields);
+            if !nullable(std::slice::from_ref(expr)) {
+                break;
+            }
+        }
+    }
+    fields
+}
+
+fn leftmost_expr_fields<'e>(expr: &'e Expr, fields: &mut Vec<&'e str>) {
+    match expr {
+        Expr::Field { name, .. } => fields.push(name.as_str()),
+        Expr::Group(choice) => fields.extend(leftmost_fields(choice)),
+        Expr::Repeat(inner, _) => leftmost_expr_fields(inner, fields),
+        _ => (),
+    }
+}
+
+/// Returns the literal text matched by a discriminant, if its peggle expression consists of nothing else.
+fn literal_discriminant(discriminant: &CollectionInfo) -> Option<String> {
+    let choice = parse_pattern(
+        &discriminant.pegex,
+        PatternOptions {
+            fields: true,
+            backtrack: discriminant.backtrack,
+        },
+    );
+
+    match choice.as_slice() {
+        [sequence] => literal_text(sequence),
+        _ => None,
+    }
+}
+
+/// Adds a warning for each literal discriminant that can never match because an earlier literal discriminant
+/// without a `validate` check matches a prefix of its text (such as `"<"` before `"<="`).
+fn shadowed_discriminant_warnings(e: &EnumInfo, warnings: &mut Vec<proc_macro2::TokenStream>) {
+    if e.longest {
+        return;
+    }
+
+    let discriminants = &e.discriminants;
+    let literals: Vec<_> = discriminants.iter().map(literal_discriminant).collect();
+
+    for (later_idx, later) in discriminants.iter().enumerate() {
+        let Some(later_literal) = &literals[later_idx] else {
+            continue;
+        };
+
+        // A discriminant with a `validate` check may reject its match, letting later discriminants be tried
+        let shadowing =
+            discriminants[..later_idx]
+                .iter()
+                .zip(&literals)
+                .find(|(earlier, literal)| {
+                    earlier.validate.is_none()
+                        && literal
+                            .as_ref()
+                            .is_some_and(|l| later_literal.starts_with(l.as_str()))
+                });
+        let Some((earlier, _)) = shadowing else {
+            continue;
+        };
+
+        let note = format!(
+            "discriminant `{}` can never match, as the earlier discriminant `{}` matches the start of \"{}\"; \
+             reorder the discriminants or use `#[peg(longest)]`",
+            later.name, earlier.name, later_literal
+        );
+        warnings.push(warning(
+            "__peggle_shadowed_discriminant",
+            note,
+            later.name.span(),
+        ));
+    }
+}
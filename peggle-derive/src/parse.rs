@@ -1,9 +1,15 @@
 use std::collections::HashMap;
 use std::iter::Iterator;
 
-use crate::structure::{CollectionInfo, EnumInfo, FieldCardinality, FieldInfo};
-
-use peggle::Parse;
+use crate::class::{char_after, char_before, CharClass};
+use crate::pattern::{
+    first_chars, parse_pattern, sequence_text, Choice, Expr, PatternOptions, RepeatMode,
+    Repetition, Sequence,
+};
+use crate::structure::{
+    Backend, CollectionInfo, Conversion, ConvertedInfo, EnumInfo, FieldCardinality, FieldInfo,
+};
+use crate::vm;
 
 // TODO: support Box<T> types, Option<Box<T>> types and Vec<Box<T>> types
 
@@ -37,7 +43,10 @@ impl FieldRequirements {
 
     fn check_field(&self, field_name: &String, min: usize, max: usize) -> Result<(), String> {
         let Some(field_info) = self.fields.get(field_name) else {
-            return Err(format!("Field '{}' not found during check_field", field_name))
+            return Err(format!(
+                "Field '{}' not found during check_field",
+                field_name
+            ));
         };
 
         match field_info.cardinality {
@@ -82,9 +91,16 @@ impl FieldRequirements {
 
     pub fn add_field(&mut self, field_name: String, min: usize, max: usize) -> Result<(), String> {
         let Some(field_info) = self.fields.get(&field_name) else {
-            return Err(format!("Field '{}' not found", field_name))
+            return Err(format!("Field '{}' not found", field_name));
         };
 
+        if field_info.default.is_some() {
+            return Err(format!(
+                "field '{}' is skipped or given a default value, yet is referenced in regular expression",
+                field_name
+            ));
+        }
+
         if field_info.cardinality == FieldCardinality::Vec {
             return Ok(()); // Variable fields can have any count of fields
         }
@@ -140,6 +156,34 @@ impl FieldRequirements {
         Ok(())
     }
 
+    /// Records the cardinality of each field referenced within the given alternatives.
+    pub fn add_choice(&mut self, choice: &[Sequence]) -> Result<(), String> {
+        for (idx, sequence) in choice.iter().enumerate() {
+            if idx > 0 {
+                self.add_choice_split()?;
+            }
+
+            for expr in sequence {
+                self.add_expr(expr, 1, 1)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn add_expr(&mut self, expr: &Expr, min: usize, max: usize) -> Result<(), String> {
+        match expr {
+            Expr::Field { name, .. } => self.add_field(name.clone(), min, max),
+            Expr::Group(choice) => {
+                self.push_nested_expr();
+                self.add_choice(choice)?;
+                self.pop_nested_expr(min, max)
+            }
+            Expr::Repeat(inner, repetition) => self.add_expr(inner, repetition.min, repetition.max),
+            Expr::Char(_) | Expr::Any | Expr::Class(_) | Expr::Cut => Ok(()),
+        }
+    }
+
     pub fn check_final_fields(&self) -> Result<(), String> {
         assert!(self.field_min_max_stack.len() == 1);
 
@@ -148,7 +192,7 @@ impl FieldRequirements {
         let field_counter = &self.field_min_max_stack.last().expect("5").1;
         // Now check to ensure all required fields exist
         for field in self.fields.iter().filter_map(|(field_name, field)| {
-            if field.cardinality == FieldCardinality::Single {
+            if field.cardinality == FieldCardinality::Single && field.default.is_none() {
                 Some(field_name)
             } else {
                 None
@@ -168,16 +212,22 @@ impl FieldRequirements {
 
 pub fn derive_unnamed_struct(struct_info: CollectionInfo) -> proc_macro2::TokenStream {
     // First generate field declarations for struct members
-    let field_declarations = struct_info.fields.iter().map(|info| {
-        let identity = quote::format_ident!("__peggle_field_{}", &info.ident);
-        let ty = info.ty.clone();
+    let field_declarations = struct_info
+        .fields
+        .iter()
+        .filter(|info| info.default.is_none())
+        .map(|info| {
+            let identity = quote::format_ident!("__peggle_field_{}", &info.ident);
+            let ty = info.ty.clone();
 
-        match info.cardinality {
-            FieldCardinality::Single => quote::quote! { let mut #identity: Option<#ty> = None; },
-            FieldCardinality::Option => quote::quote! { let mut #identity: #ty = None; },
-            FieldCardinality::Vec => quote::quote! { let mut #identity: #ty = Vec::new(); },
-        }
-    });
+            match info.cardinality {
+                FieldCardinality::Single => {
+                    quote::quote! { let mut #identity: Option<#ty> = None; }
+                }
+                FieldCardinality::Option => quote::quote! { let mut #identity: #ty = None; },
+                FieldCardinality::Vec => quote::quote! { let mut #identity: #ty = Vec::new(); },
+            }
+        });
 
     // Then generate actual parsing code that fills in fields
     let parse_steps = derive_fields_steps(&struct_info);
@@ -185,21 +235,30 @@ pub fn derive_unnamed_struct(struct_info: CollectionInfo) -> proc_macro2::TokenS
     // Lastly, generate fields for instantiation of the struct
     let field_comma_list = struct_info.fields.iter().map(|info| {
         let identity = quote::format_ident!("__peggle_field_{}", &info.ident);
-        match info.cardinality {
-            FieldCardinality::Single => quote::quote! { #identity.unwrap(), },
+        match (&info.default, info.cardinality) {
+            (Some(default), _) => quote::quote! { #default, },
+            (None, FieldCardinality::Single) => quote::quote! { #identity.unwrap(), },
             _ => quote::quote! { #identity, },
         }
     });
 
+    let error_declaration = error_slot_declaration(!struct_info.fields.is_empty());
+    let failure_error = failure_error(
+        quote::quote! { __peggle_curr },
+        struct_info.expected.as_deref(),
+        !struct_info.fields.is_empty(),
+    );
+
     quote::quote! {
         let mut __peggle_curr = __peggle_index;
         let mut __peggle_failure = false;
+        #error_declaration
         #(#field_declarations)*
 
         #parse_steps
 
         if __peggle_failure {
-            return Err(peggle::ParseError::from_index(__peggle_curr))
+            return Err(#failure_error)
         }
 
         Ok((
@@ -213,16 +272,22 @@ pub fn derive_unnamed_struct(struct_info: CollectionInfo) -> proc_macro2::TokenS
 
 pub fn derive_named_struct(struct_info: CollectionInfo) -> proc_macro2::TokenStream {
     // First generate field declarations for struct members
-    let field_declarations = struct_info.fields.iter().map(|info| {
-        let identity = quote::format_ident!("__peggle_field_{}", &info.ident);
-        let ty = info.ty.clone();
+    let field_declarations = struct_info
+        .fields
+        .iter()
+        .filter(|info| info.default.is_none())
+        .map(|info| {
+            let identity = quote::format_ident!("__peggle_field_{}", &info.ident);
+            let ty = info.ty.clone();
 
-        match info.cardinality {
-            FieldCardinality::Single => quote::quote! { let mut #identity: Option<#ty> = None; },
-            FieldCardinality::Option => quote::quote! { let mut #identity: #ty = None; },
-            FieldCardinality::Vec => quote::quote! { let mut #identity: #ty = Vec::new(); },
-        }
-    });
+            match info.cardinality {
+                FieldCardinality::Single => {
+                    quote::quote! { let mut #identity: Option<#ty> = None; }
+                }
+                FieldCardinality::Option => quote::quote! { let mut #identity: #ty = None; },
+                FieldCardinality::Vec => quote::quote! { let mut #identity: #ty = Vec::new(); },
+            }
+        });
 
     // Then generate actual parsing code that fills in fields
     let parse_steps = derive_fields_steps(&struct_info);
@@ -231,21 +296,32 @@ pub fn derive_named_struct(struct_info: CollectionInfo) -> proc_macro2::TokenStr
     let field_comma_list = struct_info.fields.iter().map(|info| {
         let identity = quote::format_ident!("__peggle_field_{}", &info.ident);
         let original_identity = quote::format_ident!("{}", &info.ident);
-        match info.cardinality {
-            FieldCardinality::Single => quote::quote! { #original_identity: #identity.unwrap(), },
+        match (&info.default, info.cardinality) {
+            (Some(default), _) => quote::quote! { #original_identity: #default, },
+            (None, FieldCardinality::Single) => {
+                quote::quote! { #original_identity: #identity.unwrap(), }
+            }
             _ => quote::quote! { #original_identity: #identity, },
         }
     });
 
+    let error_declaration = error_slot_declaration(!struct_info.fields.is_empty());
+    let failure_error = failure_error(
+        quote::quote! { __peggle_curr },
+        struct_info.expected.as_deref(),
+        !struct_info.fields.is_empty(),
+    );
+
     quote::quote! {
         let mut __peggle_curr = __peggle_index;
         let mut __peggle_failure = false;
+        #error_declaration
         #(#field_declarations)*
 
         #parse_steps
 
         if __peggle_failure {
-            return Err(peggle::ParseError::from_index(__peggle_curr))
+            return Err(#failure_error)
         }
 
         Ok((
@@ -258,850 +334,1411 @@ pub fn derive_named_struct(struct_info: CollectionInfo) -> proc_macro2::TokenStr
 }
 
 pub fn derive_enum(e: EnumInfo) -> proc_macro2::TokenStream {
-    derive_enum_steps(e.name, e.discriminants)
+    derive_enum_steps(
+        e.name,
+        e.expected,
+        e.longest,
+        e.dispatch,
+        e.input,
+        e.discriminants,
+    )
 }
 
 fn derive_enum_steps(
     enum_name: syn::Ident,
+    expected: Option<String>,
+    longest: bool,
+    dispatch: bool,
+    input: Option<syn::Type>,
     discriminants: Vec<CollectionInfo>,
 ) -> proc_macro2::TokenStream {
     let mut expr_tokens = Vec::new();
+    let (dispatch_declaration, dispatch_conditions) = if dispatch {
+        dispatch_table(&discriminants)
+    } else {
+        (quote::quote! {}, vec![None; discriminants.len()])
+    };
 
-    for discriminant in discriminants {
+    // Labeled errors from any of the discriminants are collected in a single slot for the enum
+    let has_error_slot = discriminants
+        .iter()
+        .any(|d| !d.fields.is_empty() || d.expected.is_some() || d.validate.is_some());
+    let discriminants_recover: Vec<bool> =
+        discriminants.iter().map(|d| d.recover.is_some()).collect();
+
+    let discriminant_count = discriminants.len();
+    for (idx, discriminant) in discriminants.into_iter().enumerate() {
         let discriminant_name = &discriminant.name;
 
-        // First generate field declarations for struct members
-        let field_declarations = discriminant.fields.iter().map(|info| {
-            let identity = quote::format_ident!("__peggle_field_{}", &info.ident);
-            let ty = info.ty.clone();
+        // A discriminant that fails to match is backtracked from to try the next
+        let trace_backtrack = |index: proc_macro2::TokenStream| {
+            if idx + 1 == discriminant_count {
+                return quote::quote! {};
+            }
+            let text = &discriminant.pegex;
+            quote::quote! { peggle::__trace!(backtrack, #text, #index); }
+        };
 
-            match info.cardinality {
-                FieldCardinality::Single => {
-                    quote::quote! { let mut #identity: Option<#ty> = None; }
+        // First generate field declarations for struct members
+        let field_declarations = discriminant
+            .fields
+            .iter()
+            .filter(|info| info.default.is_none())
+            .map(|info| {
+                let identity = quote::format_ident!("__peggle_field_{}", &info.ident);
+                let ty = info.ty.clone();
+
+                match info.cardinality {
+                    FieldCardinality::Single => {
+                        quote::quote! { let mut #identity: Option<#ty> = None; }
+                    }
+                    FieldCardinality::Option => quote::quote! { let mut #identity: #ty = None; },
+                    FieldCardinality::Vec => quote::quote! { let mut #identity: #ty = Vec::new(); },
                 }
-                FieldCardinality::Option => quote::quote! { let mut #identity: #ty = None; },
-                FieldCardinality::Vec => quote::quote! { let mut #identity: #ty = Vec::new(); },
-            }
-        });
+            });
 
         // Then generate actual parsing code that fills in fields
         let parse_steps = derive_fields_steps(&discriminant);
 
         // Lastly, generate fields for instantiation of the struct
-        let field_comma_list = discriminant.fields.iter().map(|info| {
+        let field_values = discriminant.fields.iter().map(|info| {
             let identity = quote::format_ident!("__peggle_field_{}", &info.ident);
-            match info.cardinality {
-                FieldCardinality::Single => quote::quote! { #identity.unwrap(), },
-                _ => quote::quote! { #identity, },
+            match (&info.default, info.cardinality) {
+                (Some(default), _) => quote::quote! { #default },
+                (None, FieldCardinality::Single) => quote::quote! { #identity.unwrap() },
+                _ => quote::quote! { #identity },
             }
         });
+        let enum_fields = discriminant_fields(&discriminant, field_values);
+
+        let record_expected = match &discriminant.expected {
+            Some(expected) => quote::quote! {
+                let __peggle_err = peggle::ParseError::from_index(__peggle_index).with_expected(#expected);
+                __peggle_error = Some(match __peggle_error.take() {
+                    Some(__peggle_prev) => __peggle_prev.furthest(__peggle_err),
+                    None => __peggle_err,
+                });
+            },
+            None => quote::quote! {},
+        };
 
-        let enum_fields = if field_comma_list.len() > 0 {
-            quote::quote! { (#(#field_comma_list)*) }
+        // With `longest`, a match is set aside (along with any errors recovered from within it) until the rest of the
+        // discriminants have been tried, replacing any shorter match found earlier
+        let accept = if longest {
+            let split_recovered = if discriminant.fields.is_empty() {
+                quote::quote! { Vec::new() }
+            } else {
+                quote::quote! { __peggle_index.split_recovered(__peggle_recovered) }
+            };
+            quote::quote! {{
+                let __peggle_errors = #split_recovered;
+                let __peggle_shorter = match &__peggle_best {
+                    Some(__peggle_prev) => peggle::Input::len(__peggle_prev.1.remaining) > peggle::Input::len(__peggle_matched.1.remaining),
+                    None => true,
+                };
+                if __peggle_shorter {
+                    __peggle_best = Some((__peggle_matched.0, __peggle_matched.1, __peggle_errors));
+                }
+                break '__choice_lifetime_0;
+            }}
         } else {
-            quote::quote! {}
+            quote::quote! { return Ok(__peggle_matched) }
+        };
+
+        // A discriminant whose value is rejected by its validation records why, then falls through to the next one
+        let accept_matched = match &discriminant.validate {
+            Some(validate) => {
+                let validation = validation_tokens(
+                    validate,
+                    quote::quote! { &__peggle_matched.0 },
+                    discriminant.ctx.as_ref(),
+                    &quote::quote! { __peggle_index },
+                );
+                quote::quote! {
+                    match #validation {
+                        Ok(()) => #accept,
+                        Err(__peggle_err) => {
+                            __peggle_error = Some(match __peggle_error.take() {
+                                Some(__peggle_prev) => __peggle_prev.furthest(__peggle_err),
+                                None => __peggle_err,
+                            });
+                        }
+                    }
+                }
+            }
+            None => accept,
+        };
+
+        // Errors recovered from within a discriminant that ultimately fails to match are discarded
+        let (count_recovered, discard_recovered) = if discriminant.fields.is_empty() {
+            (quote::quote! {}, quote::quote! {})
+        } else {
+            (
+                quote::quote! { let __peggle_recovered = __peggle_index.recovered_count(); },
+                quote::quote! { __peggle_index.truncate_recovered(__peggle_recovered); },
+            )
+        };
+
+        let Some(recover) = &discriminant.recover else {
+            let trace_curr = trace_backtrack(quote::quote! { __peggle_curr });
+            expr_tokens.push(quote::quote! {
+                '__choice_lifetime_0: {
+                    __peggle_curr = __peggle_index;
+                    __peggle_failure = false;
+                    #count_recovered
+                    #(#field_declarations)*
+
+                    #parse_steps
+
+                    if !__peggle_failure {
+                        let __peggle_matched = (#enum_name::#discriminant_name #enum_fields, __peggle_curr);
+                        #accept_matched
+                    }
+
+                    #trace_curr
+                    #discard_recovered
+                    #record_expected
+                }
+            });
+            continue;
         };
 
+        // A recovering discriminant is matched within a closure, so that errors returned after a cut can be
+        // recovered from by skipping over the synchronization pattern instead. Fields are taken out of their
+        // variables on success, leaving them available to fill in a partial result otherwise.
+        let recover_steps = derive_single_field_steps(
+            recover,
+            false,
+            discriminant.backend,
+            discriminant.input.as_ref(),
+        );
+        let (input_generics, input_ty) = input_tokens(discriminant.input.as_ref());
+
+        let take_fields = discriminant.fields.iter().map(|info| {
+            let identity = quote::format_ident!("__peggle_field_{}", &info.ident);
+            match (&info.default, info.cardinality) {
+                (Some(default), _) => quote::quote! { #default },
+                (None, FieldCardinality::Single) => quote::quote! { #identity.take().unwrap() },
+                _ => quote::quote! { std::mem::take(&mut #identity) },
+            }
+        });
+        let recovered_fields = discriminant.fields.iter().map(|info| {
+            let identity = quote::format_ident!("__peggle_field_{}", &info.ident);
+            match (&info.default, info.cardinality) {
+                (Some(default), _) => quote::quote! { #default },
+                (None, FieldCardinality::Single) => quote::quote! { #identity.unwrap_or_default() },
+                _ => quote::quote! { #identity },
+            }
+        });
+        let trace_index = trace_backtrack(quote::quote! { __peggle_index });
+        let taken_fields = discriminant_fields(&discriminant, take_fields);
+        let recovered_fields = discriminant_fields(&discriminant, recovered_fields);
+
         expr_tokens.push(quote::quote! {
             '__choice_lifetime_0: {
-                __peggle_curr = __peggle_index;
-                __peggle_failure = false;
+                #[inline]
+                fn __peggle_recover #input_generics (__peggle_index: peggle::Index<'a, #input_ty>) -> Result<(&'a #input_ty, peggle::Index<'a, #input_ty>), peggle::ParseError> {
+                    let mut __peggle_curr = __peggle_index;
+                    let mut __peggle_failure = false;
+                    #recover_steps
+                }
+
+                #count_recovered
                 #(#field_declarations)*
 
-                #parse_steps
+                let __peggle_result = (|| -> Result<Option<(Self, peggle::Index<'a, #input_ty>)>, peggle::ParseError> {
+                    let mut __peggle_curr = __peggle_index;
+                    let mut __peggle_failure = false;
 
-                if !__peggle_failure {
-                    return Ok((#enum_name::#discriminant_name #enum_fields, __peggle_curr))
+                    #parse_steps
+
+                    if __peggle_failure {
+                        Ok(None)
+                    } else {
+                        Ok(Some((#enum_name::#discriminant_name #taken_fields, __peggle_curr)))
+                    }
+                })();
+
+                match __peggle_result {
+                    Ok(Some(__peggle_matched)) => { #accept_matched }
+                    Ok(None) => (),
+                    Err(__peggle_err) if __peggle_index.is_recovering() => match __peggle_recover(__peggle_index) {
+                        Ok((__peggle_skipped, __peggle_new_index)) if !peggle::Input::is_empty(__peggle_skipped) => {
+                            __peggle_index.record_error(__peggle_err);
+                            return Ok((#enum_name::#discriminant_name #recovered_fields, __peggle_new_index))
+                        }
+                        _ => return Err(__peggle_err),
+                    },
+                    Err(__peggle_err) => return Err(__peggle_err),
                 }
+
+                #trace_index
+                #discard_recovered
+                #record_expected
             }
         });
     }
 
+    // Discriminants that can't match the next character of input are skipped
+    let expr_tokens = expr_tokens.into_iter().zip(dispatch_conditions).map(
+        |(tokens, condition)| match condition {
+            Some(condition) => quote::quote! { if #condition { #tokens } },
+            None => tokens,
+        },
+    );
+
+    let error_declaration = error_slot_declaration(has_error_slot);
+    let failure_error = failure_error(
+        quote::quote! { __peggle_index },
+        expected.as_deref(),
+        has_error_slot,
+    );
+
+    // Recovering discriminants declare their own state within their closure
+    let state_declarations = if discriminants_recover.iter().all(|r| *r) {
+        quote::quote! {}
+    } else {
+        quote::quote! {
+            let mut __peggle_curr;
+            let mut __peggle_failure;
+        }
+    };
+
+    let (best_declaration, return_best) = if longest {
+        let (_, input_ty) = input_tokens(input.as_ref());
+        (
+            quote::quote! {
+                let mut __peggle_best: Option<(Self, peggle::Index<'a, #input_ty>, Vec<peggle::ParseError>)> = None;
+            },
+            quote::quote! {
+                if let Some((__peggle_out, __peggle_end_idx, __peggle_errors)) = __peggle_best {
+                    for __peggle_err in __peggle_errors {
+                        __peggle_index.record_error(__peggle_err);
+                    }
+                    return Ok((__peggle_out, __peggle_end_idx))
+                }
+            },
+        )
+    } else {
+        (quote::quote! {}, quote::quote! {})
+    };
+
     quote::quote! {
-        let mut __peggle_curr;
-        let mut __peggle_failure;
+        #state_declarations
+        #error_declaration
+        #best_declaration
+        #dispatch_declaration
 
         '__expression_lifetime_0: {
             #(#expr_tokens)*
         }
 
-        Err(peggle::ParseError::from_index(__peggle_index))
+        #return_best
+        Err(#failure_error)
+    }
+}
+
+/// Generates a table of which discriminants may match, indexed by the next character of input, so that those that
+/// can't be skipped without being tried.
+///
+/// The table is a bitmask of the discriminants that may match (`__peggle_viable`), computed by a `match` on the next
+/// character. Alongside its declaration, the condition under which each discriminant is tried is returned (or
+/// `None` if it must always be tried, such as when it begins with a field or has an `expected` label to report).
+fn dispatch_table(
+    discriminants: &[CollectionInfo],
+) -> (
+    proc_macro2::TokenStream,
+    Vec<Option<proc_macro2::TokenStream>>,
+) {
+    let firsts: Vec<Option<CharClass>> = discriminants
+        .iter()
+        .map(|discriminant| {
+            if discriminant.expected.is_some() {
+                return None;
+            }
+
+            first_chars(&parse_pattern(
+                &discriminant.pegex,
+                PatternOptions {
+                    fields: true,
+                    backtrack: discriminant.backtrack,
+                },
+            ))
+        })
+        .collect();
+
+    if discriminants.len() < 2 || firsts.iter().all(Option::is_none) {
+        return (quote::quote! {}, vec![None; discriminants.len()]);
+    }
+
+    let words = discriminants.len().div_ceil(64);
+    let mut always = vec![0u64; words];
+    for (idx, first) in firsts.iter().enumerate() {
+        if first.is_none() {
+            always[idx / 64] |= 1 << (idx % 64);
+        }
+    }
+
+    // Split the characters into intervals within which the same discriminants may match
+    let mut boundaries = std::collections::BTreeSet::from(['\0']);
+    for class in firsts.iter().flatten() {
+        for &(start, end) in class.ranges() {
+            boundaries.insert(start);
+            if let Some(after) = char_after(end) {
+                boundaries.insert(after);
+            }
+        }
+    }
+
+    let boundaries: Vec<char> = boundaries.into_iter().collect();
+    let mut arms: std::collections::BTreeMap<Vec<u64>, CharClass> =
+        std::collections::BTreeMap::new();
+    for (idx, &start) in boundaries.iter().enumerate() {
+        let end = boundaries
+            .get(idx + 1)
+            .map_or(char::MAX, |&next| char_before(next));
+
+        let mut viable = always.clone();
+        for (idx, first) in firsts.iter().enumerate() {
+            if first.as_ref().is_some_and(|class| class.contains(start)) {
+                viable[idx / 64] |= 1 << (idx % 64);
+            }
+        }
+
+        if viable != always {
+            arms.entry(viable).or_default().add_range(start, end);
+        }
+    }
+
+    let next = quote::format_ident!("__peggle_next");
+    let arms = arms.iter().map(|(viable, class)| {
+        let condition = class.to_tokens(&next);
+        quote::quote! { Some(#next) if #condition => [#(#viable),*], }
+    });
+
+    let declaration = quote::quote! {
+        let __peggle_viable: [u64; #words] = match __peggle_index.peek() {
+            #(#arms)*
+            _ => [#(#always),*],
+        };
+    };
+
+    let conditions = firsts
+        .iter()
+        .enumerate()
+        .map(|(idx, first)| {
+            first.as_ref().map(|_| {
+                let word = idx / 64;
+                let bit = 1u64 << (idx % 64);
+                quote::quote! { __peggle_viable[#word] & #bit != 0 }
+            })
+        })
+        .collect();
+
+    (declaration, conditions)
+}
+
+/// Generates the fields used to instantiate a discriminant from the value given for each of its fields.
+fn discriminant_fields(
+    discriminant: &CollectionInfo,
+    values: impl Iterator<Item = proc_macro2::TokenStream>,
+) -> proc_macro2::TokenStream {
+    if discriminant.named {
+        let names = discriminant
+            .fields
+            .iter()
+            .map(|info| quote::format_ident!("{}", &info.ident));
+        quote::quote! { { #(#names: #values,)* } }
+    } else if discriminant.fields.is_empty() {
+        quote::quote! {}
+    } else {
+        quote::quote! { (#(#values,)*) }
+    }
+}
+
+/// Declares the slot in which the furthest labeled error encountered while parsing is kept.
+fn error_slot_declaration(has_error_slot: bool) -> proc_macro2::TokenStream {
+    if has_error_slot {
+        quote::quote! { let mut __peggle_error: Option<peggle::ParseError> = None; }
+    } else {
+        quote::quote! {}
+    }
+}
+
+/// Generates the error returned when a struct or enum fails to parse.
+///
+/// A type labeled with `expected` reports that label at the position it started parsing from, unless a labeled error
+/// from within it occurred further along. Otherwise, the furthest labeled error is preferred over an unlabeled one at
+/// the failing position `index`.
+fn failure_error(
+    index: proc_macro2::TokenStream,
+    expected: Option<&str>,
+    has_error_slot: bool,
+) -> proc_macro2::TokenStream {
+    match (expected, has_error_slot) {
+        (Some(expected), true) => quote::quote! {{
+            let __peggle_err = peggle::ParseError::from_index(__peggle_index).with_expected(#expected);
+            match __peggle_error {
+                Some(__peggle_inner) => __peggle_inner.furthest(__peggle_err),
+                None => __peggle_err,
+            }
+        }},
+        (Some(expected), false) => quote::quote! {
+            peggle::ParseError::from_index(__peggle_index).with_expected(#expected)
+        },
+        (None, true) => quote::quote! {{
+            let __peggle_err = peggle::ParseError::from_index(#index);
+            match __peggle_error {
+                Some(__peggle_inner) => __peggle_err.furthest(__peggle_inner),
+                None => __peggle_err,
+            }
+        }},
+        (None, false) => quote::quote! { peggle::ParseError::from_index(#index) },
+    }
+}
+
+fn derive_single_field_fns(
+    field: &FieldInfo,
+    collection: &CollectionInfo,
+) -> proc_macro2::TokenStream {
+    let (input_generics, input_ty) = input_tokens(collection.input.as_ref());
+    let Some(recover) = &field.recover else {
+        return derive_field_parse_fns(
+            field,
+            quote::format_ident!("__peggle_parse_{}", field.ident),
+            collection,
+        );
+    };
+
+    // The field is parsed by an inner attempt function; should that fail in a way that's committed (rather than
+    // ordinary backtracking) while errors are being recovered from, the synchronization pattern is matched from the
+    // field's start and the field takes its default value instead. The synchronization pattern must consume input
+    // for recovery to succeed, so that repeated fields can't loop.
+    let attempt_fn = quote::format_ident!("__peggle_attempt_{}", field.ident);
+    let recover_fn = quote::format_ident!("__peggle_recover_{}", field.ident);
+    let field_fn = quote::format_ident!("__peggle_parse_{}", field.ident);
+    let field_ty = &field.inner_ty;
+
+    let attempt_fn_tokens = derive_field_parse_fns(field, attempt_fn.clone(), collection);
+    let recover_fn_tokens = derive_single_field_steps(
+        recover,
+        false,
+        collection.backend,
+        collection.input.as_ref(),
+    );
+
+    quote::quote! {
+        #attempt_fn_tokens
+
+        #[inline]
+        fn #recover_fn #input_generics (__peggle_index: peggle::Index<'a, #input_ty>) -> Result<(&'a #input_ty, peggle::Index<'a, #input_ty>), peggle::ParseError> {
+            let mut __peggle_curr = __peggle_index;
+            let mut __peggle_failure = false;
+            #recover_fn_tokens
+        }
+
+        let #field_fn = |__peggle_index: peggle::Index<'a, #input_ty>| -> Result<(#field_ty, peggle::Index<'a, #input_ty>), peggle::ParseError> {
+            match #attempt_fn(__peggle_index) {
+                Err(__peggle_err) if __peggle_index.recovers_from(&__peggle_err) => match #recover_fn(__peggle_index) {
+                    Ok((__peggle_skipped, __peggle_new_index)) if !peggle::Input::is_empty(__peggle_skipped) => {
+                        __peggle_index.record_error(__peggle_err);
+                        Ok((<#field_ty as Default>::default(), __peggle_new_index))
+                    }
+                    _ => Err(__peggle_err),
+                },
+                __peggle_result => __peggle_result,
+            }
+        };
     }
 }
 
-fn derive_single_field_fns(field: &FieldInfo) -> proc_macro2::TokenStream {
+/// Generates the closure (named `field_fn`) that parses a single instance of a field, along with the function that
+/// restricts its input if the field has its own peggle expression.
+///
+/// Parsing is done within a closure rather than a nested function so that the field's type may refer to the generic
+/// parameters of the type being derived.
+fn derive_field_parse_fns(
+    field: &FieldInfo,
+    field_fn: syn::Ident,
+    collection: &CollectionInfo,
+) -> proc_macro2::TokenStream {
+    let (input_generics, input_ty) = input_tokens(collection.input.as_ref());
+    let field_ty = &field.inner_ty;
+
     if let Some(pegex) = &field.pegex {
         let restrict_fn = quote::format_ident!("__peggle_restrict_{}", field.ident);
-        let field_fn = quote::format_ident!("__peggle_parse_{}", field.ident);
-        let field_ty = &field.inner_ty;
-
-        let restrict_fn_tokens = derive_single_field_steps(pegex);
+        let value_tokens = field_value_tokens(
+            field,
+            collection,
+            quote::quote! { __peggle_restricted_index },
+        );
+
+        let restrict_fn_tokens = derive_single_field_steps(
+            pegex,
+            field.backtrack,
+            collection.backend,
+            collection.input.as_ref(),
+        );
 
         quote::quote! {
             #[inline]
-            fn #restrict_fn<'a>(__peggle_index: peggle::Index<'a>) -> Result<(&'a str, peggle::Index<'a>), peggle::ParseError> {
-                let mut __peggle_curr: peggle::Index<'_>;
-                let mut __peggle_failure: bool;
+            fn #restrict_fn #input_generics (__peggle_index: peggle::Index<'a, #input_ty>) -> Result<(&'a #input_ty, peggle::Index<'a, #input_ty>), peggle::ParseError> {
+                let mut __peggle_curr = __peggle_index;
+                let mut __peggle_failure = false;
                 #restrict_fn_tokens
             }
 
-            #[inline]
-            fn #field_fn<'a>(__peggle_index: peggle::Index<'a>) -> Result<(#field_ty, peggle::Index<'a>), peggle::ParseError> {
+            let #field_fn = |__peggle_index: peggle::Index<'a, #input_ty>| -> Result<(#field_ty, peggle::Index<'a, #input_ty>), peggle::ParseError> {
                 let (__peggle_restricted_str, __peggle_new_index) = #restrict_fn(__peggle_index)?;
 
-                let __peggle_restricted_index = peggle::Index {
-                    remaining: __peggle_restricted_str,
-                    lineno: __peggle_index.lineno,
-                    colno: __peggle_index.colno,
-                };
-                let (__peggle_out, __peggle_end_idx) = #field_ty::parse_at(__peggle_restricted_index)?;
+                let __peggle_restricted_index = __peggle_index.with_remaining(__peggle_restricted_str);
+                #value_tokens
 
-                if __peggle_end_idx.remaining.is_empty() {
+                if peggle::Input::is_empty(__peggle_end_idx.remaining) {
                     Ok((__peggle_out, __peggle_new_index))
                 } else {
                     // This may happen if the restriction regex is not a proper subset of the type's input parsing
                     Err(peggle::ParseError::from_index(__peggle_new_index))
                 }
-            }
+            };
         }
     } else {
-        let field_fn = quote::format_ident!("__peggle_parse_{}", field.ident);
-        let field_ty = &field.inner_ty;
+        let value_tokens = field_value_tokens(field, collection, quote::quote! { __peggle_index });
 
         quote::quote! {
-            #[inline]
-            fn #field_fn<'a>(__peggle_index: peggle::Index<'a>) -> Result<(#field_ty, peggle::Index<'a>), peggle::ParseError> {
-                #field_ty::parse_at(__peggle_index)
-            }
+            let #field_fn = |__peggle_index: peggle::Index<'a, #input_ty>| -> Result<(#field_ty, peggle::Index<'a, #input_ty>), peggle::ParseError> {
+                #value_tokens
+                Ok((__peggle_out, __peggle_end_idx))
+            };
         }
     }
 }
 
-fn derive_single_field_steps(pegex: &str) -> proc_macro2::TokenStream {
-    let mut index = peggle::Index::new(pegex);
-    let mut nested_expressions = vec![vec![Vec::new()]];
-
-    while let Some(c) = index.next() {
-        match c {
-            '(' => nested_expressions.push(vec![Vec::new()]),
-            ')' => coalesce_topmost_expression(&mut index, &mut nested_expressions, None),
-            '|' => nested_expressions.last_mut().expect("6").push(Vec::new()), // Add another option to the current expression
-            '[' => match_one_of(&mut index, &mut nested_expressions),
-            '<' | '>' => panic!("carrot brackets reserved for member fields, which are not allowed within a field derive. Use \"[<]\" and \"[>]\" to specify literal carrot brackets"),
-            ']' | '*' | '+' | '?' | '{' | '}' => panic!("unexpected token {} at peggle index {}", c, index.colno),
-            '^' | '$' => panic!("{} regex symbol not implemented (prepend a backslash to use the literal `{}` character)", c, c),
-            '.' => match_any_character(&mut index, &mut nested_expressions),
-            _ => match_character(c, &mut index, &mut nested_expressions),
-        }
-    }
+/// Generates statements that parse a single value of a field from `index`, applying any conversion and validation,
+/// and bind it to `__peggle_out` (along with the index following it to `__peggle_end_idx`) or else return an error.
+fn field_value_tokens(
+    field: &FieldInfo,
+    collection: &CollectionInfo,
+    index: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let field_ty = &field.inner_ty;
+    let (_, input_ty) = input_tokens(collection.input.as_ref());
+    let parse_tokens = value_tokens(
+        field.conversion.as_ref(),
+        &quote::quote! { #field_ty },
+        &index,
+        &input_ty,
+    );
+
+    let validate_tokens = field.validate.as_ref().map(|validate| {
+        let validation = validation_tokens(
+            validate,
+            quote::quote! { &__peggle_out },
+            collection.ctx.as_ref(),
+            &index,
+        );
+        quote::quote! { #validation?; }
+    });
 
-    if nested_expressions.len() > 1 {
-        panic!("missing close parantheses in peggle expression");
+    quote::quote! {
+        #parse_tokens
+        #validate_tokens
     }
+}
 
-    let choice_lifetime = id_to_lifetime(quote::format_ident!("__choice_lifetime_1"));
-    let expr_tokens = nested_expressions
-        .last_mut()
-        .expect("missing last expr_token")
-        .iter_mut()
-        .map(|choice_tokens| {
-            quote::quote! {
-                __peggle_curr = __peggle_index;
-                __peggle_failure = false;
-                #choice_lifetime: {
-                    #(#choice_tokens)*
+/// Generates statements that parse a value of type `ty` from `index` (converting it from another parsed type if
+/// need be), binding it to `__peggle_out` and the index following it to `__peggle_end_idx`, or else return an error.
+pub fn value_tokens(
+    conversion: Option<&Conversion>,
+    ty: &proc_macro2::TokenStream,
+    index: &proc_macro2::TokenStream,
+    input_ty: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    match conversion {
+        None => quote::quote! {
+            let (__peggle_out, __peggle_end_idx) = <#ty as peggle::Parse<#input_ty>>::parse_at(#index)?;
+        },
+        Some(Conversion::TryFrom(raw_ty)) => quote::quote! {
+            let (__peggle_raw, __peggle_end_idx) = <#raw_ty as peggle::Parse<#input_ty>>::parse_at(#index)?;
+            let __peggle_out = match <#ty as ::std::convert::TryFrom<#raw_ty>>::try_from(__peggle_raw) {
+                Ok(__peggle_out) => __peggle_out,
+                Err(__peggle_err) => {
+                    return Err(peggle::ParseError::from_index(#index).with_message(::std::string::ToString::to_string(&__peggle_err)))
                 }
+            };
+        },
+        Some(Conversion::Map(map)) => quote::quote! {
+            // The type parsed is inferred from the argument of the mapping function
+            let (__peggle_raw, __peggle_end_idx) = <_ as peggle::Parse<#input_ty>>::parse_at(#index)?;
+            let __peggle_out: #ty = (#map)(__peggle_raw);
+        },
+    }
+}
 
-                if !__peggle_failure {
-                    break '__expression_lifetime_1
-                }
+/// Generates an expression that calls the given validation function with `value` (and the parse context, if the type
+/// requires one), evaluating to a `ParseError` at `index` carrying the function's error message if validation fails.
+pub fn validation_tokens(
+    validate: &syn::Expr,
+    value: proc_macro2::TokenStream,
+    ctx: Option<&syn::Type>,
+    index: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let ctx_arg = ctx.map(|ctx| {
+        quote::quote! { , #index.context::<#ctx>().expect("parse context is checked upon entry") }
+    });
+
+    quote::quote! {
+        (#validate)(#value #ctx_arg).map_err(|__peggle_msg| {
+            peggle::ParseError::from_index(#index).with_message(::std::string::ToString::to_string(&__peggle_msg))
+        })
+    }
+}
+
+/// Generates the body of `parse_at` for a type that is parsed as another type and converted from it.
+pub fn derive_converted(converted: ConvertedInfo) -> proc_macro2::TokenStream {
+    let (_, input_ty) = input_tokens(converted.input.as_ref());
+    let parse_tokens = value_tokens(
+        Some(&converted.conversion),
+        &quote::quote! { Self },
+        &quote::quote! { __peggle_index },
+        &input_ty,
+    );
+
+    let Some(expected) = &converted.expected else {
+        return quote::quote! {
+            #parse_tokens
+            Ok((__peggle_out, __peggle_end_idx))
+        };
+    };
+
+    // Errors that don't describe what went wrong are replaced with the type's label
+    quote::quote! {
+        let __peggle_result: Result<(Self, peggle::Index<'a, #input_ty>), peggle::ParseError> = (|| {
+            #parse_tokens
+            Ok((__peggle_out, __peggle_end_idx))
+        })();
+
+        __peggle_result.map_err(|__peggle_err| {
+            if __peggle_err.cut || __peggle_err.is_descriptive() {
+                __peggle_err
+            } else {
+                peggle::ParseError::from_index(__peggle_index).with_expected(#expected)
             }
-        });
+        })
+    }
+}
+
+/// Wraps the body of `parse_at` such that each value parsed is checked with the given validation function.
+pub fn derive_validated(
+    parse_tokens: proc_macro2::TokenStream,
+    validate: &syn::Expr,
+    ctx: Option<&syn::Type>,
+    input: Option<&syn::Type>,
+) -> proc_macro2::TokenStream {
+    let (_, input_ty) = input_tokens(input);
+    let validation = validation_tokens(
+        validate,
+        quote::quote! { &__peggle_out },
+        ctx,
+        &quote::quote! { __peggle_index },
+    );
 
     quote::quote! {
-        '__expression_lifetime_1: {
-            #(#expr_tokens)*
-        }
+        let __peggle_result: Result<(Self, peggle::Index<'a, #input_ty>), peggle::ParseError> = (|| {
+            #parse_tokens
+        })();
+
+        let (__peggle_out, __peggle_end_idx) = __peggle_result?;
+        #validation?;
+        Ok((__peggle_out, __peggle_end_idx))
+    }
+}
+
+fn derive_single_field_steps(
+    pegex: &str,
+    backtrack: bool,
+    backend: Backend,
+    input: Option<&syn::Type>,
+) -> proc_macro2::TokenStream {
+    let choice = parse_pattern(
+        pegex,
+        PatternOptions {
+            fields: false,
+            backtrack,
+        },
+    );
+
+    let pattern_tokens = match backend {
+        Backend::Inline => Generator::new(&[]).expression(&choice),
+        Backend::Vm => vm_steps(&choice, &[], input),
+    };
+
+    quote::quote! {
+        #pattern_tokens
 
         if __peggle_failure {
             Err(peggle::ParseError::from_index(__peggle_curr))
         } else {
-            let __peggle_restricted_str = &__peggle_index.remaining.get(..__peggle_index.remaining.len() - __peggle_curr.remaining.len()).ok_or(peggle::ParseError::from_index(__peggle_curr))?;
+            let __peggle_consumed = peggle::Input::len(__peggle_index.remaining) - peggle::Input::len(__peggle_curr.remaining);
+            let __peggle_restricted_str = peggle::Input::prefix(__peggle_index.remaining, __peggle_consumed).ok_or(peggle::ParseError::from_index(__peggle_curr))?;
             Ok((__peggle_restricted_str, __peggle_curr))
         }
     }
 }
 
 fn derive_fields_steps(collection: &CollectionInfo) -> proc_macro2::TokenStream {
-    let mut index = peggle::Index::new(collection.pegex.as_str());
-    let mut nested_expressions = vec![vec![Vec::new()]]; // TODO: a triple-Vec is GNARLY--fix...
-    let mut requirements = FieldRequirements::new(collection);
+    let choice = parse_pattern(
+        &collection.pegex,
+        PatternOptions {
+            fields: true,
+            backtrack: collection.backtrack,
+        },
+    );
 
-    let mut field_steps = Vec::new();
-    for field in &collection.fields {
-        field_steps.push(derive_single_field_fns(field));
+    let mut requirements = FieldRequirements::new(collection);
+    if let Err(e) = requirements.add_choice(&choice) {
+        panic!("{}", e);
     }
-
-    while let Some(c) = index.next() {
-        match c {
-            '(' => {
-                nested_expressions.push(vec![Vec::new()]); // Add another nested expression on the stack
-                requirements.push_nested_expr();
-            }
-            ')' => coalesce_topmost_expression(&mut index, &mut nested_expressions, Some(&mut requirements)),
-            '|' => {
-                // Add another option to the current expression
-                nested_expressions.last_mut().expect("6").push(Vec::new());
-                requirements.add_choice_split().expect("failed to add choice split");
-            }
-            '<' => match_field(&mut index, &mut requirements, &mut nested_expressions),
-            '[' => match_one_of(&mut index, &mut nested_expressions),
-            '>' | ']' | '*' | '+' | '?' | '{' | '}' => panic!("unexpected token {} at peggle index {}", c, index.colno),
-            '^' | '$' => panic!("{} regex symbol not implemented (prepend a backslash to use the literal `{}` character)", c, c),
-            '.' => match_any_character(&mut index, &mut nested_expressions),
-            '\\' => match_backslash_class(&mut index, &mut nested_expressions),
-            _ => match_character(c, &mut index, &mut nested_expressions),
-        }
+    if let Err(e) = requirements.check_final_fields() {
+        panic!("{}", e);
     }
 
-    if nested_expressions.len() > 1 {
-        panic!("missing close parantheses in peggle expression");
+    // Fields that are skipped or given a default value aren't parsed
+    let mut field_steps = Vec::new();
+    for field in collection.fields.iter().filter(|f| f.default.is_none()) {
+        field_steps.push(derive_single_field_fns(field, collection));
     }
 
-    requirements.check_final_fields().expect("7");
-
-    let choice_lifetime = id_to_lifetime(quote::format_ident!("__choice_lifetime_1"));
-    let expr_tokens = nested_expressions
-        .last_mut()
-        .expect("missing last expr_token")
-        .iter_mut()
-        .map(|choice_tokens| {
-            quote::quote! {
-                __peggle_curr = __peggle_index;
-                __peggle_failure = false;
-                #choice_lifetime: {
-                    #(#choice_tokens)*
-                }
-
-                if !__peggle_failure {
-                    break '__expression_lifetime_1
-                }
-            }
-        });
+    let pattern_tokens = match collection.backend {
+        Backend::Inline => Generator::new(&collection.fields).expression(&choice),
+        Backend::Vm => vm_steps(&choice, &collection.fields, collection.input.as_ref()),
+    };
 
     quote::quote! {
         #(#field_steps)*
 
-        '__expression_lifetime_1: {
-            #(#expr_tokens)*
-        }
+        #pattern_tokens
     }
 }
 
-fn match_field(
-    index: &mut peggle::Index,
-    requirements: &mut FieldRequirements,
-    nested_expressions: &mut Vec<Vec<Vec<proc_macro2::TokenStream>>>,
-) {
-    let mut field_name = String::new();
-    loop {
-        match index.next() {
-            Some('>') => break,
-            Some(c) => field_name.push(c),
-            None => panic!(
-                "parameter field missing closing '>' bracket at column {}",
-                index.colno
-            ),
-        }
+/// Returns the generic parameters and input type for functions generated to parse part of a derived type.
+///
+/// These functions are generic over any type of input, unless the derived implementation is restricted to `input`.
+fn input_tokens(input: Option<&syn::Type>) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    match input {
+        Some(ty) => (quote::quote! { <'a> }, quote::quote! { #ty }),
+        None => (
+            quote::quote! { <'a, PeggleInput: ?Sized + peggle::Input> },
+            quote::quote! { PeggleInput },
+        ),
     }
+}
 
-    // Handle any possible repetition/variable number suffixes
-    let (min, max, advanced_index) = get_repetition_bounds(*index);
-    *index = advanced_index;
-
-    requirements
-        .add_field(field_name.clone(), min, max)
-        .expect("8");
+fn id_to_lifetime(ident: syn::Ident) -> syn::Lifetime {
+    syn::Lifetime {
+        apostrophe: ident.span(),
+        ident,
+    }
+}
 
-    let field_fn = quote::format_ident!("__peggle_parse_{}", field_name);
+/// Generates the statement assigning a parsed value (bound to `__peggle_val`) to its field, as `Some(val)` or
+/// `.push(val)` depending on the field's type.
+fn assign_field_tokens(field: &FieldInfo) -> proc_macro2::TokenStream {
+    let identity = quote::format_ident!("__peggle_field_{}", &field.ident);
 
-    let expr_depth = nested_expressions.len().to_string();
-    let choice_lifetime = id_to_lifetime(quote::format_ident!("__choice_lifetime_{}", expr_depth));
-    let loop_revert_index = quote::format_ident!("__loop_revert_{}", expr_depth);
-    let loop_iter_ident = quote::format_ident!("__loop_iter_{}", expr_depth);
+    match (field.cardinality, field.is_boxed) {
+        (FieldCardinality::Single | FieldCardinality::Option, false) => {
+            quote::quote! { #identity = Some(__peggle_val); }
+        }
+        (FieldCardinality::Single | FieldCardinality::Option, true) => {
+            quote::quote! { #identity = Some(Box::new(__peggle_val)); }
+        }
+        (FieldCardinality::Vec, false) => quote::quote! { #identity.push(__peggle_val); },
+        (FieldCardinality::Vec, true) => quote::quote! { #identity.push(Box::new(__peggle_val)); },
+    }
+}
 
-    let Some(field_info) = requirements.fields.get(&field_name) else {
-        panic!("Unrecognized field {}", field_name)
-    };
+/// Generates the code that matches a parsed peggle expression against the input by running it as a `peggle::vm`
+/// program, advancing `__peggle_curr` and leaving `__peggle_failure` set if it did not match.
+///
+/// As a field may be parsed within an attempt that is later backtracked over, parsed values are first kept in a list
+/// for each field; once the program has finished, the values that are part of the match are assigned to their fields.
+fn vm_steps(
+    choice: &Choice,
+    fields: &[FieldInfo],
+    input: Option<&syn::Type>,
+) -> proc_macro2::TokenStream {
+    let (_, input_ty) = input_tokens(input);
+    let program = vm::compile(choice, fields);
+
+    let parsed_fields = fields
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| field.default.is_none());
+
+    let mut value_declarations = Vec::new();
+    let mut parse_arms = Vec::new();
+    let mut assign_arms = Vec::new();
+    for (idx, field) in parsed_fields {
+        let values = quote::format_ident!("__peggle_values_{}", &field.ident);
+        let field_fn = quote::format_ident!("__peggle_parse_{}", &field.ident);
+        let field_ty = &field.inner_ty;
+        let assign_tokens = assign_field_tokens(field);
 
-    let field_name = quote::format_ident!("__peggle_field_{}", field_name);
+        value_declarations.push(quote::quote! {
+            let mut #values: Vec<Option<#field_ty>> = Vec::new();
+        });
+        parse_arms.push(quote::quote! {
+            #idx => {
+                let (__peggle_val, __peggle_new_index) = #field_fn(__peggle_at)?;
+                #values.push(Some(__peggle_val));
+                Ok((#values.len() - 1, __peggle_new_index))
+            }
+        });
+        assign_arms.push(quote::quote! {
+            #idx => {
+                let __peggle_val = #values[__peggle_capture.value].take().unwrap();
+                #assign_tokens
+            }
+        });
+    }
 
-    let assign_tokens = match (field_info.cardinality, field_info.is_boxed) {
-        (FieldCardinality::Single, false) => quote::quote! { #field_name = Some(__peggle_val); },
-        (FieldCardinality::Single, true) => {
-            quote::quote! { #field_name = Some(Box::new(__peggle_val)); }
-        }
-        (FieldCardinality::Option, false) => quote::quote! { #field_name = Some(__peggle_val); },
-        (FieldCardinality::Option, true) => {
-            quote::quote! { #field_name = Some(Box::new(__peggle_val)); }
-        }
-        (FieldCardinality::Vec, false) => quote::quote! { #field_name.push(__peggle_val); },
-        (FieldCardinality::Vec, true) => {
-            quote::quote! { #field_name.push(Box::new(__peggle_val)); }
-        }
+    // Only types with fields have a slot for the errors of failed fields
+    let error_slot = if fields.is_empty() {
+        quote::quote! { &mut None }
+    } else {
+        quote::quote! { &mut __peggle_error }
     };
 
-    nested_expressions
-        .last_mut()
-        .expect("missing last expression")
-        .last_mut()
-        .expect("missing last choice")
-        .push(quote::quote! {
-            let mut #loop_revert_index = __peggle_curr;
-            for #loop_iter_ident in 0..#max {
-                #loop_revert_index = __peggle_curr;
-
-                match #field_fn(__peggle_curr) {
-                    Ok((__peggle_val, new_idx)) => {
-                        __peggle_curr = new_idx;
-                        #assign_tokens // assign val, Some(val) or .push(val) depending on type
-                    }
-                    Err(_) => {
-                        __peggle_failure = true;
-                        if #loop_iter_ident >= #min {
-                            __peggle_curr = #loop_revert_index; // Rewind to where last successful loop iteration finished
-                            __peggle_failure = false;
-                        }
-                        break
+    quote::quote! {
+        #(#value_declarations)*
+
+        let __peggle_outcome = {
+            static __PEGGLE_PROGRAM: &[peggle::vm::Inst] = #program;
+
+            peggle::vm::run(
+                __PEGGLE_PROGRAM,
+                __peggle_curr,
+                #error_slot,
+                &mut |__peggle_field: usize, __peggle_at: peggle::Index<'a, #input_ty>| -> Result<(usize, peggle::Index<'a, #input_ty>), peggle::ParseError> {
+                    match __peggle_field {
+                        #(#parse_arms)*
+                        _ => unreachable!(),
                     }
-                }
+                },
+            )
+        };
+
+        let (__peggle_captures, __peggle_cut_error) = match __peggle_outcome {
+            peggle::vm::Outcome::Matched { end: __peggle_end, captures: __peggle_captures } => {
+                __peggle_curr = __peggle_end;
+                (__peggle_captures, None)
+            }
+            peggle::vm::Outcome::Failed(__peggle_at) => {
+                __peggle_curr = __peggle_at;
+                __peggle_failure = true;
+                (Vec::new(), None)
+            }
+            peggle::vm::Outcome::Cut { error: __peggle_err, captures: __peggle_captures } => {
+                (__peggle_captures, Some(__peggle_err))
             }
+        };
 
-            if __peggle_failure {
-                break #choice_lifetime
+        for __peggle_capture in __peggle_captures {
+            match __peggle_capture.field {
+                #(#assign_arms)*
+                _ => unreachable!(),
             }
-        });
-}
+        }
 
-fn id_to_lifetime(ident: syn::Ident) -> syn::Lifetime {
-    syn::Lifetime {
-        apostrophe: ident.span(),
-        ident,
+        // Fields parsed before a cut are kept, so that they may fill in a partial result when recovering
+        if let Some(__peggle_err) = __peggle_cut_error {
+            return Err(__peggle_err)
+        }
     }
 }
 
-fn match_posix_class(
-    index: &mut peggle::Index,
-    bracket_possibilities: &mut Vec<proc_macro2::TokenStream>,
-    bracket_lifetime: &syn::Lifetime,
-) {
-    index.next(); // Consume ':'
-    let label: String = index.take(5).collect::<String>();
-    match label.as_str() {
-        "upper" => bracket_possibilities.push(quote::quote!{
-            if let 'A'..='Z' = __bracket_char {
-                __peggle_failure = false;
-                break #bracket_lifetime
-            }
-        }),
-        "lower" => bracket_possibilities.push(quote::quote!{
-            if let 'a'..='z' = __bracket_char {
-                __peggle_failure = false;
-                break #bracket_lifetime
-            }
+/// How generated code should handle an expression that fails to match.
+enum Fail {
+    /// Set `__peggle_failure` and break out of the given labeled block, so that other possibilities may be tried
+    Break(syn::Lifetime),
+    /// Return the error immediately, as the expression follows a cut (`~`)
+    Cut,
+}
 
-        }),
-        "alpha" => bracket_possibilities.push(quote::quote!{
-            if let 'a'..='z' | 'A'..='Z' = __bracket_char {
-                __peggle_failure = false;
-                break #bracket_lifetime
-            }
-        }),
-        "digit" => bracket_possibilities.push(quote::quote!{
-            if let '0'..='9' = __bracket_char {
-                __peggle_failure = false;
-                break #bracket_lifetime
-            }
-        }),
-        "xdigi" if Some('t') == index.next() => {
-            bracket_possibilities.push(quote::quote!{
-                if let '0'..='9' | 'a'..='f' | 'A'..='F' = __bracket_char {
-                    __peggle_failure = false;
-                    break #bracket_lifetime
+impl Fail {
+    /// Generates the handling for a failed field, whose error is bound to `__peggle_err`.
+    ///
+    /// If the field is labeled, its error is replaced with one describing what was expected at the field's position.
+    /// Descriptive errors (those with a label or message) are kept in `__peggle_error` if they are further along than
+    /// any already there, so that the most relevant one can be reported should the entire expression fail.
+    fn field_tokens(&self, expected: Option<&str>) -> proc_macro2::TokenStream {
+        let label_tokens = match expected {
+            Some(expected) => quote::quote! {
+                let __peggle_err = peggle::ParseError::from_index(__peggle_curr).with_expected(#expected);
+            },
+            None => quote::quote! {},
+        };
+
+        match self {
+            Fail::Break(lifetime) => quote::quote! {
+                if __peggle_err.cut {
+                    return Err(__peggle_err)
                 }
-            });
-        },
-        "alnum" => bracket_possibilities.push(quote::quote!{
-            if let '0'..='9' | 'a'..='z' | 'A'..='Z' = __bracket_char {
-                __peggle_failure = false;
-                break #bracket_lifetime
-            }
-        }),
-        "punct" => bracket_possibilities.push(quote::quote!{
-            if "][!\"#$%&'()*+,./:;<=>?@\\^_`{|}~-".contains(__bracket_char) {
-                __peggle_failure = false;
-                break #bracket_lifetime
-            }
-        }),
-        "blank" => bracket_possibilities.push(quote::quote!{
-            if " \t".contains(__bracket_char) {
-                __peggle_failure = false;
-                break #bracket_lifetime
-            }
-        }),
-        "space" => bracket_possibilities.push(quote::quote!{
-            if " \t\n\r\x0c\x0b".contains(__bracket_char) {
-                __peggle_failure = false;
-                break #bracket_lifetime
-            }
-        }),
-        "cntrl" => bracket_possibilities.push(quote::quote!{
-            if let '\x00'..='\x1f' | '\x7f' = __bracket_char {
-                __peggle_failure = false;
-                break #bracket_lifetime
-            }
-        }),
-        "graph" => bracket_possibilities.push(quote::quote!{
-            if let '\x21'..='\x7e' = __bracket_char {
-                __peggle_failure = false;
-                break #bracket_lifetime
-            }
-        }),
-        "print" => bracket_possibilities.push(quote::quote!{
-            if let '\x20'..='\x7e' = __bracket_char {
-                __peggle_failure = false;
-                break #bracket_lifetime
-            }
-        }),
-        _ => panic!("unrecognized POSIX character class '{}'", label),
+                #label_tokens
+                if __peggle_err.is_descriptive() {
+                    __peggle_error = Some(match __peggle_error.take() {
+                        Some(__peggle_prev) => __peggle_prev.furthest(__peggle_err),
+                        None => __peggle_err,
+                    });
+                }
+                __peggle_failure = true;
+                break #lifetime
+            },
+            Fail::Cut => quote::quote! {
+                #label_tokens
+                return Err(__peggle_err.with_cut())
+            },
+        }
     }
-    let Some(':') = index.next() else {
-        panic!("POSIX character class {} missing closing ':]' bracket", label);
-    };
-    let Some(']') = index.next() else {
-        panic!("POSIX character class {} missing closing ']' bracket", label);
-    };
 }
 
-fn match_backslash_class(
-    index: &mut peggle::Index,
-    nested_expressions: &mut Vec<Vec<Vec<proc_macro2::TokenStream>>>,
-) {
-    let Some(character) = index.next() else {
-        panic!("expected character after backslash")
-    };
+impl quote::ToTokens for Fail {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        tokens.extend(match self {
+            Fail::Break(lifetime) => quote::quote! {
+                __peggle_failure = true;
+                break #lifetime
+            },
+            Fail::Cut => quote::quote! {
+                return Err(peggle::ParseError::from_index(__peggle_curr).with_cut())
+            },
+        });
+    }
+}
 
-    let (min, max, advanced_index) = get_repetition_bounds(*index);
-    *index = advanced_index;
+/// Generates the code that matches a parsed peggle expression against the input.
+///
+/// Generated code advances `__peggle_curr` as it matches. When an expression fails to match, it sets
+/// `__peggle_failure` and breaks out of the labeled block it was given; `__peggle_curr` is unspecified
+/// after a failure, so callers that go on to try something else must first revert it.
+struct Generator<'a> {
+    fields: &'a [FieldInfo],
+    label_count: usize,
+}
 
-    let expr_depth = nested_expressions.len().to_string();
-    let choice_lifetime = id_to_lifetime(quote::format_ident!("__choice_lifetime_{}", expr_depth));
-    let loop_iter_ident = quote::format_ident!("__loop_iter_{}", expr_depth);
+impl<'a> Generator<'a> {
+    fn new(fields: &'a [FieldInfo]) -> Self {
+        Self {
+            fields,
+            label_count: 0,
+        }
+    }
 
-    let character_match_tokens = match character {
-        'w' => quote::quote! {
-            let 'a'..='z' | 'A'..='Z' | '_'  = __backslash_char else {
-                __peggle_failure = true;
-            }
-        },
-        'W' => quote::quote! {
-            if let 'a'..='z' | 'A'..='Z' | '_'  = __backslash_char {
-                __peggle_failure = true;
-            }
-        },
-        'd' => quote::quote! {
-            let '0'..='9'  = __backslash_char else {
-                __peggle_failure = true;
-            }
-        },
-        'D' => quote::quote! {
-            if let '0'..='9'  = __backslash_char {
-                __peggle_failure = true;
-            }
-        },
-        's' => quote::quote! {
-            let ' ' | '\t' | '\n' | '\r' | '\x0b' | '\x0c' = __backslash_char else {
-                __peggle_failure = true;
-            }
-        },
-        'S' => quote::quote! {
-            if let ' ' | '\t' | '\n' | '\r' | '\x0b' | '\x0c' = __backslash_char {
-                __peggle_failure = true;
-            }
-        },
-        // Covers all other backslash-escaped characters
-        '\\' | '{' | '}' | '[' | ']' | '(' | ')' | '^' | '$' | '.' | '|' | '*' | '+' | '?'
-        | '<' | '>' | '&' => quote::quote! {
-            if __backslash_char != #character {
-                __peggle_failure = true;
-            }
-        },
-        _ => panic!("unrecognized backslash-escaped character '{}'", character),
-    };
+    /// Returns a new identifier that is unique within the generated code (used for labels and variables).
+    fn unique_id(&mut self, name: &str) -> syn::Ident {
+        self.label_count += 1;
+        quote::format_ident!("__{}_{}", name, self.label_count)
+    }
 
-    nested_expressions
-        .last_mut()
-        .unwrap()
-        .last_mut()
-        .unwrap()
-        .push(quote::quote! {
-            __peggle_failure = false;
-            for #loop_iter_ident in 0..#max {
-                if let Some(__backslash_char) = __peggle_curr.peek() {
-                    #character_match_tokens
-                } else {
-                    __peggle_failure = true;
-                }
+    fn unique_lifetime(&mut self, name: &str) -> syn::Lifetime {
+        let ident = self.unique_id(name);
+        id_to_lifetime(ident)
+    }
 
-                if __peggle_failure {
-                    if #loop_iter_ident >= #min {
-                        __peggle_failure = false;
-                    }
-                    break
-                }
-                __peggle_curr.next();
-            }
+    /// Generates the code for an entire peggle expression, leaving `__peggle_failure` set if it did not match.
+    fn expression(&mut self, choice: &Choice) -> proc_macro2::TokenStream {
+        let expr_lifetime = self.unique_lifetime("expression_lifetime");
+        let choice_tokens = self.choice(choice, &Fail::Break(expr_lifetime.clone()));
 
-            if __peggle_failure {
-                break #choice_lifetime
+        quote::quote! {
+            #expr_lifetime: {
+                #choice_tokens
             }
-        });
-}
+        }
+    }
 
-fn match_one_of(
-    index: &mut peggle::Index,
-    nested_expressions: &mut Vec<Vec<Vec<proc_macro2::TokenStream>>>,
-) {
-    let mut bracket_possibilities = Vec::new();
+    fn choice(&mut self, choice: &[Sequence], fail: &Fail) -> proc_macro2::TokenStream {
+        if let [sequence] = choice {
+            return self.sequence(sequence, fail);
+        }
+
+        let expr_lifetime = self.unique_lifetime("expression_lifetime");
+        let choice_revert_index = self.unique_id("choice_revert_index");
 
-    let expr_depth = nested_expressions.len().to_string();
-    let choice_lifetime = id_to_lifetime(quote::format_ident!("__choice_lifetime_{}", expr_depth));
-    let bracket_lifetime =
-        id_to_lifetime(quote::format_ident!("__bracket_lifetime_{}", expr_depth));
+        let mut expression_tokens = quote::quote! {
+            let #choice_revert_index = __peggle_curr;
+        };
+
+        for (idx, sequence) in choice.iter().enumerate() {
+            // Handle each possible choice in order, breaking upon the first success
+            let choice_lifetime = self.unique_lifetime("choice_lifetime");
+            let sequence_tokens = self.sequence(sequence, &Fail::Break(choice_lifetime.clone()));
+            let trace_tokens = if idx + 1 < choice.len() {
+                let text = sequence_text(sequence);
+                quote::quote! { peggle::__trace!(backtrack, #text, __peggle_curr); }
+            } else {
+                quote::quote! {}
+            };
 
-    let inverted = Some('^') == index.peek();
-    if inverted {
-        index.next();
+            expression_tokens.extend(quote::quote! {
+                __peggle_failure = false;
+                __peggle_curr = #choice_revert_index;
+                #choice_lifetime: {
+                    #sequence_tokens
+                }
+                if !__peggle_failure {
+                    break #expr_lifetime // Choice matched--break at the given index
+                }
+                #trace_tokens
+                // No match--move to next choice (resetting both `__peggle_failure` and `__peggle_curr`) or else return failure
+            });
+        }
+
+        quote::quote! {
+            #expr_lifetime: {
+                #expression_tokens
+                #fail
+            }
+        }
     }
 
-    'refactor_this: {
-        if let Some(lit @ (']' | '-')) = index.peek() {
-            index.next();
-            if let Some('-') = index.peek() {
-                // TODO: this code is duplicated
-                index.next();
-                match index
-                    .next()
-                    .expect("missing closing bracket (`]`) character in regex")
-                {
-                    ']' => {
-                        bracket_possibilities.push(quote::quote! {
-                            if __bracket_char == #lit || __bracket_char == '-' {
-                                __peggle_failure = false;
-                                break #bracket_lifetime
-                            }
-                        });
-                        break 'refactor_this;
+    fn sequence(&mut self, sequence: &[Expr], fail: &Fail) -> proc_macro2::TokenStream {
+        let mut tokens = proc_macro2::TokenStream::new();
+
+        let mut idx = 0;
+        while idx < sequence.len() {
+            let expr = &sequence[idx];
+            idx += 1;
+            let rest = &sequence[idx..];
+
+            match expr {
+                Expr::Char(first) if matches!(rest.first(), Some(Expr::Char(_))) => {
+                    // A run of literal characters is matched all at once
+                    let mut literal = first.to_string();
+                    while let Some(Expr::Char(c)) = sequence.get(idx) {
+                        literal.push(*c);
+                        idx += 1;
                     }
-                    end_char if end_char < lit => panic!(
-                        "range {}-{} invalid: {} comes before {} in ordering",
-                        lit, end_char, end_char, lit
-                    ),
-                    end_char => bracket_possibilities.push(quote::quote! {
-                        if __bracket_char >= #lit && __bracket_char <= #end_char {
-                            __peggle_failure = false;
-                            break #bracket_lifetime
+
+                    tokens.extend(quote::quote! {
+                        if !__peggle_curr.consume(#literal) {
+                            #fail
                         }
-                    }),
+                    });
                 }
-            } else {
-                bracket_possibilities.push(quote::quote! {
-                    if __bracket_char == #lit {
-                        __peggle_failure = false;
-                        break #bracket_lifetime
-                    }
-                })
+                Expr::Repeat(inner, repetition) if repetition.mode.backtracks() => {
+                    // The remainder of the sequence is matched from within the repetition, so that it can be retried
+                    tokens.extend(self.backtracking_repeat(inner, *repetition, rest, fail));
+                    return tokens;
+                }
+                Expr::Cut => {
+                    // Any failure past this point is returned directly, rather than falling back to other choices
+                    tokens.extend(self.sequence(rest, &Fail::Cut));
+                    return tokens;
+                }
+                _ => tokens.extend(self.expr(expr, fail)),
             }
         }
 
-        loop {
-            let c = index
-                .next()
-                .expect("missing closing bracket (`]`) character in regex");
-            match (c, index.peek()) {
-                (']', _) => break,
-                ('-', _) => panic!("dash must be preceded by starting value that is not also an ending value for another dash"),
-                (_, Some('-')) => {
-                    // Handle "a-z" case (using a dash to indicate a range of values)
-                    index.next();
-                    match index.next().expect("missing closing bracket (`]`) character in regex") {
-                        ']' => {
-                            bracket_possibilities.push(quote::quote! {
-                                if __bracket_char == #c || __bracket_char == '-' {
-                                    __peggle_failure = false;
-                                    break #bracket_lifetime
-                                }
-                            });
-                            break
-                        }
-                        end_char if end_char < c => panic!("range {}-{} invalid: {} comes before {} in ordering", c, end_char, end_char, c),
-                        end_char => bracket_possibilities.push(quote::quote! {
-                            if __bracket_char >= #c && __bracket_char <= #end_char {
-                                __peggle_failure = false;
-                                break #bracket_lifetime
-                            }
-                        }),
+        tokens
+    }
+
+    fn expr(&mut self, expr: &Expr, fail: &Fail) -> proc_macro2::TokenStream {
+        match expr {
+            Expr::Char(character) => quote::quote! {
+                match __peggle_curr.peek() {
+                    Some(#character) => {
+                        __peggle_curr.next();
+                    }
+                    _ => {
+                        #fail
                     }
                 }
-                ('[', Some(':')) => match_posix_class(index, &mut bracket_possibilities, &bracket_lifetime),
-                _ => bracket_possibilities.push(quote::quote!{
-                    if __bracket_char == #c {
-                        __peggle_failure = false;
-                        break #bracket_lifetime
+            },
+            Expr::Any => quote::quote! {
+                if __peggle_curr.next().is_none() {
+                    #fail
+                }
+            },
+            Expr::Class(class) => {
+                let class_char = quote::format_ident!("__class_char");
+                let class_tokens = class.to_tokens(&class_char);
+
+                quote::quote! {
+                    match __peggle_curr.peek() {
+                        Some(#class_char) if #class_tokens => {
+                            __peggle_curr.next();
+                        }
+                        _ => {
+                            #fail
+                        }
                     }
-                }),
+                }
             }
+            Expr::Field { name, expected } => self.field(name, expected.as_deref(), fail),
+            Expr::Group(choice) => self.choice(choice, fail),
+            Expr::Repeat(inner, repetition) => self.repeat(inner, *repetition, fail),
+            Expr::Cut => unreachable!("cuts are handled within sequences"),
         }
     }
 
-    // Handle any possible repetition/variable number suffixes
-    let (min, max, advanced_index) = get_repetition_bounds(*index);
-    *index = advanced_index;
-
-    let loop_revert_index = quote::format_ident!("__loop_revert_{}", expr_depth);
-    let loop_iter_ident = quote::format_ident!("__loop_iter_{}", expr_depth);
-
-    nested_expressions
-        .last_mut()
-        .expect("9")
-        .last_mut()
-        .expect("10")
-        .push(quote::quote! {
-            let mut #loop_revert_index = __peggle_curr;
-            for #loop_iter_ident in 0..#max {
-                #loop_revert_index = __peggle_curr;
-
-                match __peggle_curr.next() {
-                    Some(__bracket_char) => {
-                        __peggle_failure = true;
-                        #bracket_lifetime: {
-                            #(#bracket_possibilities)*
-                        }
+    fn field(
+        &mut self,
+        field_name: &str,
+        expected: Option<&str>,
+        fail: &Fail,
+    ) -> proc_macro2::TokenStream {
+        let Some(field_info) = self.fields.iter().find(|f| f.ident == field_name) else {
+            panic!("Unrecognized field {}", field_name)
+        };
 
-                        if #inverted {
-                            __peggle_failure = !__peggle_failure
-                        }
-                    }
-                    None => __peggle_failure = true,
-                }
+        let field_fn = quote::format_ident!("__peggle_parse_{}", field_name);
+        let field_fail_tokens = fail.field_tokens(expected);
+        let assign_tokens = assign_field_tokens(field_info);
 
-                if __peggle_failure {
-                    if #loop_iter_ident >= #min {
-                        __peggle_curr = #loop_revert_index; // Rewind to where last successful loop iteration finished
-                        __peggle_failure = false;
-                    }
-                    break
+        quote::quote! {
+            match #field_fn(__peggle_curr) {
+                Ok((__peggle_val, __peggle_new_index)) => {
+                    __peggle_curr = __peggle_new_index;
+                    #assign_tokens // assign val, Some(val) or .push(val) depending on type
+                }
+                Err(__peggle_err) => {
+                    #field_fail_tokens
                 }
             }
+        }
+    }
 
-            if __peggle_failure {
-                break #choice_lifetime
+    /// Generates a possessive repetition, which matches as many times as it can and never gives any matches back.
+    fn repeat(
+        &mut self,
+        inner: &Expr,
+        repetition: Repetition,
+        fail: &Fail,
+    ) -> proc_macro2::TokenStream {
+        let Repetition { min, max, .. } = repetition;
+
+        let loop_lifetime = self.unique_lifetime("loop_lifetime");
+        let iter_lifetime = self.unique_lifetime("choice_lifetime");
+        let loop_iter_ident = self.unique_id("loop_iter");
+        let loop_revert_index = self.unique_id("loop_revert");
+        let inner_tokens = self.expr(inner, &Fail::Break(iter_lifetime.clone()));
+
+        let min_check_tokens = if min > 0 {
+            quote::quote! {
+                if #loop_iter_ident < #min {
+                    #fail
+                }
             }
-        });
-}
+        } else {
+            quote::quote! {}
+        };
+        let past_min_tokens = if min > 0 {
+            quote::quote! { && #loop_iter_ident >= #min }
+        } else {
+            quote::quote! {}
+        };
 
-fn match_character(
-    character: char,
-    index: &mut peggle::Index,
-    nested_expressions: &mut Vec<Vec<Vec<proc_macro2::TokenStream>>>,
-) {
-    let (min, max, advanced_index) = get_repetition_bounds(*index);
-    *index = advanced_index;
-
-    let expr_depth = nested_expressions.len().to_string();
-    let choice_lifetime = id_to_lifetime(quote::format_ident!("__choice_lifetime_{}", expr_depth));
-    let loop_iter_ident = quote::format_ident!("__loop_iter_{}", expr_depth);
-
-    nested_expressions
-        .last_mut()
-        .expect("11")
-        .last_mut()
-        .expect("12")
-        .push(quote::quote! {
-            __peggle_failure = false;
-            for #loop_iter_ident in 0..#max {
-                let Some(#character) = __peggle_curr.peek() else {
-                    if #loop_iter_ident < #min {
-                        __peggle_failure = true;
-                        break #choice_lifetime
-                    } else {
-                        break
-                    }
-                };
-                __peggle_curr.next();
+        quote::quote! {
+            #loop_lifetime: for #loop_iter_ident in 0..#max {
+                __peggle_curr.step()?;
+                let #loop_revert_index = __peggle_curr;
+                __peggle_failure = false;
+                #iter_lifetime: {
+                    #inner_tokens
+                }
+
+                if __peggle_failure {
+                    __peggle_curr = #loop_revert_index; // Rewind to where last successful loop iteration finished
+                    #min_check_tokens
+                    __peggle_failure = false;
+                    break #loop_lifetime
+                }
+
+                // An iteration that consumed nothing would match the same way every time, so repeating stops there
+                if __peggle_curr == #loop_revert_index #past_min_tokens {
+                    break #loop_lifetime
+                }
             }
-        });
-}
+        }
+    }
 
-fn match_any_character(
-    index: &mut peggle::Index,
-    nested_expressions: &mut Vec<Vec<Vec<proc_macro2::TokenStream>>>,
-) {
-    let (min, max, advanced_index) = get_repetition_bounds(*index);
-    *index = advanced_index;
-
-    let expr_depth = nested_expressions.len().to_string();
-    let choice_lifetime = id_to_lifetime(quote::format_ident!("__choice_lifetime_{}", expr_depth));
-    let loop_iter_ident = quote::format_ident!("__loop_iter_{}", expr_depth);
-
-    nested_expressions
-        .last_mut()
-        .expect("13")
-        .last_mut()
-        .expect("14")
-        .push(quote::quote! {
-            __peggle_failure = false;
-            for #loop_iter_ident in 0..#max {
-                let Some(_) = __peggle_curr.next() else {
-                    if #loop_iter_ident < #min {
-                        __peggle_failure = true;
-                        break #choice_lifetime
-                    } else {
-                        break
+    /// Generates a repetition that retries the remainder of its sequence (`rest`) with each permissible number of
+    /// matches--from most to fewest for [`RepeatMode::Greedy`], or from fewest to most for [`RepeatMode::Lazy`].
+    fn backtracking_repeat(
+        &mut self,
+        inner: &Expr,
+        repetition: Repetition,
+        rest: &[Expr],
+        fail: &Fail,
+    ) -> proc_macro2::TokenStream {
+        let Repetition { min, max, mode } = repetition;
+
+        let loop_lifetime = self.unique_lifetime("loop_lifetime");
+        let iter_lifetime = self.unique_lifetime("choice_lifetime");
+        let rest_lifetime = self.unique_lifetime("choice_lifetime");
+        let loop_revert_index = self.unique_id("loop_revert");
+        let loop_revert_fields = self.unique_id("loop_revert_fields");
+        let inner_tokens = self.expr(inner, &Fail::Break(iter_lifetime.clone()));
+        let rest_tokens = self.sequence(rest, &Fail::Break(rest_lifetime.clone()));
+
+        // Fields assigned by a match that gets given back (or by a failed attempt at the rest of the sequence)
+        // must be unassigned again before trying the next possibility
+        let snapshot_fields = self.snapshot_fields();
+        let restore_fields = self.restore_fields(&loop_revert_fields);
+
+        match mode {
+            RepeatMode::Greedy => {
+                let positions = self.unique_id("backtrack_positions");
+
+                quote::quote! {
+                    let mut #positions = vec![(__peggle_curr, #snapshot_fields)];
+                    #loop_lifetime: for _ in 0..#max {
+                        __peggle_curr.step()?;
+                        let #loop_revert_index = __peggle_curr;
+                        let #loop_revert_fields = #snapshot_fields;
+                        __peggle_failure = false;
+                        #iter_lifetime: {
+                            #inner_tokens
+                        }
+
+                        if __peggle_failure {
+                            __peggle_curr = #loop_revert_index;
+                            #restore_fields
+                            break #loop_lifetime
+                        }
+                        #positions.push((__peggle_curr, #snapshot_fields));
+
+                        // Positions include the one before the first match, so this is past the minimum
+                        if __peggle_curr == #loop_revert_index && #positions.len() > #min + 1 {
+                            break #loop_lifetime
+                        }
                     }
-                };
-            }
-        });
-}
 
-fn coalesce_topmost_expression(
-    index: &mut peggle::Index,
-    nested_expressions: &mut Vec<Vec<Vec<proc_macro2::TokenStream>>>,
-    requirements: Option<&mut FieldRequirements>,
-) {
-    let expr_depth = nested_expressions.len().to_string();
-    let choice_revert_index = quote::format_ident!("__choice_revert_index_{}", expr_depth);
-    let expr_lifetime =
-        id_to_lifetime(quote::format_ident!("__expression_lifetime_{}", expr_depth));
-    let choice_lifetime = id_to_lifetime(quote::format_ident!("__choice_lifetime_{}", expr_depth));
-
-    let mut expression_tokens = quote::quote! {
-        let #choice_revert_index = __peggle_curr;
-    };
+                    // Give back one match at a time until the rest of the sequence matches
+                    __peggle_failure = true;
+                    while #positions.len() > #min {
+                        __peggle_curr.step()?;
+                        let (#loop_revert_index, #loop_revert_fields) = #positions.pop().unwrap();
+                        __peggle_curr = #loop_revert_index;
+                        #restore_fields
+                        __peggle_failure = false;
+                        #rest_lifetime: {
+                            #rest_tokens
+                        }
 
-    for choice_tokens in nested_expressions.pop().expect("15") {
-        // Handle each possible choice in order, breaking upon the first success
-        expression_tokens.extend(quote::quote! {
-            __peggle_failure = false;
-            __peggle_curr = #choice_revert_index;
-            #choice_lifetime: {
-                #(#choice_tokens)*
-            }
-            if !__peggle_failure {
-                break #expr_lifetime // Choice matched--break at the given index
+                        if !__peggle_failure {
+                            break
+                        }
+                    }
+
+                    if __peggle_failure {
+                        #fail
+                    }
+                }
             }
-            // No match--move to next choice (resetting both `__peggle_failure` and `__peggle_curr`) or else return failure
-        });
-    }
+            RepeatMode::Lazy => {
+                let loop_count = self.unique_id("loop_count");
+                let min_check_tokens = if min > 0 {
+                    quote::quote! { #loop_count >= #min }
+                } else {
+                    quote::quote! { true }
+                };
 
-    let underlayer_choice_lifetime = id_to_lifetime(quote::format_ident!(
-        "__choice_lifetime_{}",
-        nested_expressions.len().to_string()
-    ));
+                quote::quote! {
+                    let mut #loop_count = 0usize;
+                    #loop_lifetime: loop {
+                        __peggle_curr.step()?;
+                        let #loop_revert_index = __peggle_curr;
+                        let #loop_revert_fields = #snapshot_fields;
 
-    let (min, max, advanced_index) = get_repetition_bounds(*index);
-    *index = advanced_index;
+                        // Try the rest of the sequence before taking another match
+                        if #min_check_tokens {
+                            __peggle_failure = false;
+                            #rest_lifetime: {
+                                #rest_tokens
+                            }
 
-    if let Some(requirements) = requirements {
-        requirements.pop_nested_expr(min, max).expect("Failed");
-    }
+                            if !__peggle_failure {
+                                break #loop_lifetime
+                            }
 
-    let loop_revert_index = quote::format_ident!("__loop_revert_{}", expr_depth);
-    let loop_iter_ident = quote::format_ident!("__loop_iter_{}", expr_depth);
-
-    nested_expressions
-        .last_mut()
-        .expect("peggle expression had one too many closing parentheses")
-        .last_mut()
-        .expect("17")
-        .push(quote::quote! {
-            let mut #loop_revert_index = __peggle_curr;
-            for #loop_iter_ident in 0..#max {
-                #loop_revert_index = __peggle_curr;
-
-                #expr_lifetime: {
-                    #expression_tokens
-                }
+                            __peggle_curr = #loop_revert_index;
+                            #restore_fields
+                        }
+
+                        __peggle_failure = true;
+                        if #loop_count == #max {
+                            break #loop_lifetime
+                        }
 
-                if __peggle_failure {
-                    if #loop_iter_ident >= #min {
-                        __peggle_curr = #loop_revert_index; // Rewind to where last successful loop iteration finished
                         __peggle_failure = false;
+                        #iter_lifetime: {
+                            #inner_tokens
+                        }
+
+                        if __peggle_failure {
+                            __peggle_curr = #loop_revert_index;
+                            #restore_fields
+                            break #loop_lifetime
+                        }
+                        #loop_count += 1;
+
+                        // After an iteration that consumed nothing, the rest of the sequence would be tried where it
+                        // has already failed (once the minimum was reached), so there's nothing left to try
+                        if __peggle_curr == #loop_revert_index && #loop_count > #min {
+                            #restore_fields
+                            __peggle_failure = true;
+                            break #loop_lifetime
+                        }
+                    }
+
+                    if __peggle_failure {
+                        #fail
                     }
-                    break
                 }
             }
+            RepeatMode::Possessive => unreachable!("possessive repetitions never backtrack"),
+        }
+    }
 
-            if __peggle_failure {
-                break #underlayer_choice_lifetime
+    /// Generates an expression capturing which fields are currently assigned (and how many values each `Vec` holds).
+    fn snapshot_fields(&self) -> proc_macro2::TokenStream {
+        // Skipped and defaulted fields aren't parsed, so have nothing to snapshot
+        let parsed_fields = self.fields.iter().filter(|field| field.default.is_none());
+        let snapshots = parsed_fields.map(|field| {
+            let identity = quote::format_ident!("__peggle_field_{}", &field.ident);
+            match field.cardinality {
+                FieldCardinality::Single | FieldCardinality::Option => {
+                    quote::quote! { #identity.is_some(), }
+                }
+                FieldCardinality::Vec => quote::quote! { #identity.len(), },
             }
         });
-}
 
-fn get_repetition_bounds(mut index: peggle::Index<'_>) -> (usize, usize, peggle::Index<'_>) {
-    match index.peek() {
-        Some('?') => {
-            index.next();
-            (0usize, 1usize, index)
-        }
-        Some('*') => {
-            index.next();
-            (0, usize::MAX, index)
-        }
-        Some('+') => {
-            index.next();
-            (1, usize::MAX, index)
-        }
-        Some('{') => {
-            index.next();
-
-            let min: usize;
-            let max: usize;
+        quote::quote! { (#(#snapshots)*) }
+    }
 
-            if let Some(',') = index.peek() {
-                min = 0;
-                index.next();
-                if let Some('}') = index.peek() {
-                    panic!("Range missing high value after comma within curly braces")
-                }
-            } else {
-                (min, index) = usize::parse_at(index)
-                    .expect("invalid `min` value contained within curly braces: {}");
-                let Some(',') = index.peek() else {
-                    let Some('}') = index.next() else {
-                        panic!("invalid value contained within curly braces: missing closing curly brace")
-                    };
-                    return (min, min, index)
-                };
-                index.next();
+    /// Generates statements that unassign any field values added since the given snapshot was taken.
+    fn restore_fields(&self, snapshot: &syn::Ident) -> proc_macro2::TokenStream {
+        let parsed_fields = self.fields.iter().filter(|field| field.default.is_none());
+        let restores = parsed_fields.enumerate().map(|(idx, field)| {
+            let identity = quote::format_ident!("__peggle_field_{}", &field.ident);
+            let idx = syn::Index::from(idx);
+            match field.cardinality {
+                FieldCardinality::Single | FieldCardinality::Option => quote::quote! {
+                    if !#snapshot.#idx {
+                        #identity = None;
+                    }
+                },
+                FieldCardinality::Vec => quote::quote! { #identity.truncate(#snapshot.#idx); },
             }
+        });
 
-            if let Some('}') = index.peek() {
-                max = usize::MAX;
-                index.next();
-            } else {
-                (max, index) = usize::parse_at(index)
-                    .expect("invalid `max` value contained within curly braces: {}");
-                let Some('}') = index.next() else {
-                    panic!("invalid value contained within curly braces: missing closing curly brace")
-                };
-            }
-            assert!(
-                min <= max,
-                "min value must be less than or equal to max value in {{min,max}} range"
-            );
-            (min, max, index)
+        quote::quote! {
+            let _ = #snapshot;
+            #(#restores)*
         }
-        _ => (1usize, 1usize, index),
     }
 }
@@ -0,0 +1,417 @@
+use peggle::Parse;
+
+use crate::class::{backslash_class, escape_sequence, parse_bracket_class, CharClass};
+
+/// A list of alternatives, tried in order until one matches (`a|b|c`).
+pub type Choice = Vec<Sequence>;
+
+/// A list of expressions that must each match one after the other (`abc`).
+pub type Sequence = Vec<Expr>;
+
+/// A single element of a parsed peggle expression.
+#[derive(Clone, Debug)]
+pub enum Expr {
+    /// A literal character
+    Char(char),
+    /// Any single character (`.`)
+    Any,
+    /// A set of characters, such as `[a-z]` or `\d`
+    Class(CharClass),
+    /// A reference to a member field (`<name>`), optionally labeled with what it represents
+    /// for error messages (`<name:expected 'an identifier'>`)
+    Field {
+        name: String,
+        expected: Option<String>,
+    },
+    /// A parenthesized set of alternatives (`(a|b)`)
+    Group(Choice),
+    /// An expression followed by a repetition suffix (`a*`, `(ab){2,5}`, `<field>+?`, ...)
+    Repeat(Box<Expr>, Repetition),
+    /// A cut (`~`), which commits to the alternative currently being matched
+    Cut,
+}
+
+/// The number of times a repeated expression may match, and how it gives matches back.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Repetition {
+    pub min: usize,
+    pub max: usize,
+    pub mode: RepeatMode,
+}
+
+/// How a repeated expression interacts with the expressions that follow it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RepeatMode {
+    /// Match as many times as possible and never give any matches back (PEG semantics; `a*+` or plain `a*`).
+    Possessive,
+    /// Match as many times as possible, giving matches back one at a time if the rest of the sequence
+    /// fails to match (regex semantics; plain `a*` within a `#[peg(backtrack)]` pattern).
+    Greedy,
+    /// Match as few times as possible, taking one more match at a time if the rest of the sequence
+    /// fails to match (regex semantics; `a*?`).
+    Lazy,
+}
+
+impl RepeatMode {
+    /// Whether the expressions following the repetition must be retried with a different number of matches.
+    #[inline]
+    pub fn backtracks(&self) -> bool {
+        *self != RepeatMode::Possessive
+    }
+}
+
+/// Options that change how a peggle expression is interpreted.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct PatternOptions {
+    /// Whether `<field>` references are permitted
+    pub fields: bool,
+    /// Whether plain repetition suffixes (`*`, `+`, `?`, `{m,n}`) backtrack like a regular expression
+    pub backtrack: bool,
+}
+
+/// Parses a peggle expression into the alternatives it consists of.
+pub fn parse_pattern(pegex: &str, options: PatternOptions) -> Choice {
+    let mut index = peggle::Index::new(pegex);
+    let mut nested_choices: Vec<Choice> = vec![vec![Vec::new()]];
+
+    while let Some(c) = index.next() {
+        let expr = match c {
+            '(' => {
+                nested_choices.push(vec![Vec::new()]); // Add another nested expression on the stack
+                continue;
+            }
+            ')' => {
+                let choice = nested_choices.pop().expect("17");
+                if nested_choices.is_empty() {
+                    panic!("peggle expression had one too many closing parentheses");
+                }
+                Expr::Group(choice)
+            }
+            '|' => {
+                // Add another option to the current expression
+                nested_choices.last_mut().expect("6").push(Vec::new());
+                continue;
+            }
+            '<' if options.fields => parse_field(&mut index),
+            '<' | '>' if !options.fields => panic!("carrot brackets reserved for member fields, which are not allowed within a field derive. Use \"[<]\" and \"[>]\" to specify literal carrot brackets"),
+            '[' => Expr::Class(parse_bracket_class(&mut index)),
+            '>' | ']' | '*' | '+' | '?' | '{' | '}' => panic!("unexpected token {} at peggle index {}", c, index.colno),
+            '^' | '$' => panic!("{} regex symbol not implemented (prepend a backslash to use the literal `{}` character)", c, c),
+            '.' => Expr::Any,
+            '~' => Expr::Cut,
+            '\\' => parse_backslash(&mut index),
+            _ => Expr::Char(c),
+        };
+
+        // Handle any possible repetition/variable number suffixes
+        let expr = match parse_repetition(&mut index, options) {
+            Some(_) if matches!(expr, Expr::Cut) => panic!("a cut (`~`) cannot be repeated"),
+            Some(repetition) => Expr::Repeat(Box::new(expr), repetition),
+            None => expr,
+        };
+
+        nested_choices
+            .last_mut()
+            .expect("missing last expression")
+            .last_mut()
+            .expect("missing last choice")
+            .push(expr);
+    }
+
+    if nested_choices.len() > 1 {
+        panic!("missing close parantheses in peggle expression");
+    }
+
+    nested_choices.pop().expect("missing last expr_token")
+}
+
+fn parse_field(index: &mut peggle::Index) -> Expr {
+    let mut name = String::new();
+    loop {
+        match index.next() {
+            Some('>') => {
+                return Expr::Field {
+                    name,
+                    expected: None,
+                }
+            }
+            Some(':') => break,
+            Some(c) => name.push(c),
+            None => panic!(
+                "parameter field missing closing '>' bracket at column {}",
+                index.colno
+            ),
+        }
+    }
+
+    // Handle an error label, such as in `<name:expected 'an identifier'>`
+    let mut keyword = String::new();
+    while let Some(c @ 'a'..='z') = index.peek() {
+        keyword.push(c);
+        index.next();
+    }
+    assert!(
+        keyword == "expected",
+        "unrecognized label '{}' for field '{}' (labels must be of the form `<field:expected 'label'>`)",
+        keyword,
+        name
+    );
+
+    while let Some(' ') = index.peek() {
+        index.next();
+    }
+
+    let Some(quote @ ('\'' | '"')) = index.next() else {
+        panic!(
+            "expected label for field '{}' must be enclosed in quotes",
+            name
+        )
+    };
+
+    let mut expected = String::new();
+    loop {
+        match index.next() {
+            Some(c) if c == quote => break,
+            Some(c) => expected.push(c),
+            None => panic!("expected label for field '{}' missing closing quote", name),
+        }
+    }
+
+    let Some('>') = index.next() else {
+        panic!(
+            "parameter field '{}' missing closing '>' bracket after label",
+            name
+        )
+    };
+
+    Expr::Field {
+        name,
+        expected: Some(expected),
+    }
+}
+
+fn parse_backslash(index: &mut peggle::Index) -> Expr {
+    let Some(character) = index.next() else {
+        panic!("expected character after backslash")
+    };
+
+    if let Some(escaped) = escape_sequence(character, index) {
+        return Expr::Char(escaped);
+    }
+
+    if let Some(class) = backslash_class(character) {
+        return Expr::Class(class);
+    }
+
+    match character {
+        // Covers all other backslash-escaped characters
+        '\\' | '{' | '}' | '[' | ']' | '(' | ')' | '^' | '$' | '.' | '|' | '*' | '+' | '?'
+        | '<' | '>' | '&' | '~' => Expr::Char(character),
+        _ => panic!("unrecognized backslash-escaped character '{}'", character),
+    }
+}
+
+/// Reads a repetition suffix (such as `*`, `{2,}` or `+?`) if one immediately follows in the index.
+fn parse_repetition(index: &mut peggle::Index, options: PatternOptions) -> Option<Repetition> {
+    let (min, max) = get_repetition_bounds(index)?;
+
+    let mode = match index.peek() {
+        Some('?') => {
+            index.next();
+            RepeatMode::Lazy
+        }
+        Some('+') => {
+            index.next();
+            RepeatMode::Possessive
+        }
+        _ if options.backtrack => RepeatMode::Greedy,
+        _ => RepeatMode::Possessive,
+    };
+
+    Some(Repetition { min, max, mode })
+}
+
+fn get_repetition_bounds(index: &mut peggle::Index) -> Option<(usize, usize)> {
+    match index.peek() {
+        Some('?') => {
+            index.next();
+            Some((0usize, 1usize))
+        }
+        Some('*') => {
+            index.next();
+            Some((0, usize::MAX))
+        }
+        Some('+') => {
+            index.next();
+            Some((1, usize::MAX))
+        }
+        Some('{') => {
+            index.next();
+
+            let min: usize;
+            let max: usize;
+
+            if let Some(',') = index.peek() {
+                min = 0;
+                index.next();
+                if let Some('}') = index.peek() {
+                    panic!("Range missing high value after comma within curly braces")
+                }
+            } else {
+                (min, *index) = usize::parse_at(*index)
+                    .expect("invalid `min` value contained within curly braces: {}");
+                let Some(',') = index.peek() else {
+                    let Some('}') = index.next() else {
+                        panic!("invalid value contained within curly braces: missing closing curly brace")
+                    };
+                    return Some((min, min));
+                };
+                index.next();
+            }
+
+            if let Some('}') = index.peek() {
+                max = usize::MAX;
+                index.next();
+            } else {
+                (max, *index) = usize::parse_at(*index)
+                    .expect("invalid `max` value contained within curly braces: {}");
+                let Some('}') = index.next() else {
+                    panic!(
+                        "invalid value contained within curly braces: missing closing curly brace"
+                    )
+                };
+            }
+            assert!(
+                min <= max,
+                "min value must be less than or equal to max value in {{min,max}} range"
+            );
+            Some((min, max))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the set of characters that input matching the given alternatives may begin with.
+///
+/// `None` is returned when this can't be determined, such as when the alternatives may match empty input, begin
+/// with a field (whose type may begin with anything) or begin with a cut (whose failure must be reported).
+pub fn first_chars(choice: &[Sequence]) -> Option<CharClass> {
+    let mut first = CharClass::new();
+    for sequence in choice {
+        let (class, nullable) = sequence_first_chars(sequence)?;
+        if nullable {
+            return None;
+        }
+        first.union(&class);
+    }
+
+    Some(first)
+}
+
+/// Returns the set of characters a sequence may begin with, along with whether it may match empty input.
+fn sequence_first_chars(sequence: &[Expr]) -> Option<(CharClass, bool)> {
+    let mut first = CharClass::new();
+    for expr in sequence {
+        let (class, nullable) = expr_first_chars(expr)?;
+        first.union(&class);
+        if !nullable {
+            return Some((first, false));
+        }
+    }
+
+    Some((first, true))
+}
+
+fn expr_first_chars(expr: &Expr) -> Option<(CharClass, bool)> {
+    match expr {
+        Expr::Char(c) => Some((CharClass::from_range(*c, *c), false)),
+        Expr::Any => Some((CharClass::from_range('\0', char::MAX), false)),
+        Expr::Class(class) => Some((class.clone(), false)),
+        Expr::Field { .. } | Expr::Cut => None,
+        Expr::Group(choice) => {
+            let mut first = CharClass::new();
+            let mut nullable = false;
+            for sequence in choice {
+                let (class, sequence_nullable) = sequence_first_chars(sequence)?;
+                first.union(&class);
+                nullable |= sequence_nullable;
+            }
+            Some((first, nullable))
+        }
+        Expr::Repeat(inner, repetition) => {
+            let (class, nullable) = expr_first_chars(inner)?;
+            Some((class, nullable || repetition.min == 0))
+        }
+    }
+}
+
+/// Writes a sequence back out as a peggle expression, such as to describe it while tracing.
+///
+/// Whether a repetition gives matches back depends on the options its expression was parsed with, so the `+` that
+/// marks a possessive repetition within a backtracking expression is left out.
+pub fn sequence_text(sequence: &[Expr]) -> String {
+    sequence.iter().map(expr_text).collect()
+}
+
+fn expr_text(expr: &Expr) -> String {
+    match expr {
+        Expr::Char(c) => char_text(*c, SPECIAL_CHARS),
+        Expr::Any => ".".to_string(),
+        Expr::Class(class) => {
+            let ranges: String = class
+                .ranges()
+                .iter()
+                .map(|&(start, end)| match start == end {
+                    true => char_text(start, CLASS_SPECIAL_CHARS),
+                    false => format!(
+                        "{}-{}",
+                        char_text(start, CLASS_SPECIAL_CHARS),
+                        char_text(end, CLASS_SPECIAL_CHARS)
+                    ),
+                })
+                .collect();
+            format!("[{}]", ranges)
+        }
+        Expr::Field { name, expected } => match expected {
+            Some(expected) => format!("<{}:expected '{}'>", name, expected),
+            None => format!("<{}>", name),
+        },
+        Expr::Group(choice) => {
+            let alternatives: Vec<_> = choice.iter().map(|s| sequence_text(s)).collect();
+            format!("({})", alternatives.join("|"))
+        }
+        Expr::Repeat(inner, repetition) => {
+            let count = match (repetition.min, repetition.max) {
+                (0, usize::MAX) => "*".to_string(),
+                (1, usize::MAX) => "+".to_string(),
+                (0, 1) => "?".to_string(),
+                (min, usize::MAX) => format!("{{{},}}", min),
+                (min, max) if min == max => format!("{{{}}}", min),
+                (min, max) => format!("{{{},{}}}", min, max),
+            };
+            let mode = match repetition.mode {
+                RepeatMode::Lazy => "?",
+                RepeatMode::Possessive | RepeatMode::Greedy => "",
+            };
+            format!("{}{}{}", expr_text(inner), count, mode)
+        }
+        Expr::Cut => "~".to_string(),
+    }
+}
+
+/// Characters that must be escaped to be matched literally.
+const SPECIAL_CHARS: &str = "\\{}[]()^$.|*+?<>&~";
+
+/// Characters that must be escaped to be matched literally within a bracketed class.
+const CLASS_SPECIAL_CHARS: &str = "\\[]^-";
+
+fn char_text(c: char, special: &str) -> String {
+    match c {
+        '\n' => "\\n".to_string(),
+        '\r' => "\\r".to_string(),
+        '\t' => "\\t".to_string(),
+        c if special.contains(c) => format!("\\{}", c),
+        c if c.is_control() => format!("\\x{:02X}", c as u32),
+        c => c.to_string(),
+    }
+}
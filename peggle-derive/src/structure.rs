@@ -8,6 +8,8 @@ pub enum DeriveInfo {
     UnnamedStruct(CollectionInfo),
     /// An enum
     Enum(EnumInfo),
+    /// A struct or enum parsed as another type and converted from it (`try_from = Raw` or `map = path`)
+    Converted(ConvertedInfo),
 }
 
 impl DeriveInfo {
@@ -15,135 +17,515 @@ impl DeriveInfo {
     #[inline]
     pub fn new(ast: &syn::DeriveInput) -> Self {
         match &ast.data {
-            syn::Data::Struct(syn::DataStruct {
-                fields: syn::Fields::Named(fields),
-                ..
-            }) => {
-                let syn::Meta::List(pegex_list) = &ast.attrs.iter().find(|&a| a.path().is_ident(ATTRIBUTE_NAME))
-                    .expect("missing mandatory 'pegex' attribute for derived struct").meta else {
-                    panic!("'pegex' attribute must be a Meta::List attribute");
-                };
-
-                let pegex = pegex_list
-                    .parse_args::<syn::LitStr>()
-                    .expect("Invalid format for pegex expression: must be a string literal")
-                    .value();
+            syn::Data::Struct(_) | syn::Data::Enum(_)
+                if PegAttributes::from_attrs(&ast.attrs).conversion.is_some() =>
+            {
+                let attrs = PegAttributes::from_attrs(&ast.attrs);
+                assert!(
+                    attrs.pegex.is_none(),
+                    "a type with 'try_from' or 'map' is parsed as the type it's converted from, so it can't have its own pegex"
+                );
+                assert!(
+                    !attrs.backtrack && attrs.recover.is_none() && attrs.backend.is_none(),
+                    "'backtrack', 'recover' and 'backend' can't be applied to a type with 'try_from' or 'map'"
+                );
 
-                Self::NamedStruct(CollectionInfo {
-                    name: ast.ident.clone(),
-                    pegex,
-                    fields: fields.named.iter().map(|field| {
-                        let (inner_ty, cardinality, is_boxed) = get_inner_type(&field.ty);
-                        let field_pegex = field.attrs.iter().find(|&a| a.path().is_ident(ATTRIBUTE_NAME)).map(|attr| {
-                            let syn::Meta::List(pegex_list) = &attr.meta else {
-                                panic!("'pegex' attribute must be a Meta::List attribute");
-                            };
-                            pegex_list.parse_args::<syn::LitStr>().expect("Invalid format for pegex expression: must be a string literal").value()
-                        });
-
-                        FieldInfo {
-                            ident: field.ident.as_ref().expect("TODO named struct missing ident").to_string(),
-                            ty: field.ty.clone(),
-                            inner_ty,
-                            is_boxed,
-                            cardinality,
-                            pegex: field_pegex,
-                        }
-                    }).collect()
+                Self::Converted(ConvertedInfo {
+                    conversion: attrs.conversion.unwrap(),
+                    expected: attrs.expected,
+                    validate: attrs.validate,
+                    input: attrs.input,
+                    ctx: attrs.ctx,
                 })
             }
-            syn::Data::Struct(syn::DataStruct {
-                fields: syn::Fields::Unnamed(fields),
-                ..
-            }) => {
-                let syn::Meta::List(pegex_list) = &ast.attrs.iter().find(|&a| a.path().is_ident(ATTRIBUTE_NAME))
-                    .expect("missing mandatory 'pegex' attribute for derived struct").meta else {
-                    panic!("'pegex' attribute must be a Meta::List attribute");
-                };
-
-                let pegex = pegex_list
-                    .parse_args::<syn::LitStr>()
-                    .expect("Invalid format for pegex expression: must be a string literal")
-                    .value();
+            syn::Data::Struct(syn::DataStruct { fields, .. }) => {
+                let attrs = PegAttributes::from_attrs(&ast.attrs);
+                let pegex = attrs
+                    .pegex
+                    .expect("missing mandatory 'pegex' attribute for derived struct");
+                assert!(
+                    attrs.recover.is_none(),
+                    "'recover' patterns apply to fields and enum discriminants rather than structs"
+                );
+                assert!(
+                    attrs.default.is_none(),
+                    "'skip' and 'default' apply to fields rather than structs"
+                );
+                assert!(
+                    attrs.rename_all.is_none() && !attrs.longest && !attrs.no_dispatch,
+                    "'rename_all', 'longest' and 'no_dispatch' apply to enums rather than structs"
+                );
 
-                Self::UnnamedStruct(CollectionInfo {
+                let collection = CollectionInfo {
                     name: ast.ident.clone(),
                     pegex,
-                    fields: fields.unnamed.iter().enumerate().map(|(idx, field)| {
-                        let (inner_ty, cardinality, is_boxed) = get_inner_type(&field.ty);
-                        let field_pegex = field.attrs.iter().find(|&a| a.path().is_ident(ATTRIBUTE_NAME)).map(|attr| {
-                            let syn::Meta::List(pegex_list) = &attr.meta else {
-                                panic!("'pegex' attribute must be a Meta::List attribute");
-                            };
-                            pegex_list.parse_args::<syn::LitStr>().expect("Invalid format for pegex expression: must be a string literal").value()
-                        });
-
-                        FieldInfo {
-                            ident: idx.to_string(),
-                            ty: field.ty.clone(),
-                            inner_ty,
-                            is_boxed,
-                            cardinality,
-                            pegex: field_pegex,
-                        }
-                    }).collect()
-                })
+                    backtrack: attrs.backtrack,
+                    backend: attrs.backend.unwrap_or_default(),
+                    expected: attrs.expected,
+                    recover: None,
+                    validate: attrs.validate,
+                    input: attrs.input,
+                    ctx: attrs.ctx,
+                    named: matches!(fields, syn::Fields::Named(_)),
+                    fields: collect_fields(fields),
+                };
+
+                match fields {
+                    syn::Fields::Unnamed(_) => Self::UnnamedStruct(collection),
+                    _ => Self::NamedStruct(collection),
+                }
             }
             syn::Data::Enum(syn::DataEnum { variants, .. }) => {
+                let attrs = PegAttributes::from_attrs(&ast.attrs);
                 assert!(
-                    !ast.attrs.iter().any(|a| a.path().is_ident(ATTRIBUTE_NAME)),
+                    attrs.pegex.is_none(),
                     "'pegex' attribute applied erroneously to enum type"
                 );
+                assert!(
+                    !attrs.backtrack && attrs.recover.is_none(),
+                    "'backtrack' and 'recover' must be applied to individual enum discriminants"
+                );
+                assert!(
+                    attrs.default.is_none(),
+                    "'skip' and 'default' apply to fields rather than enums"
+                );
 
                 Self::Enum(EnumInfo {
                     name: ast.ident.clone(),
-                    discriminants: Self::collect_enum_discriminants(variants),
+                    longest: attrs.longest,
+                    dispatch: !attrs.no_dispatch,
+                    discriminants: Self::collect_enum_discriminants(variants, &attrs),
+                    expected: attrs.expected,
+                    validate: attrs.validate,
+                    input: attrs.input,
+                    ctx: attrs.ctx,
                 })
             }
             _ => panic!("derive applied to incompatible type (only structs and enums supported"),
         }
     }
 
+    /// The type of context that the derived implementation requires, if any.
+    pub fn ctx(&self) -> Option<&syn::Type> {
+        match self {
+            Self::NamedStruct(collection) | Self::UnnamedStruct(collection) => {
+                collection.ctx.as_ref()
+            }
+            Self::Enum(e) => e.ctx.as_ref(),
+            Self::Converted(c) => c.ctx.as_ref(),
+        }
+    }
+
+    /// The type of input that the derived implementation parses, or `None` if it parses any type of input.
+    pub fn input(&self) -> Option<&syn::Type> {
+        match self {
+            Self::NamedStruct(collection) | Self::UnnamedStruct(collection) => {
+                collection.input.as_ref()
+            }
+            Self::Enum(e) => e.input.as_ref(),
+            Self::Converted(c) => c.input.as_ref(),
+        }
+    }
+
+    /// The function that validates each value of the derived type once parsed, if any.
+    pub fn validate(&self) -> Option<&syn::Expr> {
+        match self {
+            Self::NamedStruct(collection) | Self::UnnamedStruct(collection) => {
+                collection.validate.as_ref()
+            }
+            Self::Enum(e) => e.validate.as_ref(),
+            Self::Converted(c) => c.validate.as_ref(),
+        }
+    }
+
     fn collect_enum_discriminants(
         variants: &syn::punctuated::Punctuated<syn::Variant, syn::token::Comma>,
+        enum_attrs: &PegAttributes,
     ) -> Vec<CollectionInfo> {
-        variants.iter().map(|variant| {
-            let syn::Meta::List(pegex_list) = &variant.attrs.iter().find(|&a| a.path().is_ident(ATTRIBUTE_NAME))
-                .expect("missing mandatory 'pegex' attribute for enum discriminant").meta else {
+        variants
+            .iter()
+            .map(|variant| {
+                let attrs = PegAttributes::from_attrs(&variant.attrs);
+
+                // Unit discriminants match their own name by default
+                let pegex = match attrs.pegex {
+                    Some(pegex) => pegex,
+                    None if matches!(variant.fields, syn::Fields::Unit) => {
+                        let name = variant.ident.to_string();
+                        match enum_attrs.rename_all {
+                            Some(rule) => rule.apply(&name),
+                            None => name,
+                        }
+                    }
+                    None => panic!(
+                        "missing mandatory 'pegex' attribute for enum discriminant with fields"
+                    ),
+                };
+                assert!(
+                    attrs.input.is_none() && attrs.ctx.is_none(),
+                    "'input' and 'ctx' must be applied to the enum type rather than its discriminants"
+                );
+
+                assert!(
+                    attrs.conversion.is_none(),
+                    "'try_from' and 'map' can't be applied to enum discriminants"
+                );
+                assert!(
+                    attrs.default.is_none(),
+                    "'skip' and 'default' apply to fields rather than enum discriminants"
+                );
+                assert!(
+                    attrs.bound.is_none()
+                        && attrs.rename_all.is_none()
+                        && !attrs.longest
+                        && !attrs.no_dispatch
+                        && attrs.backend.is_none(),
+                    "'bound', 'rename_all', 'longest', 'no_dispatch' and 'backend' must be applied to the enum type rather than its discriminants"
+                );
+
+                CollectionInfo {
+                    name: variant.ident.clone(),
+                    pegex,
+                    backtrack: attrs.backtrack,
+                    backend: enum_attrs.backend.unwrap_or_default(),
+                    expected: attrs.expected,
+                    recover: attrs.recover,
+                    validate: attrs.validate,
+                    input: enum_attrs.input.clone(),
+                    ctx: enum_attrs.ctx.clone(),
+                    named: matches!(variant.fields, syn::Fields::Named(_)),
+                    fields: collect_fields(&variant.fields),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Collects information on each of the fields of a struct or enum discriminant.
+///
+/// Named fields are identified by their name, while unnamed fields are identified by their index.
+fn collect_fields(fields: &syn::Fields) -> Vec<FieldInfo> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(idx, field)| {
+            let (inner_ty, cardinality, is_boxed) = get_inner_type(&field.ty);
+            let attrs = PegAttributes::from_attrs(&field.attrs);
+            assert!(
+                attrs.expected.is_none(),
+                "'expected' labels for fields are given within the peggle expression (`<field:expected 'label'>`)"
+            );
+            assert!(
+                attrs.input.is_none() && attrs.ctx.is_none(),
+                "'input' and 'ctx' must be applied to the struct or enum type rather than its fields"
+            );
+            assert!(
+                attrs.bound.is_none()
+                    && attrs.rename_all.is_none()
+                    && !attrs.longest
+                    && !attrs.no_dispatch
+                    && attrs.backend.is_none(),
+                "'bound', 'rename_all', 'longest', 'no_dispatch' and 'backend' must be applied to the struct or enum type rather than its fields"
+            );
+            assert!(
+                attrs.default.is_none()
+                    || (attrs.pegex.is_none()
+                        && !attrs.backtrack
+                        && attrs.recover.is_none()
+                        && attrs.validate.is_none()
+                        && attrs.conversion.is_none()),
+                "a field with 'skip' or 'default' isn't parsed, so it can't have parsing options"
+            );
+
+            FieldInfo {
+                ident: match &field.ident {
+                    Some(ident) => ident.to_string(),
+                    None => idx.to_string(),
+                },
+                ty: field.ty.clone(),
+                inner_ty,
+                is_boxed,
+                cardinality,
+                pegex: attrs.pegex,
+                backtrack: attrs.backtrack,
+                recover: attrs.recover,
+                validate: attrs.validate,
+                conversion: attrs.conversion,
+                default: attrs.default,
+            }
+        })
+        .collect()
+}
+
+/// The options supplied within the `#[peg(...)]` attribute(s) of a struct, enum discriminant or field.
+///
+/// An attribute may contain a peggle expression as a string literal, followed by any number of comma-separated
+/// options (e.g. `#[peg("a*ab", backtrack)]`) or `key = value` settings (e.g. `#[peg(expected = "a number")]`);
+/// these may also be given in separate `#[peg(...)]` attributes.
+#[derive(Default)]
+pub struct PegAttributes {
+    /// The peggle expression to be matched
+    pub pegex: Option<String>,
+    /// Whether plain repetitions within the peggle expression use regex-style backtracking (`backtrack`)
+    pub backtrack: bool,
+    /// Whether an enum tries every discriminant and chooses the one that matches the most input (`longest`)
+    pub longest: bool,
+    /// Whether an enum tries each discriminant in turn rather than dispatching on the next character (`no_dispatch`)
+    pub no_dispatch: bool,
+    /// A description of the item used in error messages when it fails to parse (`expected = "..."`)
+    pub expected: Option<String>,
+    /// A synchronization pattern skipped over to recover from errors (`recover = "..."`)
+    pub recover: Option<String>,
+    /// The type of input the derived implementation is restricted to (`input = "str"`); any type by default
+    pub input: Option<syn::Type>,
+    /// The type of user-defined context the derived implementation requires (`ctx = MyCtx`)
+    pub ctx: Option<syn::Type>,
+    /// A function that checks each parsed value, rejecting it with a message (`validate = path::to::fn`)
+    pub validate: Option<syn::Expr>,
+    /// How the item is converted from the type it is parsed as (`try_from = Raw` or `map = path::to::fn`)
+    pub conversion: Option<Conversion>,
+    /// The value of a field that is left out of the peggle expression (`default = expr`, or `skip`/`default` for
+    /// `Default::default()`)
+    pub default: Option<syn::Expr>,
+    /// Where-predicates that replace the bounds inferred for the type's generic parameters (`bound = "T: Parse"`)
+    pub bound: Option<Vec<syn::WherePredicate>>,
+    /// How the names of unit discriminants are cased when matched by default (`rename_all = "snake_case"`)
+    pub rename_all: Option<RenameRule>,
+    /// How the type's peggle expressions are turned into code (`backend = "vm"`)
+    pub backend: Option<Backend>,
+}
+
+impl PegAttributes {
+    pub fn from_attrs(attrs: &[syn::Attribute]) -> Self {
+        let mut peg_attrs = Self::default();
+
+        for attr in attrs.iter().filter(|&a| a.path().is_ident(ATTRIBUTE_NAME)) {
+            let syn::Meta::List(pegex_list) = &attr.meta else {
                 panic!("'pegex' attribute must be a Meta::List attribute");
             };
 
-            let pegex = pegex_list.parse_args::<syn::LitStr>().expect("Invalid format for pegex expression: must be a string literal").value();
-
-            CollectionInfo {
-                name: variant.ident.clone(),
-                pegex,
-                fields: variant.fields.iter().enumerate().map(|(idx, field)| {
-                    let (inner_ty, cardinality, is_boxed) = get_inner_type(&field.ty);
-                    let field_pegex = field.attrs.iter().find(|&a| a.path().is_ident(ATTRIBUTE_NAME)).map(|attr| {
-                        let syn::Meta::List(pegex_list) = &attr.meta else {
-                            panic!("'pegex' attribute must be a Meta::List attribute");
-                        };
-                        pegex_list.parse_args::<syn::LitStr>().expect("Invalid format for pegex expression: must be a string literal").value()
-                    });
-
-                    FieldInfo {
-                        ident: idx.to_string(),
-                        ty: field.ty.clone(),
-                        is_boxed,
-                        inner_ty,
-                        cardinality,
-                        pegex: field_pegex,
-                   }
-                }).collect()
+            let arguments = pegex_list
+                .parse_args_with(
+                    syn::punctuated::Punctuated::<PegArgument, syn::Token![,]>::parse_terminated,
+                )
+                .expect("Invalid format for pegex attribute: must be a string literal and/or a list of options");
+
+            for argument in arguments {
+                match argument {
+                    PegArgument::Pegex(pegex) => {
+                        assert!(
+                            peg_attrs.pegex.is_none(),
+                            "multiple pegex expressions supplied for a single item"
+                        );
+                        peg_attrs.pegex = Some(pegex.value());
+                    }
+                    PegArgument::Option(option) => match option.to_string().as_str() {
+                        "backtrack" => peg_attrs.backtrack = true,
+                        "longest" => peg_attrs.longest = true,
+                        "no_dispatch" => peg_attrs.no_dispatch = true,
+                        "skip" | "default" => {
+                            assert!(
+                                peg_attrs.default.is_none(),
+                                "multiple default values supplied for a single item"
+                            );
+                            peg_attrs.default =
+                                Some(syn::parse_quote! { ::std::default::Default::default() });
+                        }
+                        other => panic!("unrecognized peg option '{}'", other),
+                    },
+                    PegArgument::Value(key, value) => match key.to_string().as_str() {
+                        "expected" => {
+                            assert!(
+                                peg_attrs.expected.is_none(),
+                                "multiple 'expected' labels supplied for a single item"
+                            );
+                            peg_attrs.expected = Some(string_value(&key, &value));
+                        }
+                        "recover" => {
+                            assert!(
+                                peg_attrs.recover.is_none(),
+                                "multiple 'recover' patterns supplied for a single item"
+                            );
+                            peg_attrs.recover = Some(string_value(&key, &value));
+                        }
+                        "input" => {
+                            assert!(
+                                peg_attrs.input.is_none(),
+                                "multiple 'input' types supplied for a single item"
+                            );
+                            peg_attrs.input = Some(type_value(&key, &value));
+                        }
+                        "ctx" => {
+                            assert!(
+                                peg_attrs.ctx.is_none(),
+                                "multiple 'ctx' types supplied for a single item"
+                            );
+                            peg_attrs.ctx = Some(type_value(&key, &value));
+                        }
+                        "validate" => {
+                            assert!(
+                                peg_attrs.validate.is_none(),
+                                "multiple 'validate' functions supplied for a single item"
+                            );
+                            peg_attrs.validate = Some(path_value(&key, &value));
+                        }
+                        "default" => {
+                            assert!(
+                                peg_attrs.default.is_none(),
+                                "multiple default values supplied for a single item"
+                            );
+                            peg_attrs.default = Some(value);
+                        }
+                        "rename_all" => {
+                            assert!(
+                                peg_attrs.rename_all.is_none(),
+                                "multiple 'rename_all' rules supplied for a single item"
+                            );
+                            let rule = string_value(&key, &value);
+                            peg_attrs.rename_all =
+                                Some(RenameRule::from_str(&rule).unwrap_or_else(|| {
+                                    panic!("unrecognized 'rename_all' rule '{}'", rule)
+                                }));
+                        }
+                        "backend" => {
+                            assert!(
+                                peg_attrs.backend.is_none(),
+                                "multiple 'backend' settings supplied for a single item"
+                            );
+                            let backend = string_value(&key, &value);
+                            peg_attrs.backend =
+                                Some(Backend::from_str(&backend).unwrap_or_else(|| {
+                                    panic!("unrecognized 'backend' '{}'", backend)
+                                }));
+                        }
+                        "bound" => {
+                            assert!(
+                                peg_attrs.bound.is_none(),
+                                "multiple 'bound' settings supplied for a single item"
+                            );
+                            peg_attrs.bound = Some(bound_value(&key, &value));
+                        }
+                        "try_from" | "map" => {
+                            assert!(
+                                peg_attrs.conversion.is_none(),
+                                "only one of 'try_from' or 'map' may be supplied for a single item"
+                            );
+                            peg_attrs.conversion = Some(match key.to_string().as_str() {
+                                "try_from" => Conversion::TryFrom(type_value(&key, &value)),
+                                _ => Conversion::Map(path_value(&key, &value)),
+                            });
+                        }
+                        other => panic!("unrecognized peg setting '{}'", other),
+                    },
+                }
             }
-        }).collect()
+        }
+
+        peg_attrs
+    }
+}
+
+/// Extracts the string literal assigned to a `key = "value"` setting.
+fn string_value(key: &syn::Ident, value: &syn::Expr) -> String {
+    match value {
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(s),
+            ..
+        }) => s.value(),
+        _ => panic!("peg setting '{}' must be a string literal", key),
+    }
+}
+
+/// Extracts the type assigned to a `key = Type` or `key = "Type"` setting.
+fn type_value(key: &syn::Ident, value: &syn::Expr) -> syn::Type {
+    let parsed = match value {
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(s),
+            ..
+        }) => s.parse(),
+        _ => syn::parse2(quote::ToTokens::to_token_stream(value)),
+    };
+
+    parsed.unwrap_or_else(|_| panic!("peg setting '{}' must be a type", key))
+}
+
+/// Extracts the where-predicates assigned to a `key = "T: Trait, U: Trait"` setting.
+fn bound_value(key: &syn::Ident, value: &syn::Expr) -> Vec<syn::WherePredicate> {
+    let predicates = string_value(key, value);
+    let parser =
+        syn::punctuated::Punctuated::<syn::WherePredicate, syn::Token![,]>::parse_terminated;
+
+    syn::parse::Parser::parse_str(parser, &predicates)
+        .unwrap_or_else(|_| panic!("peg setting '{}' must be a list of where-predicates", key))
+        .into_iter()
+        .collect()
+}
+
+/// Extracts the function assigned to a `key = path::to::fn` or `key = "path::to::fn"` setting.
+fn path_value(key: &syn::Ident, value: &syn::Expr) -> syn::Expr {
+    match value {
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(s),
+            ..
+        }) => s
+            .parse()
+            .unwrap_or_else(|_| panic!("peg setting '{}' must be a path to a function", key)),
+        _ => value.clone(),
+    }
+}
+
+/// A single comma-separated argument within a `#[peg(...)]` attribute.
+enum PegArgument {
+    Pegex(syn::LitStr),
+    Option(syn::Ident),
+    Value(syn::Ident, syn::Expr),
+}
+
+impl syn::parse::Parse for PegArgument {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        if input.peek(syn::LitStr) {
+            return Ok(Self::Pegex(input.parse()?));
+        }
+
+        let ident: syn::Ident = input.parse()?;
+        if input.peek(syn::Token![=]) {
+            input.parse::<syn::Token![=]>()?;
+            Ok(Self::Value(ident, input.parse()?))
+        } else {
+            Ok(Self::Option(ident))
+        }
     }
 }
 
+/// How a value is obtained from a value of another type that is parsed in its place.
+#[derive(Clone)]
+pub enum Conversion {
+    /// Converted with `TryFrom<Raw>`, rejecting the input with the conversion error's message upon failure
+    TryFrom(syn::Type),
+    /// Converted by calling the given function with the parsed value (whose type is that of the function's argument)
+    Map(syn::Expr),
+}
+
+/// Information on a type parsed as another type and converted from it.
+pub struct ConvertedInfo {
+    pub conversion: Conversion,
+    pub expected: Option<String>,
+    pub validate: Option<syn::Expr>,
+    pub input: Option<syn::Type>,
+    pub ctx: Option<syn::Type>,
+}
+
 /// Information on an `enum` element.
 pub struct EnumInfo {
     pub name: syn::Ident,
+    pub expected: Option<String>,
+    pub validate: Option<syn::Expr>,
+    pub input: Option<syn::Type>,
+    pub ctx: Option<syn::Type>,
+    /// Whether the discriminant matching the most input is chosen, rather than the first to match
+    pub longest: bool,
+    /// Whether discriminants that can't match the next character are skipped without being tried
+    pub dispatch: bool,
     pub discriminants: Vec<CollectionInfo>,
 }
 
@@ -152,6 +534,16 @@ pub struct EnumInfo {
 pub struct CollectionInfo {
     pub name: syn::Ident,
     pub pegex: String,
+    pub backtrack: bool,
+    /// How the peggle expressions of the element and its fields are turned into code
+    pub backend: Backend,
+    pub expected: Option<String>,
+    pub recover: Option<String>,
+    pub validate: Option<syn::Expr>,
+    pub input: Option<syn::Type>,
+    pub ctx: Option<syn::Type>,
+    /// Whether the fields are named (as in `Foo { a: u32 }`) rather than positional
+    pub named: bool,
     pub fields: Vec<FieldInfo>,
 }
 
@@ -164,6 +556,99 @@ pub struct FieldInfo {
     pub is_boxed: bool,
     pub cardinality: FieldCardinality,
     pub pegex: Option<String>,
+    pub backtrack: bool,
+    pub recover: Option<String>,
+    pub validate: Option<syn::Expr>,
+    pub conversion: Option<Conversion>,
+    /// The value given to the field instead of parsing it, if it's left out of the peggle expression
+    pub default: Option<syn::Expr>,
+}
+
+/// A rule for converting the (PascalCase) name of a unit discriminant into the text it matches by default.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum RenameRule {
+    /// `lowercase`
+    Lower,
+    /// `UPPERCASE`
+    Upper,
+    /// `PascalCase`, leaving the name as it is
+    Pascal,
+    /// `camelCase`
+    Camel,
+    /// `snake_case`
+    Snake,
+    /// `SCREAMING_SNAKE_CASE`
+    ScreamingSnake,
+    /// `kebab-case`
+    Kebab,
+    /// `SCREAMING-KEBAB-CASE`
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    pub fn from_str(rule: &str) -> Option<Self> {
+        Some(match rule {
+            "lowercase" => Self::Lower,
+            "UPPERCASE" => Self::Upper,
+            "PascalCase" => Self::Pascal,
+            "camelCase" => Self::Camel,
+            "snake_case" => Self::Snake,
+            "SCREAMING_SNAKE_CASE" => Self::ScreamingSnake,
+            "kebab-case" => Self::Kebab,
+            "SCREAMING-KEBAB-CASE" => Self::ScreamingKebab,
+            _ => return None,
+        })
+    }
+
+    pub fn apply(self, name: &str) -> String {
+        let snake = || {
+            let mut snake = String::new();
+            for (idx, c) in name.char_indices() {
+                if c.is_uppercase() && idx > 0 {
+                    snake.push('_');
+                }
+                snake.extend(c.to_lowercase());
+            }
+            snake
+        };
+
+        match self {
+            Self::Lower => name.to_lowercase(),
+            Self::Upper => name.to_uppercase(),
+            Self::Pascal => name.to_string(),
+            Self::Camel => {
+                let mut chars = name.chars();
+                chars
+                    .next()
+                    .map(|first| first.to_lowercase().chain(chars).collect())
+                    .unwrap_or_default()
+            }
+            Self::Snake => snake(),
+            Self::ScreamingSnake => snake().to_uppercase(),
+            Self::Kebab => snake().replace('_', "-"),
+            Self::ScreamingKebab => snake().replace('_', "-").to_uppercase(),
+        }
+    }
+}
+
+/// How the peggle expressions of a type are turned into code.
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// Each expression is expanded into nested blocks and loops (`inline`)
+    #[default]
+    Inline,
+    /// Each expression is compiled into a static table of instructions run by `peggle::vm` (`vm`)
+    Vm,
+}
+
+impl Backend {
+    pub fn from_str(backend: &str) -> Option<Self> {
+        match backend {
+            "inline" => Some(Self::Inline),
+            "vm" => Some(Self::Vm),
+            _ => None,
+        }
+    }
 }
 
 /// the "cardinality" of a field, or the minimum/maximum number of times that field is permitted to match in a PEG.
@@ -198,7 +683,11 @@ fn get_inner_type(ty: &syn::Type) -> (syn::Type, FieldCardinality, bool) {
             for arg in &angle_args.args {
                 if let syn::GenericArgument::Type(ty) = arg {
                     let syn::Type::Path(tp) = ty else {
-                        return (ty.clone(), cardinality, cardinality == FieldCardinality::Single) // Single => had Box wrapping outside, otherwise not
+                        return (
+                            ty.clone(),
+                            cardinality,
+                            cardinality == FieldCardinality::Single,
+                        ); // Single => had Box wrapping outside, otherwise not
                     };
 
                     let inner_final_segment = tp.path.segments.last().unwrap();
@@ -210,7 +699,9 @@ fn get_inner_type(ty: &syn::Type) -> (syn::Type, FieldCardinality, bool) {
                         );
                     }
 
-                    let syn::PathArguments::AngleBracketed(inner_angle_args) = &inner_final_segment.arguments else {
+                    let syn::PathArguments::AngleBracketed(inner_angle_args) =
+                        &inner_final_segment.arguments
+                    else {
                         panic!("Parantheses-bracketed Type args not supported");
                     };
 
@@ -0,0 +1,383 @@
+use crate::pattern::{Choice, Expr, RepeatMode, Repetition, Sequence};
+use crate::structure::FieldInfo;
+
+/// An instruction of a compiled peggle expression, mirroring `peggle::vm::Inst`.
+enum Inst {
+    Char(char),
+    Literal(String),
+    Any,
+    Class(Vec<(char, char)>),
+    Field {
+        field: usize,
+        expected: Option<String>,
+    },
+    Choice(usize),
+    Split(usize),
+    Commit(usize),
+    Loop(usize),
+    Jump(usize),
+    Counter,
+    Count {
+        target: usize,
+        min: usize,
+        max: usize,
+    },
+    PopCounter(usize),
+    Rewind,
+    Cut,
+    Fail,
+    Match,
+}
+
+impl quote::ToTokens for Inst {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        tokens.extend(match self {
+            Inst::Char(c) => quote::quote! { peggle::vm::Inst::Char(#c) },
+            Inst::Literal(literal) => quote::quote! { peggle::vm::Inst::Literal(#literal) },
+            Inst::Any => quote::quote! { peggle::vm::Inst::Any },
+            Inst::Class(ranges) => {
+                let ranges = ranges
+                    .iter()
+                    .map(|(start, end)| quote::quote! { (#start, #end) });
+                quote::quote! { peggle::vm::Inst::Class(&[#(#ranges),*]) }
+            }
+            Inst::Field { field, expected } => {
+                let expected = match expected {
+                    Some(expected) => quote::quote! { Some(#expected) },
+                    None => quote::quote! { None },
+                };
+                quote::quote! { peggle::vm::Inst::Field { field: #field, expected: #expected } }
+            }
+            Inst::Choice(target) => quote::quote! { peggle::vm::Inst::Choice(#target) },
+            Inst::Split(target) => quote::quote! { peggle::vm::Inst::Split(#target) },
+            Inst::Commit(target) => quote::quote! { peggle::vm::Inst::Commit(#target) },
+            Inst::Loop(target) => quote::quote! { peggle::vm::Inst::Loop(#target) },
+            Inst::Jump(target) => quote::quote! { peggle::vm::Inst::Jump(#target) },
+            Inst::Counter => quote::quote! { peggle::vm::Inst::Counter },
+            Inst::Count { target, min, max } => quote::quote! {
+                peggle::vm::Inst::Count { target: #target, min: #min, max: #max }
+            },
+            Inst::PopCounter(min) => quote::quote! { peggle::vm::Inst::PopCounter(#min) },
+            Inst::Rewind => quote::quote! { peggle::vm::Inst::Rewind },
+            Inst::Cut => quote::quote! { peggle::vm::Inst::Cut },
+            Inst::Fail => quote::quote! { peggle::vm::Inst::Fail },
+            Inst::Match => quote::quote! { peggle::vm::Inst::Match },
+        });
+    }
+}
+
+/// Compiles a parsed peggle expression into the instructions of a `peggle::vm` program.
+///
+/// Fields are numbered by their position within `fields`. The semantics are those of the code generated for the
+/// inline backend: alternatives, groups and each iteration of a repetition are atomic once matched, so a
+/// backtracking repetition only ever retries the remainder of its own sequence, and a cut lasts until the end of
+/// the sequence it appears in.
+pub fn compile(choice: &Choice, fields: &[FieldInfo]) -> proc_macro2::TokenStream {
+    let mut compiler = Compiler {
+        fields,
+        program: Vec::new(),
+    };
+    compiler.choice(choice);
+    compiler.program.push(Inst::Match);
+
+    let program = compiler.program;
+    quote::quote! { &[#(#program),*] }
+}
+
+struct Compiler<'a> {
+    fields: &'a [FieldInfo],
+    program: Vec<Inst>,
+}
+
+impl Compiler<'_> {
+    /// Adds an instruction, returning its position so that its jump target can be filled in later.
+    fn emit(&mut self, inst: Inst) -> usize {
+        self.program.push(inst);
+        self.program.len() - 1
+    }
+
+    /// Points the jump instruction at `at` to the next instruction to be emitted.
+    fn patch(&mut self, at: usize) {
+        let next = self.program.len();
+        match &mut self.program[at] {
+            Inst::Choice(target)
+            | Inst::Split(target)
+            | Inst::Commit(target)
+            | Inst::Loop(target)
+            | Inst::Jump(target)
+            | Inst::Count { target, .. } => *target = next,
+            _ => unreachable!("only jumps have a target"),
+        }
+    }
+
+    fn choice(&mut self, choice: &[Sequence]) {
+        let mut commits = Vec::new();
+
+        for (idx, sequence) in choice.iter().enumerate() {
+            if idx + 1 == choice.len() {
+                self.atomic(leaves_frames(sequence), |compiler| {
+                    compiler.sequence(sequence)
+                });
+            } else {
+                let choice_at = self.emit(Inst::Choice(0));
+                self.sequence(sequence);
+                commits.push(self.emit(Inst::Commit(0)));
+                self.patch(choice_at);
+            }
+        }
+
+        for commit in commits {
+            self.patch(commit);
+        }
+    }
+
+    /// Emits the instructions of `body`, discarding any positions it saved once it has matched if `needed`.
+    fn atomic(&mut self, needed: bool, body: impl FnOnce(&mut Self)) {
+        if !needed {
+            body(self);
+            return;
+        }
+
+        let choice_at = self.emit(Inst::Choice(0));
+        body(self);
+        let commit_at = self.emit(Inst::Commit(0));
+        self.patch(choice_at);
+        self.emit(Inst::Fail);
+        self.patch(commit_at);
+    }
+
+    fn sequence(&mut self, sequence: &[Expr]) {
+        let mut idx = 0;
+        while idx < sequence.len() {
+            let expr = &sequence[idx];
+            idx += 1;
+
+            match expr {
+                Expr::Char(first) if matches!(sequence.get(idx), Some(Expr::Char(_))) => {
+                    let mut literal = first.to_string();
+                    while let Some(Expr::Char(c)) = sequence.get(idx) {
+                        literal.push(*c);
+                        idx += 1;
+                    }
+                    self.emit(Inst::Literal(literal));
+                }
+                _ => self.expr(expr),
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Char(c) => {
+                self.emit(Inst::Char(*c));
+            }
+            Expr::Any => {
+                self.emit(Inst::Any);
+            }
+            Expr::Class(class) => {
+                self.emit(Inst::Class(class.ranges().to_vec()));
+            }
+            Expr::Field { name, expected } => {
+                let Some(field) = self.fields.iter().position(|f| &f.ident == name) else {
+                    panic!("Unrecognized field {}", name)
+                };
+                self.emit(Inst::Field {
+                    field,
+                    expected: expected.clone(),
+                });
+            }
+            Expr::Group(choice) => self.choice(choice),
+            Expr::Repeat(inner, repetition) => self.repeat(inner, *repetition),
+            Expr::Cut => {
+                self.emit(Inst::Cut);
+            }
+        }
+    }
+
+    fn repeat(&mut self, inner: &Expr, repetition: Repetition) {
+        let Repetition { min, max, mode } = repetition;
+
+        // A repetition that would need more than one copy of its expression counts its iterations instead, so that
+        // its size doesn't depend on its bounds. So does a lazy one with an upper bound, which fails once the rest
+        // of the sequence fails after its last iteration.
+        let copies = if max == usize::MAX { min } else { max };
+        if copies > 1 || (mode == RepeatMode::Lazy && max != usize::MAX) {
+            self.counted_repeat(inner, repetition);
+            return;
+        }
+
+        if min == 1 {
+            self.iteration(inner);
+        }
+
+        if max == usize::MAX {
+            self.unbounded_repeat(inner, mode);
+            return;
+        }
+
+        if min == 0 {
+            let needed = matches!(inner, Expr::Repeat(_, r) if r.mode.backtracks());
+            match mode {
+                RepeatMode::Possessive => {
+                    // A failed match leaves the repetition; a successful one is never given back
+                    let choice_at = self.emit(Inst::Choice(0));
+                    self.expr(inner);
+                    let commit_at = self.emit(Inst::Commit(0));
+                    self.patch(choice_at);
+                    self.patch(commit_at);
+                }
+                RepeatMode::Greedy => {
+                    // Matching is tried first, saving the position to give the match back at
+                    let split_at = self.emit(Inst::Split(0));
+                    self.atomic(needed, |compiler| compiler.expr(inner));
+                    self.patch(split_at);
+                }
+                RepeatMode::Lazy => {
+                    unreachable!("lazy repetitions with an upper bound are counted")
+                }
+            }
+        }
+    }
+
+    /// Emits a required iteration of a repetition, which fails (at the position it started from, as the repetition
+    /// ends after its last matched iteration) should its expression fail to match.
+    fn iteration(&mut self, inner: &Expr) {
+        if matches!(
+            inner,
+            Expr::Char(_) | Expr::Any | Expr::Class(_) | Expr::Field { .. }
+        ) {
+            // These fail without consuming anything, so already fail where they started
+            self.expr(inner);
+            return;
+        }
+
+        let choice_at = self.emit(Inst::Choice(0));
+        self.expr(inner);
+        let commit_at = self.emit(Inst::Commit(0));
+        self.patch(choice_at);
+        self.emit(Inst::Rewind);
+        self.emit(Inst::Fail);
+        self.patch(commit_at);
+    }
+
+    /// Emits a repetition that counts its iterations, repeating its expression until `max` iterations have matched
+    /// (or one consumed nothing past the minimum) and failing should fewer than `min` match. Each iteration is made
+    /// atomic by a `Choice` that the closing `Count` discards. A failure of the repetition itself is reported at the
+    /// end of its last matched iteration.
+    fn counted_repeat(&mut self, inner: &Expr, repetition: Repetition) {
+        let Repetition { min, max, mode } = repetition;
+
+        self.emit(Inst::Counter);
+        let loop_start = self.program.len();
+
+        match mode {
+            RepeatMode::Possessive => {
+                // A failed match leaves the repetition; a successful one is never given back
+                let choice_at = self.emit(Inst::Choice(0));
+                self.expr(inner);
+                self.emit(Inst::Count {
+                    target: loop_start,
+                    min,
+                    max,
+                });
+                self.patch(choice_at);
+                self.emit(Inst::Rewind);
+            }
+            RepeatMode::Greedy => {
+                // Matching is tried first, saving the position (and count) to give the match back at
+                let split_at = self.emit(Inst::Split(0));
+                let choice_at = self.emit(Inst::Choice(0));
+                self.expr(inner);
+                self.emit(Inst::Count {
+                    target: loop_start,
+                    min,
+                    max,
+                });
+                let exit_at = self.emit(Inst::Jump(0));
+                self.patch(choice_at);
+                self.emit(Inst::Rewind);
+                self.emit(Inst::Fail);
+                self.patch(split_at);
+                self.patch(exit_at);
+            }
+            RepeatMode::Lazy => {
+                // The rest of the sequence is tried first (which fails until the minimum is reached), saving the
+                // position to take another match at. Once the maximum is reached, the rest is tried one last time,
+                // failing at the end of the last iteration should it not match.
+                let split_at = self.emit(Inst::Split(0));
+                let rest_at = self.emit(Inst::Jump(0));
+                self.patch(split_at);
+                let choice_at = self.emit(Inst::Choice(0));
+                self.expr(inner);
+                self.emit(Inst::Count {
+                    target: loop_start,
+                    min,
+                    max,
+                });
+                let last_at = self.emit(Inst::Split(0));
+                let last_rest_at = self.emit(Inst::Jump(0));
+                self.patch(choice_at);
+                self.patch(last_at);
+                self.emit(Inst::Rewind);
+                self.emit(Inst::Fail);
+                self.patch(rest_at);
+                self.patch(last_rest_at);
+            }
+        }
+
+        self.emit(Inst::PopCounter(min));
+    }
+
+    /// Emits the optional iterations of a repetition without an upper bound. Each iteration is made atomic by a
+    /// `Choice` that the closing `Loop` discards, which also ends the repetition should the iteration consume nothing.
+    fn unbounded_repeat(&mut self, inner: &Expr, mode: RepeatMode) {
+        let loop_start = self.program.len();
+
+        match mode {
+            RepeatMode::Possessive => {
+                // A failed match leaves the repetition; a successful one is never given back
+                let choice_at = self.emit(Inst::Choice(0));
+                self.expr(inner);
+                self.emit(Inst::Loop(loop_start));
+                self.patch(choice_at);
+            }
+            RepeatMode::Greedy => {
+                // Matching is tried first, saving the position to give the match back at
+                let split_at = self.emit(Inst::Split(0));
+                let choice_at = self.emit(Inst::Choice(0));
+                self.expr(inner);
+                self.emit(Inst::Loop(loop_start));
+                let exit_at = self.emit(Inst::Jump(0));
+                self.patch(choice_at);
+                self.emit(Inst::Fail);
+                self.patch(split_at);
+                self.patch(exit_at);
+            }
+            RepeatMode::Lazy => {
+                // The rest of the sequence is tried first, saving the position to take another match at, and the
+                // repetition fails at the end of its last iteration should another not match. Taking a match that
+                // consumed nothing would only retry the rest where it has already failed.
+                let split_at = self.emit(Inst::Split(0));
+                let exit_at = self.emit(Inst::Jump(0));
+                self.patch(split_at);
+                let choice_at = self.emit(Inst::Choice(0));
+                self.expr(inner);
+                self.emit(Inst::Loop(loop_start));
+                self.patch(choice_at);
+                self.emit(Inst::Rewind);
+                self.emit(Inst::Fail);
+                self.patch(exit_at);
+            }
+        }
+    }
+}
+
+/// Whether matching `sequence` may leave saved positions behind once it has matched (from a cut, or a repetition
+/// that may give matches back), which must be discarded for it to be atomic.
+fn leaves_frames(sequence: &[Expr]) -> bool {
+    sequence.iter().any(|expr| match expr {
+        Expr::Cut => true,
+        Expr::Repeat(_, repetition) => repetition.mode.backtracks(),
+        _ => false,
+    })
+}
@@ -0,0 +1,152 @@
+use peggle::Parse;
+use peggle_derive::Parse;
+
+macro_rules! pass {
+    ($test_name:ident,$st_name:ident,$input:literal) => {
+        #[test]
+        fn $test_name() {
+            if let Err(_) = $st_name::parse($input) {
+                panic!()
+            }
+        }
+    };
+}
+
+macro_rules! fail {
+    ($test_name:ident,$st_name:ident,$input:literal) => {
+        #[test]
+        fn $test_name() {
+            if let Ok(_) = $st_name::parse($input) {
+                panic!()
+            }
+        }
+    };
+}
+
+#[derive(Debug, Parse)]
+#[peg("a*ab", backtrack)]
+pub struct TestBacktrack01 {}
+
+pass!(greedy_backtrack_one_match, TestBacktrack01, "ab");
+pass!(greedy_backtrack_multi_match, TestBacktrack01, "aaab");
+fail!(greedy_backtrack_nomatch1, TestBacktrack01, "b");
+fail!(greedy_backtrack_nomatch2, TestBacktrack01, "aaa");
+
+#[derive(Debug, Parse)]
+#[peg("a*+ab", backtrack)]
+pub struct TestBacktrack02 {}
+
+fail!(possessive_in_backtrack_nomatch, TestBacktrack02, "aaab");
+
+#[derive(Debug, Parse)]
+#[peg("a*?ab")]
+pub struct TestBacktrack03 {}
+
+pass!(lazy_one_match, TestBacktrack03, "ab");
+pass!(lazy_multi_match, TestBacktrack03, "aaab");
+fail!(lazy_nomatch, TestBacktrack03, "aaa");
+
+#[derive(Debug, Parse)]
+#[peg("x(a|b){2,3}b", backtrack)]
+pub struct TestBacktrack04 {}
+
+pass!(greedy_group_range_match1, TestBacktrack04, "xabb");
+pass!(greedy_group_range_match2, TestBacktrack04, "xbab");
+pass!(greedy_group_range_match3, TestBacktrack04, "xaab");
+fail!(greedy_group_range_nomatch1, TestBacktrack04, "xab");
+fail!(greedy_group_range_nomatch2, TestBacktrack04, "xababb");
+
+#[derive(Debug, Parse)]
+#[peg("x(ab|a){2}b", backtrack)]
+pub struct TestBacktrack09 {}
+
+// Only the number of repetitions is backtracked; the choice made within each repetition is not revisited
+fail!(greedy_group_choice_nomatch, TestBacktrack09, "xabab");
+
+#[derive(Debug, Parse)]
+#[peg("a{2,}?a")]
+pub struct TestBacktrack05 {}
+
+pass!(lazy_range_match, TestBacktrack05, "aaa");
+fail!(lazy_range_nomatch1, TestBacktrack05, "aa");
+// The repetition takes as few matches as it can, so the remaining input is left unmatched
+fail!(lazy_range_nomatch2, TestBacktrack05, "aaaaa");
+
+#[derive(Debug, Parse)]
+#[peg("(<first>,)*<last>,", backtrack)]
+pub struct TestBacktrack06 {
+    pub first: Vec<u8>,
+    pub last: u8,
+}
+
+#[test]
+fn greedy_field_given_back() {
+    let parsed = TestBacktrack06::parse("1,2,3,").expect("backtracking field should match");
+    assert_eq!(parsed.first, vec![1, 2]);
+    assert_eq!(parsed.last, 3);
+}
+
+#[derive(Debug, Parse)]
+#[peg("<key>=<value>")]
+pub struct TestBacktrack07 {
+    #[peg("[a-z]+")]
+    pub key: String,
+    #[peg(".*;", backtrack)]
+    pub value: String,
+}
+
+#[test]
+fn greedy_field_pattern() {
+    let parsed = TestBacktrack07::parse("abc=d;e;").expect("greedy field pattern should match");
+    assert_eq!(parsed.key, "abc");
+    assert_eq!(parsed.value, "d;e;");
+}
+
+#[derive(Debug, Parse)]
+#[peg("<key>=<value><rest>")]
+pub struct TestBacktrack10 {
+    #[peg("[a-z]+")]
+    pub key: String,
+    #[peg(".*?;")]
+    pub value: String,
+    #[peg(".*")]
+    pub rest: String,
+}
+
+#[test]
+fn lazy_field_pattern() {
+    let parsed = TestBacktrack10::parse("abc=d;e;").expect("lazy field pattern should match");
+    assert_eq!(parsed.key, "abc");
+    assert_eq!(parsed.value, "d;");
+    assert_eq!(parsed.rest, "e;");
+}
+
+#[derive(Debug, Parse)]
+#[peg("<digits>?[0-9]", backtrack)]
+pub struct TestBacktrack08 {
+    #[peg("[0-9]+")]
+    pub digits: Option<String>,
+}
+
+#[test]
+fn optional_field_unassigned_on_backtrack() {
+    let parsed = TestBacktrack08::parse("7").expect("optional field should be given back");
+    assert_eq!(parsed.digits, None);
+}
+
+#[derive(Debug, Parse)]
+#[peg("<name>a*ab", backtrack)]
+pub struct TestBacktrack11 {
+    #[peg("[xyz]+")]
+    pub name: String,
+    #[peg(skip)]
+    pub id: u32,
+}
+
+#[test]
+fn skipped_field_with_backtracking() {
+    let parsed =
+        TestBacktrack11::parse("xyaaab").expect("skipped field should be left out of backtracking");
+    assert_eq!(parsed.name, "xy");
+    assert_eq!(parsed.id, 0);
+}
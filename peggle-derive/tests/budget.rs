@@ -0,0 +1,115 @@
+use std::time::{Duration, Instant};
+
+use peggle::{ErrorKind, Parse, ParseState};
+use peggle_derive::Parse;
+
+#[derive(Debug, PartialEq, Parse)]
+#[peg("(<words> )*")]
+pub struct Words {
+    words: Vec<Word>,
+}
+
+#[derive(Debug, PartialEq, Parse)]
+#[peg("<0>")]
+pub struct Word(#[peg("[a-z]+")] String);
+
+#[derive(Debug, PartialEq, Parse)]
+#[peg("(<words> )*", backend = "vm")]
+pub struct VmWords {
+    words: Vec<Word>,
+}
+
+/// Each attempt scans the whole input before failing, so that trying the alternatives in turn is costly.
+#[derive(Debug, PartialEq, Parse)]
+pub enum Backtracking {
+    #[peg("a*b")]
+    B,
+    #[peg("a*c")]
+    C,
+    #[peg("a*")]
+    A,
+}
+
+#[test]
+fn unlimited_state() {
+    let state = ParseState::default();
+    let words = Words::parse_with_state("one two three ", &state).unwrap();
+    assert_eq!(words.words.len(), 3);
+    assert_eq!(state.remaining_fuel(), None);
+}
+
+#[test]
+fn fuel_spent_while_parsing() {
+    let state = ParseState::default().with_fuel(1_000);
+    assert!(Words::parse_with_state("one two three ", &state).is_ok());
+
+    let left = state.remaining_fuel().unwrap();
+    assert!(left > 0 && left < 1_000);
+}
+
+#[test]
+fn fuel_exhausted() {
+    let input = "word ".repeat(1_000);
+
+    let state = ParseState::default().with_fuel(100);
+    let err = Words::parse_with_state(input.as_str(), &state).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::FuelExhausted);
+    assert!(err.cut);
+
+    let state = ParseState::default().with_fuel(100);
+    let err = VmWords::parse_with_state(input.as_str(), &state).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::FuelExhausted);
+}
+
+#[test]
+fn exhaustion_not_backtracked_over() {
+    // The last alternative would match, but the first two use up the budget
+    let input = "a".repeat(100);
+    assert_eq!(
+        Backtracking::parse(input.as_str()).ok(),
+        Some(Backtracking::A)
+    );
+
+    let state = ParseState::default().with_fuel(150);
+    let err = Backtracking::parse_with_state(input.as_str(), &state).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::FuelExhausted);
+    assert_eq!(state.remaining_fuel(), Some(0));
+}
+
+#[test]
+fn deadline_passed() {
+    let state = ParseState::default().with_deadline(Instant::now());
+    let err = Words::parse_with_state("one two ", &state).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Timeout);
+    assert_eq!(
+        err.to_string(),
+        "parsing took longer than allowed at line 1, column 1"
+    );
+
+    let state = ParseState::default().with_deadline(Instant::now() + Duration::from_secs(60));
+    assert!(Words::parse_with_state("one two ", &state).is_ok());
+}
+
+#[derive(Debug, PartialEq, Parse)]
+#[peg("hello")]
+pub struct Hello;
+
+#[derive(Debug, PartialEq, Parse)]
+#[peg("helps")]
+pub struct Helps;
+
+#[test]
+fn fuel_spent_on_literals() {
+    let state = ParseState::default().with_fuel(100);
+    assert!(Hello::parse_with_state("hello", &state).is_ok());
+    let matched = 100 - state.remaining_fuel().unwrap();
+
+    let state = ParseState::default().with_fuel(100);
+    assert!(Helps::parse_with_state("hello", &state).is_err());
+    let mismatched = 100 - state.remaining_fuel().unwrap();
+
+    // Beyond the step taken on entry, a whole literal costs a single step, while a mismatch costs one for each
+    // character matched before it
+    assert_eq!(matched, 1 + 1);
+    assert_eq!(mismatched, 1 + "hel".len() as u64);
+}
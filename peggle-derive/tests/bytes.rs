@@ -0,0 +1,98 @@
+use peggle::{Index, Parse, ParseError};
+use peggle_derive::Parse;
+
+#[derive(Debug, Parse)]
+#[peg("<method> <path> HTTP/1\\.<minor>\r\n")]
+pub struct RequestLine {
+    #[peg("[A-Z]+")]
+    pub method: String,
+    #[peg("[^ ]+")]
+    pub path: String,
+    pub minor: u8,
+}
+
+#[test]
+fn bytes_request_line() {
+    match RequestLine::parse(&b"GET /index.html HTTP/1.1\r\n"[..]) {
+        Ok(line) => {
+            assert_eq!(line.method, "GET");
+            assert_eq!(line.path, "/index.html");
+            assert_eq!(line.minor, 1);
+        }
+        Err(e) => panic!("err when should have been Ok: {:?}", e),
+    }
+}
+
+#[test]
+fn bytes_same_type_from_str() {
+    assert!(RequestLine::parse("POST / HTTP/1.0\r\n").is_ok());
+    assert!(RequestLine::parse("get / HTTP/1.0\r\n").is_err());
+}
+
+#[derive(Debug, Parse)]
+#[peg("\\xFF\\xD8<0>\\xFF\\xD9")]
+pub struct Marked(#[peg("[^\\xFF]*")] pub String);
+
+#[test]
+fn bytes_non_utf8() {
+    match Marked::parse(&b"\xFF\xD8abc\xE9\xFF\xD9"[..]) {
+        Ok(Marked(contents)) => assert_eq!(contents, "abc\u{E9}"),
+        Err(e) => panic!("err when should have been Ok: {:?}", e),
+    }
+}
+
+#[test]
+fn owned_inputs() {
+    let text = String::from("PUT /a HTTP/1.1\r\n");
+    assert!(RequestLine::parse(&text).is_ok());
+    let bytes = b"PUT /b HTTP/1.1\r\n".to_vec();
+    assert!(RequestLine::parse(&bytes).is_ok());
+    assert!(RequestLine::parse(b"PUT /c HTTP/1.1\r\n").is_ok());
+    assert_eq!(u32::parse(&"42".to_string()).ok(), Some(42));
+}
+
+#[test]
+fn bytes_error_position() {
+    let Err(e) = Marked::parse(&b"\xFF\xD8ab\xFF\xFF"[..]) else {
+        panic!("Ok when should have been err")
+    };
+    assert_eq!(e.colno, 5);
+}
+
+#[test]
+fn bytes_primitives() {
+    assert_eq!(u16::parse(&b"65535"[..]).ok(), Some(65535));
+    assert_eq!(i8::parse(&b"-128"[..]).ok(), Some(-128));
+    assert_eq!(char::parse(&b"\xE9"[..]).ok(), Some('\u{E9}'));
+    assert!(u8::parse(&b"256"[..]).is_err());
+}
+
+/// A type that can only be parsed from strings
+#[derive(Debug)]
+pub struct Word(pub String);
+
+impl Parse for Word {
+    fn parse_at(mut index: Index<'_>) -> Result<(Self, Index<'_>), ParseError> {
+        let start = index;
+        while let Some('a'..='z') = index.peek() {
+            index.next();
+        }
+        let len = start.remaining.len() - index.remaining.len();
+        match len {
+            0 => Err(ParseError::from_index(index)),
+            _ => Ok((Word(start.remaining[..len].to_string()), index)),
+        }
+    }
+}
+
+#[derive(Debug, Parse)]
+#[peg("<0>, <1>", input = "str")]
+pub struct WordPair(pub Word, pub Word);
+
+#[test]
+fn input_restricted_to_str() {
+    match WordPair::parse("hello, world") {
+        Ok(WordPair(Word(a), Word(b))) => assert_eq!((a.as_str(), b.as_str()), ("hello", "world")),
+        Err(e) => panic!("err when should have been Ok: {:?}", e),
+    }
+}
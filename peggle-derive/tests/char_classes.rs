@@ -0,0 +1,138 @@
+use peggle::Parse;
+use peggle_derive::Parse;
+
+macro_rules! pass {
+    ($test_name:ident,$st_name:ident,$input:literal) => {
+        #[test]
+        fn $test_name() {
+            if let Err(_) = $st_name::parse($input) {
+                panic!()
+            }
+        }
+    };
+}
+
+macro_rules! fail {
+    ($test_name:ident,$st_name:ident,$input:literal) => {
+        #[test]
+        fn $test_name() {
+            if let Ok(_) = $st_name::parse($input) {
+                panic!()
+            }
+        }
+    };
+}
+
+#[derive(Debug, Parse)]
+#[peg("[[:alpha:]--[aeiou]]+")]
+pub struct TestClass01 {}
+
+pass!(subtract_posix_match1, TestClass01, "bcd");
+pass!(subtract_posix_match2, TestClass01, "XYZ");
+fail!(subtract_posix_nomatch1, TestClass01, "bad");
+fail!(subtract_posix_nomatch2, TestClass01, "b4");
+
+#[derive(Debug, Parse)]
+#[peg("[\\w&&[^_]]+")]
+pub struct TestClass02 {}
+
+pass!(intersect_backslash_match1, TestClass02, "abc");
+pass!(intersect_backslash_match2, TestClass02, "Q");
+fail!(intersect_backslash_nomatch1, TestClass02, "a_b");
+fail!(intersect_backslash_nomatch2, TestClass02, "a-b");
+
+#[derive(Debug, Parse)]
+#[peg("[a-c[x-z]]")]
+pub struct TestClass03 {}
+
+pass!(nested_union_match1, TestClass03, "a");
+pass!(nested_union_match2, TestClass03, "y");
+fail!(nested_union_nomatch1, TestClass03, "d");
+fail!(nested_union_nomatch2, TestClass03, "[");
+
+#[derive(Debug, Parse)]
+#[peg("[^[:alnum:]--[0-9]]")]
+pub struct TestClass04 {}
+
+pass!(negated_subtract_match1, TestClass04, "5");
+pass!(negated_subtract_match2, TestClass04, "%");
+fail!(negated_subtract_nomatch1, TestClass04, "b");
+fail!(negated_subtract_nomatch2, TestClass04, "B");
+
+#[derive(Debug, Parse)]
+#[peg("[a-z&&[^aeiou]--[xyz]]")]
+pub struct TestClass05 {}
+
+pass!(chained_ops_match1, TestClass05, "b");
+pass!(chained_ops_match2, TestClass05, "w");
+fail!(chained_ops_nomatch1, TestClass05, "e");
+fail!(chained_ops_nomatch2, TestClass05, "y");
+
+#[derive(Debug, Parse)]
+#[peg("[\\d\\s]+")]
+pub struct TestClass06 {}
+
+pass!(backslash_union_match, TestClass06, "1 2\t3");
+fail!(backslash_union_nomatch, TestClass06, "1 a");
+
+#[derive(Debug, Parse)]
+#[peg("[a\\]\\-]+")]
+pub struct TestClass07 {}
+
+pass!(escaped_literals_match, TestClass07, "a]-");
+fail!(escaped_literals_nomatch, TestClass07, "a]\\");
+
+#[derive(Debug, Parse)]
+#[peg(r"first\r?\nsecond\tthird\0")]
+pub struct TestEscape01 {}
+
+pass!(
+    escape_sequence_match1,
+    TestEscape01,
+    "first\nsecond\tthird\0"
+);
+pass!(
+    escape_sequence_match2,
+    TestEscape01,
+    "first\r\nsecond\tthird\0"
+);
+fail!(
+    escape_sequence_nomatch1,
+    TestEscape01,
+    "first\\nsecond\tthird\0"
+);
+fail!(
+    escape_sequence_nomatch2,
+    TestEscape01,
+    "first\nsecond third\0"
+);
+
+#[derive(Debug, Parse)]
+#[peg(r"\x41\u{1F600}+\u{e9}")]
+pub struct TestEscape02 {}
+
+pass!(escape_codepoint_match, TestEscape02, "A😀😀é");
+fail!(escape_codepoint_nomatch1, TestEscape02, "a😀é");
+fail!(escape_codepoint_nomatch2, TestEscape02, "Aé");
+
+#[derive(Debug, Parse)]
+#[peg(r"[\x00-\x1f\u{7f}]+")]
+pub struct TestEscape03 {}
+
+pass!(escape_range_match, TestEscape03, "\x01\t\n\x1f\x7f");
+fail!(escape_range_nomatch1, TestEscape03, "\x01 ");
+fail!(escape_range_nomatch2, TestEscape03, "\u{80}");
+
+#[derive(Debug, Parse)]
+#[peg(r"[^\n]*\n")]
+pub struct TestEscape04 {}
+
+pass!(escape_negated_match, TestEscape04, "a line\n");
+fail!(escape_negated_nomatch, TestEscape04, "a line\nand more\n");
+
+#[derive(Debug, Parse)]
+#[peg(r"<0>;")]
+pub struct TestEscape05(#[peg(r"[^;\t]+(\t[^;\t]+)*")] pub String);
+
+pass!(escape_field_match, TestEscape05, "a\tb\tc;");
+fail!(escape_field_nomatch, TestEscape05, "a\t\tc;");
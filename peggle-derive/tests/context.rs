@@ -0,0 +1,120 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+
+use peggle::{ErrorKind, Index, Input, Parse, ParseError};
+use peggle_derive::Parse;
+
+#[derive(Default)]
+pub struct SymbolTable {
+    pub typedefs: RefCell<HashSet<String>>,
+}
+
+#[derive(Debug, Parse)]
+#[peg("<0>")]
+pub struct Ident(#[peg("[a-z]+")] pub String);
+
+/// An identifier that has been declared as a type within the symbol table
+#[derive(Debug)]
+pub struct TypeName(pub String);
+
+impl<I: ?Sized + Input> Parse<I> for TypeName {
+    fn parse_at(index: Index<'_, I>) -> Result<(Self, Index<'_, I>), ParseError> {
+        let (Ident(name), end) = Ident::parse_at(index)?;
+        let table = index
+            .context::<SymbolTable>()
+            .ok_or(ParseError::from_index(index))?;
+
+        if table.typedefs.borrow().contains(&name) {
+            Ok((TypeName(name), end))
+        } else {
+            Err(ParseError::from_index(index))
+        }
+    }
+}
+
+#[derive(Debug, Parse)]
+#[peg(ctx = SymbolTable)]
+pub enum Statement {
+    #[peg("<0> <1>;")]
+    Declaration(TypeName, Ident),
+    #[peg("<0> \\* <1>;")]
+    Multiplication(Ident, Ident),
+}
+
+#[test]
+fn context_typedef() {
+    let table = SymbolTable::default();
+    table.typedefs.borrow_mut().insert("foo".to_string());
+
+    match Statement::parse_with("foo * x;", &table) {
+        Ok(Statement::Multiplication(Ident(a), Ident(b))) => {
+            assert_eq!((a.as_str(), b.as_str()), ("foo", "x"))
+        }
+        other => panic!("wrong statement parsed: {:?}", other),
+    }
+
+    match Statement::parse_with("foo x;", &table) {
+        Ok(Statement::Declaration(TypeName(ty), Ident(name))) => {
+            assert_eq!((ty.as_str(), name.as_str()), ("foo", "x"))
+        }
+        other => panic!("wrong statement parsed: {:?}", other),
+    }
+}
+
+#[test]
+fn context_undeclared_type() {
+    let table = SymbolTable::default();
+    assert!(Statement::parse_with("foo x;", &table).is_err());
+}
+
+#[derive(Debug, Parse)]
+#[peg("(<statements>\n)*")]
+pub struct Program {
+    pub statements: Vec<Statement>,
+}
+
+#[test]
+fn context_propagates_through_fields() {
+    let table = SymbolTable::default();
+    table.typedefs.borrow_mut().insert("bar".to_string());
+
+    let program =
+        Program::parse_with("bar y;\nbar * y;\n", &table).expect("program should have parsed");
+    assert!(matches!(program.statements[0], Statement::Declaration(..)));
+    assert!(matches!(
+        program.statements[1],
+        Statement::Multiplication(..)
+    ));
+}
+
+#[test]
+fn context_missing() {
+    let Err(e) = Statement::parse("foo x;") else {
+        panic!("Ok when should have been err")
+    };
+    assert_eq!(e.kind, ErrorKind::MissingContext);
+    assert_eq!(
+        e.message.as_deref(),
+        Some("`Statement` must be parsed with a context of type `SymbolTable` (see `Parse::parse_with`)")
+    );
+}
+
+#[test]
+fn context_wrong_type() {
+    let Err(e) = Statement::parse_with("foo x;", &5u32) else {
+        panic!("Ok when should have been err")
+    };
+    assert_eq!(e.kind, ErrorKind::MissingContext);
+}
+
+#[test]
+fn context_missing_within_field() {
+    // The error isn't mistaken for a failure to match the statement, which would end the repetition
+    let Err(e) = Program::parse(
+        "bar y;
+",
+    ) else {
+        panic!("Ok when should have been err")
+    };
+    assert_eq!(e.kind, ErrorKind::MissingContext);
+}
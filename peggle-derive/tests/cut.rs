@@ -0,0 +1,94 @@
+use peggle::Parse;
+use peggle_derive::Parse;
+
+#[derive(Debug, Parse)]
+#[peg("<0>")]
+pub struct Ident(#[peg("[a-z]+")] pub String);
+
+#[derive(Debug, Parse)]
+pub enum Statement {
+    #[peg("let ~<0> = <1>;")]
+    Let(Ident, u32),
+    #[peg("return ~<0>;")]
+    Return(u32),
+    #[peg("<0>;")]
+    Expression(Ident),
+}
+
+#[test]
+fn cut_committed_match() {
+    match Statement::parse("let x = 5;") {
+        Ok(Statement::Let(Ident(name), 5)) => assert_eq!(name, "x"),
+        Ok(other) => panic!("wrong enum matched: {:?}", other),
+        Err(e) => panic!("err when should have been Ok: {:?}", e),
+    }
+}
+
+#[test]
+fn cut_before_commit_tries_other_choices() {
+    match Statement::parse("letter;") {
+        Ok(Statement::Expression(Ident(name))) => assert_eq!(name, "letter"),
+        Ok(other) => panic!("wrong enum matched: {:?}", other),
+        Err(e) => panic!("err when should have been Ok: {:?}", e),
+    }
+}
+
+#[test]
+fn cut_reports_error_position() {
+    let Err(e) = Statement::parse("let x = y;") else {
+        panic!("committed alternative should have failed")
+    };
+    assert!(e.cut);
+    assert_eq!(e.colno, 8);
+}
+
+#[test]
+fn cut_reports_nested_field_position() {
+    let Err(e) = Statement::parse("let 5 = 6;") else {
+        panic!("committed alternative should have failed")
+    };
+    assert!(e.cut);
+    assert_eq!(e.colno, 4);
+}
+
+#[test]
+fn cut_error_not_recovered_by_later_choices() {
+    // Without the cut, "return" would be matched by `Statement::Expression` before failing at the space
+    let Err(e) = Statement::parse("return x;") else {
+        panic!("committed alternative should have failed")
+    };
+    assert!(e.cut);
+    assert_eq!(e.colno, 7);
+}
+
+#[derive(Debug, Parse)]
+#[peg("(<statements>\n)*")]
+pub struct Block {
+    pub statements: Vec<Statement>,
+}
+
+#[test]
+fn cut_propagates_through_fields() {
+    let Err(e) = Block::parse("let x = 1;\nreturn x;\n") else {
+        panic!("committed alternative should have failed")
+    };
+    assert!(e.cut);
+    assert_eq!((e.lineno, e.colno), (1, 7));
+}
+
+#[derive(Debug, Parse)]
+#[peg("\\(~[0-9]+\\)|\\[[0-9]+\\]|\\~")]
+pub struct Bracketed {}
+
+#[test]
+fn cut_within_struct_choice() {
+    assert!(Bracketed::parse("(12)").is_ok());
+    assert!(Bracketed::parse("[12]").is_ok());
+    assert!(Bracketed::parse("~").is_ok());
+
+    let Err(e) = Bracketed::parse("(12]") else {
+        panic!("committed alternative should have failed")
+    };
+    assert!(e.cut);
+    assert_eq!(e.colno, 3);
+}
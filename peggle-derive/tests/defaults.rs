@@ -0,0 +1,91 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use peggle::Parse;
+use peggle_derive::Parse;
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
+
+fn next_id() -> usize {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+#[derive(Debug, Parse)]
+#[peg("<name>=<value>")]
+pub struct Binding {
+    #[peg("[a-z]+")]
+    pub name: String,
+    pub value: u32,
+    #[peg(skip)]
+    pub cache: Option<String>,
+    #[peg(default)]
+    pub uses: Vec<usize>,
+    #[peg(default = next_id())]
+    pub id: usize,
+    #[peg(default = "binding")]
+    pub kind: &'static str,
+}
+
+#[test]
+fn default_named_fields() {
+    match Binding::parse("x=5") {
+        Ok(binding) => {
+            assert_eq!(binding.name, "x");
+            assert_eq!(binding.value, 5);
+            assert_eq!(binding.cache, None);
+            assert!(binding.uses.is_empty());
+            assert!(binding.id > 0);
+            assert_eq!(binding.kind, "binding");
+        }
+        Err(e) => panic!("err when should have been Ok: {:?}", e),
+    }
+}
+
+#[test]
+fn default_evaluated_per_value() {
+    let (Ok(first), Ok(second)) = (Binding::parse("a=1"), Binding::parse("b=2")) else {
+        panic!("err when should have been Ok")
+    };
+    assert_ne!(first.id, second.id);
+}
+
+#[derive(Debug, Parse)]
+#[peg("<1>")]
+pub struct Tagged(#[peg(default = 7)] pub u8, pub u32);
+
+#[test]
+fn default_unnamed_field() {
+    match Tagged::parse("42") {
+        Ok(Tagged(tag, value)) => {
+            assert_eq!(tag, 7);
+            assert_eq!(value, 42);
+        }
+        Err(e) => panic!("err when should have been Ok: {:?}", e),
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Parse)]
+#[peg("marker")]
+pub struct Marker {
+    #[peg(skip)]
+    pub line: usize,
+}
+
+#[test]
+fn only_skipped_fields() {
+    assert_eq!(Marker::parse("marker").ok(), Some(Marker::default()));
+    assert!(Marker::parse("other").is_err());
+}
+
+#[derive(Debug, PartialEq, Parse)]
+pub enum Literal {
+    #[peg("<1>")]
+    Number(#[peg(skip)] Option<String>, u32),
+    #[peg("true")]
+    True(#[peg(default = 1)] u8),
+}
+
+#[test]
+fn default_variant_fields() {
+    assert_eq!(Literal::parse("12").ok(), Some(Literal::Number(None, 12)));
+    assert_eq!(Literal::parse("true").ok(), Some(Literal::True(1)));
+}
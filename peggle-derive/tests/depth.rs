@@ -0,0 +1,80 @@
+use peggle::{ErrorKind, Index, Parse, DEFAULT_MAX_DEPTH};
+use peggle_derive::Parse;
+
+#[derive(Debug, PartialEq, Parse)]
+pub enum Term {
+    #[peg("\\(<0>\\)")]
+    Paren(Box<Term>),
+    #[peg("<0>")]
+    Number(u32),
+}
+
+#[derive(Debug, PartialEq, Parse)]
+#[peg("\\[<0>?\\]", backend = "vm")]
+pub struct List(Option<Box<List>>);
+
+#[derive(Debug, PartialEq, Parse)]
+#[peg("(<terms>,)*")]
+pub struct Terms {
+    terms: Vec<Term>,
+}
+
+fn nested(depth: usize) -> String {
+    format!("{}1{}", "(".repeat(depth), ")".repeat(depth))
+}
+
+#[test]
+fn nesting_within_default_limit() {
+    // The outermost value and each level of parentheses are nested values
+    let input = nested(DEFAULT_MAX_DEPTH - 1);
+    assert!(Term::parse(input.as_str()).is_ok());
+}
+
+#[test]
+fn nesting_beyond_default_limit() {
+    let input = nested(DEFAULT_MAX_DEPTH);
+    let err = Term::parse(input.as_str()).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::DepthLimit);
+    assert_eq!(err.colno, DEFAULT_MAX_DEPTH);
+
+    // Far deeper input fails the same way rather than overflowing the stack
+    let input = nested(100_000);
+    assert_eq!(
+        Term::parse(input.as_str()).unwrap_err().kind,
+        ErrorKind::DepthLimit
+    );
+}
+
+#[test]
+fn configured_limit() {
+    let input = nested(9);
+    let index = Index::new(input.as_str()).with_max_depth(10);
+    assert!(Term::parse_at(index).is_ok());
+
+    let input = nested(10);
+    let index = Index::new(input.as_str()).with_max_depth(10);
+    let err = Term::parse_at(index).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::DepthLimit);
+}
+
+#[test]
+fn limit_not_backtracked_over() {
+    // The optional nested list would otherwise be left out, failing on the unmatched bracket instead
+    let input = format!("{}{}", "[".repeat(1000), "]".repeat(1000));
+    let err = List::parse(input.as_str()).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::DepthLimit);
+    assert!(err.to_string().starts_with("input is nested more than"));
+
+    let input = format!("{}{}", "[".repeat(100), "]".repeat(100));
+    assert!(List::parse(input.as_str()).is_ok());
+}
+
+#[test]
+fn consecutive_values_not_nested() {
+    let input = "1,".repeat(10_000);
+    let terms = Terms::parse(input.as_str()).unwrap();
+    assert_eq!(terms.terms.len(), 10_000);
+
+    let (_, end) = Terms::parse_at(Index::new(input.as_str())).unwrap();
+    assert_eq!(end.depth(), 0);
+}
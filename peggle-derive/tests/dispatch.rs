@@ -0,0 +1,111 @@
+use peggle::Parse;
+use peggle_derive::Parse;
+
+#[derive(Debug, PartialEq, Parse)]
+#[peg(rename_all = "lowercase")]
+pub enum Keyword {
+    Break,
+    Const,
+    Continue,
+    Else,
+    Enum,
+    Fn,
+    For,
+    If,
+    Impl,
+}
+
+#[test]
+fn dispatch_keywords() {
+    assert_eq!(Keyword::parse("break").ok(), Some(Keyword::Break));
+    assert_eq!(Keyword::parse("continue").ok(), Some(Keyword::Continue));
+    assert_eq!(Keyword::parse("enum").ok(), Some(Keyword::Enum));
+    assert_eq!(Keyword::parse("for").ok(), Some(Keyword::For));
+    assert_eq!(Keyword::parse("impl").ok(), Some(Keyword::Impl));
+    assert!(Keyword::parse("while").is_err());
+    assert!(Keyword::parse("").is_err());
+}
+
+#[derive(Debug, PartialEq, Parse)]
+pub enum Value {
+    #[peg("true")]
+    True,
+    #[peg("<0>")]
+    Number(u32),
+    #[peg("[0-9]+x")]
+    Hex,
+    #[peg("t(rue)?s")]
+    Ts,
+    #[peg("=?<0>")]
+    Assigned(u32),
+}
+
+#[test]
+fn dispatch_preserves_order() {
+    assert_eq!(Value::parse("true").ok(), Some(Value::True));
+    assert_eq!(Value::parse("ts").ok(), Some(Value::Ts));
+    assert_eq!(Value::parse("42").ok(), Some(Value::Number(42)));
+    assert_eq!(Value::parse("=7").ok(), Some(Value::Assigned(7)));
+    assert!(Value::parse("42x").is_err());
+}
+
+#[derive(Debug, PartialEq, Parse)]
+pub enum Optional {
+    #[peg("a")]
+    A,
+    #[peg("b?")]
+    MaybeB,
+}
+
+#[test]
+fn dispatch_nullable() {
+    assert_eq!(Optional::parse("a").ok(), Some(Optional::A));
+    assert_eq!(Optional::parse("b").ok(), Some(Optional::MaybeB));
+    assert_eq!(Optional::parse("").ok(), Some(Optional::MaybeB));
+}
+
+#[derive(Debug, PartialEq, Parse)]
+pub enum Labeled {
+    #[peg("x", expected = "an x")]
+    X,
+    #[peg("y", expected = "a y")]
+    Y,
+}
+
+#[test]
+fn dispatch_keeps_labels() {
+    let Err(e) = Labeled::parse("z") else {
+        panic!("Ok when should have been err")
+    };
+    assert_eq!(e.expected.as_deref(), Some("a y"));
+}
+
+#[test]
+fn dispatch_bytes() {
+    assert_eq!(
+        <Keyword as Parse<[u8]>>::parse(b"else").ok(),
+        Some(Keyword::Else)
+    );
+}
+
+#[derive(Debug, PartialEq, Parse)]
+#[peg(rename_all = "lowercase", no_dispatch)]
+pub enum UndispatchedKeyword {
+    Break,
+    Const,
+    Continue,
+    Else,
+}
+
+#[test]
+fn no_dispatch_keywords() {
+    assert_eq!(
+        UndispatchedKeyword::parse("break").ok(),
+        Some(UndispatchedKeyword::Break)
+    );
+    assert_eq!(
+        UndispatchedKeyword::parse("continue").ok(),
+        Some(UndispatchedKeyword::Continue)
+    );
+    assert!(UndispatchedKeyword::parse("enum").is_err());
+}
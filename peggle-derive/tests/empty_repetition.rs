@@ -0,0 +1,105 @@
+use peggle::Parse;
+use peggle_derive::Parse;
+
+/// A value that matches empty input when there's no `x` to be had.
+#[derive(Debug, PartialEq, Parse)]
+#[peg("<0>")]
+pub struct Maybe(#[peg("x?")] String);
+
+#[derive(Debug, PartialEq, Parse)]
+#[peg("<items>*y")]
+pub struct Possessive {
+    items: Vec<Maybe>,
+}
+
+#[derive(Debug, PartialEq, Parse)]
+#[peg("<items>*y", backtrack)]
+pub struct Greedy {
+    items: Vec<Maybe>,
+}
+
+#[derive(Debug, PartialEq, Parse)]
+#[peg("<items>*?y")]
+pub struct Lazy {
+    items: Vec<Maybe>,
+}
+
+#[derive(Debug, PartialEq, Parse)]
+#[peg("<items>*y", backend = "vm")]
+pub struct VmPossessive {
+    items: Vec<Maybe>,
+}
+
+#[derive(Debug, PartialEq, Parse)]
+#[peg("<items>*y", backtrack, backend = "vm")]
+pub struct VmGreedy {
+    items: Vec<Maybe>,
+}
+
+#[derive(Debug, PartialEq, Parse)]
+#[peg("<items>*?y", backend = "vm")]
+pub struct VmLazy {
+    items: Vec<Maybe>,
+}
+
+fn items(values: &[&str]) -> Vec<Maybe> {
+    values.iter().map(|v| Maybe(v.to_string())).collect()
+}
+
+#[test]
+fn possessive_stops_after_empty_match() {
+    // The empty match that ends the repetition is kept, as it matched
+    let expected = items(&["x", "x", ""]);
+    assert_eq!(Possessive::parse("xxy").unwrap().items, expected);
+    assert_eq!(VmPossessive::parse("xxy").unwrap().items, expected);
+    assert!(Possessive::parse("xxz").is_err());
+    assert!(VmPossessive::parse("xxz").is_err());
+}
+
+#[test]
+fn greedy_stops_after_empty_match() {
+    let expected = items(&["x", ""]);
+    assert_eq!(Greedy::parse("xy").unwrap().items, expected);
+    assert_eq!(VmGreedy::parse("xy").unwrap().items, expected);
+    assert!(Greedy::parse("xz").is_err());
+    assert!(VmGreedy::parse("xz").is_err());
+}
+
+#[test]
+fn lazy_fails_after_empty_match() {
+    assert_eq!(Lazy::parse("y").unwrap().items, vec![]);
+    assert_eq!(VmLazy::parse("y").unwrap().items, vec![]);
+    assert_eq!(Lazy::parse("xxy").unwrap().items, items(&["x", "x"]));
+    assert_eq!(VmLazy::parse("xxy").unwrap().items, items(&["x", "x"]));
+    assert!(Lazy::parse("xz").is_err());
+    assert!(VmLazy::parse("xz").is_err());
+}
+
+#[derive(Debug, PartialEq, Parse)]
+#[peg("<items>{2,}y")]
+pub struct AtLeastTwo {
+    items: Vec<Maybe>,
+}
+
+#[derive(Debug, PartialEq, Parse)]
+#[peg("<items>{2,}y", backtrack)]
+pub struct GreedyAtLeastTwo {
+    items: Vec<Maybe>,
+}
+
+#[derive(Debug, PartialEq, Parse)]
+#[peg("<items>{2,}y", backend = "vm")]
+pub struct VmAtLeastTwo {
+    items: Vec<Maybe>,
+}
+
+#[test]
+fn empty_matches_count_towards_minimum() {
+    // Repeating stops at the first empty match past the minimum
+    let expected = items(&["", "", ""]);
+    assert_eq!(AtLeastTwo::parse("y").unwrap().items, expected);
+    assert_eq!(GreedyAtLeastTwo::parse("y").unwrap().items, expected);
+    assert_eq!(VmAtLeastTwo::parse("y").unwrap().items, expected);
+    assert_eq!(AtLeastTwo::parse("xxxy").unwrap().items.len(), 4);
+    assert_eq!(VmAtLeastTwo::parse("xxxy").unwrap().items.len(), 4);
+}
@@ -0,0 +1,157 @@
+use std::marker::PhantomData;
+
+use peggle::Parse;
+use peggle_derive::Parse;
+
+#[derive(Debug, Parse)]
+#[peg("\\[(<items>(,<items>)*)?\\]")]
+pub struct List<T> {
+    pub items: Vec<T>,
+}
+
+#[derive(Debug, Parse)]
+#[peg("<0>")]
+pub struct Word(#[peg("[a-z]+")] pub String);
+
+#[test]
+fn generic_struct() {
+    match List::<u32>::parse("[1,2,3]") {
+        Ok(List { items }) => assert_eq!(items, vec![1, 2, 3]),
+        Err(e) => panic!("err when should have been Ok: {:?}", e),
+    }
+
+    match List::<Word>::parse("[ab,cd]") {
+        Ok(List { items }) => {
+            assert_eq!(items.len(), 2);
+            assert_eq!(items[1].0, "cd");
+        }
+        Err(e) => panic!("err when should have been Ok: {:?}", e),
+    }
+
+    assert!(List::<u32>::parse("[1,a]").is_err());
+}
+
+#[test]
+fn nested_generic_struct() {
+    match List::<List<u8>>::parse("[[1],[],[2,3]]") {
+        Ok(List { items }) => {
+            assert_eq!(items.len(), 3);
+            assert!(items[1].items.is_empty());
+            assert_eq!(items[2].items, vec![2, 3]);
+        }
+        Err(e) => panic!("err when should have been Ok: {:?}", e),
+    }
+}
+
+#[derive(Debug, PartialEq, Parse)]
+pub enum Either<L, R> {
+    #[peg("L<0>")]
+    Left(L),
+    #[peg("R<0>")]
+    Right(R),
+}
+
+#[test]
+fn generic_enum() {
+    assert_eq!(
+        Either::<u8, char>::parse("L12").ok(),
+        Some(Either::Left(12))
+    );
+    assert_eq!(
+        Either::<u8, char>::parse("Rx").ok(),
+        Some(Either::Right('x'))
+    );
+}
+
+#[derive(Debug, Parse)]
+#[peg("<key>=<value>")]
+pub struct Entry<K, V: Clone> {
+    #[peg("[a-z]+")]
+    pub key: K,
+    pub value: Box<V>,
+    #[peg(skip)]
+    pub marker: PhantomData<(K, V)>,
+}
+
+#[test]
+fn generic_box_and_skipped() {
+    match Entry::<String, u16>::parse("port=80") {
+        Ok(entry) => {
+            assert_eq!(entry.key, "port");
+            assert_eq!(*entry.value, 80);
+        }
+        Err(e) => panic!("err when should have been Ok: {:?}", e),
+    }
+}
+
+fn check_len<const N: usize>(digits: &Digits<N>) -> Result<(), String> {
+    if digits.0.len() == N {
+        Ok(())
+    } else {
+        Err(format!("expected {} digits", N))
+    }
+}
+
+#[derive(Debug, Parse)]
+#[peg("<0>")]
+#[peg(validate = check_len::<N>)]
+pub struct Digits<const N: usize>(#[peg("[0-9]+")] pub String);
+
+#[test]
+fn const_generic_struct() {
+    assert!(Digits::<3>::parse("123").is_ok());
+
+    let Err(e) = Digits::<3>::parse("1234") else {
+        panic!("Ok when should have been err")
+    };
+    assert_eq!(e.message.as_deref(), Some("expected 3 digits"));
+}
+
+/// Only parses values for which an upper bound is known.
+pub trait Bounded {
+    const MAX: u32;
+}
+
+impl Bounded for u8 {
+    const MAX: u32 = u8::MAX as u32;
+}
+
+fn check_bounded<T: Bounded>(value: &Limited<T>) -> Result<(), &'static str> {
+    if value.value <= T::MAX {
+        Ok(())
+    } else {
+        Err("value out of range")
+    }
+}
+
+#[derive(Debug, Parse)]
+#[peg("<value>")]
+#[peg(bound = "T: Bounded", validate = check_bounded::<T>)]
+pub struct Limited<T> {
+    pub value: u32,
+    #[peg(skip)]
+    pub marker: PhantomData<T>,
+}
+
+#[test]
+fn explicit_bound() {
+    assert!(Limited::<u8>::parse("255").is_ok());
+    assert!(Limited::<u8>::parse("256").is_err());
+}
+
+#[derive(Debug, Parse)]
+#[peg("<value>(\\(<children>(,<children>)*\\))?")]
+#[peg(bound = "T: peggle::Parse<PeggleInput>")]
+pub struct Tree<T> {
+    pub value: T,
+    pub children: Vec<Tree<T>>,
+}
+
+#[test]
+fn explicit_parse_bound() {
+    let tree = Tree::<u8>::parse("1(2,3(4))").unwrap();
+    assert_eq!(tree.value, 1);
+    assert_eq!(tree.children.len(), 2);
+    assert_eq!(tree.children[1].children[0].value, 4);
+    assert!(Tree::<u8>::parse("1(x)").is_err());
+}
@@ -0,0 +1,45 @@
+use peggle::Parse;
+
+peggle_derive::grammar!("tests/grammars/statements.peg");
+
+#[test]
+fn grammar_struct_rules() {
+    assert_eq!(
+        Assignment::parse("let x = 5;").ok(),
+        Some(Assignment {
+            name: Ident("x".to_string()),
+            value: 5
+        })
+    );
+
+    let Err(e) = Assignment::parse("let 5 = 5;") else {
+        panic!("Ok when should have been err")
+    };
+    assert!(e.cut);
+    assert_eq!(e.expected.as_deref(), Some("a variable name"));
+}
+
+#[test]
+fn grammar_enum_rules() {
+    assert_eq!(
+        Statement::parse("print(x);").ok(),
+        Some(Statement::Print(Ident("x".to_string())))
+    );
+    assert_eq!(Statement::parse("pass;").ok(), Some(Statement::Pass));
+}
+
+#[test]
+fn grammar_nested_rules() {
+    let script = Script::parse("let a = 1;\nprint(a);\npass;\n").unwrap();
+    assert_eq!(
+        script.statements,
+        vec![
+            Statement::Assign(Assignment {
+                name: Ident("a".to_string()),
+                value: 1
+            }),
+            Statement::Print(Ident("a".to_string())),
+            Statement::Pass,
+        ]
+    );
+}
@@ -0,0 +1,83 @@
+use peggle::grammar::{to_ebnf, to_json, to_w3c, Grammar};
+use peggle_derive::{Grammar, Parse};
+
+#[derive(Debug, Parse, Grammar)]
+#[peg("<0>")]
+pub struct Ident(#[peg("[a-z_][a-z0-9_]*")] pub String);
+
+#[derive(Debug, Parse, Grammar)]
+#[peg("let ~<name> = <value>;")]
+pub struct Assignment {
+    pub name: Ident,
+    pub value: Value,
+}
+
+#[derive(Debug, Parse, Grammar)]
+pub enum Value {
+    #[peg("<0>")]
+    Number(u32),
+    #[peg("\\[(<0>(,<0>)*)?\\]")]
+    List(Vec<Value>),
+    #[peg("<0>")]
+    Variable(Ident),
+}
+
+#[test]
+fn grammar_rules_collected() {
+    let names: Vec<_> = Assignment::rules().into_iter().map(|r| r.name).collect();
+    assert_eq!(names, vec!["Assignment", "Ident", "Value", "u32"]);
+}
+
+#[test]
+fn grammar_ebnf() {
+    assert_eq!(
+        to_ebnf(&Assignment::rules()),
+        concat!(
+            "Assignment = \"let \" , Ident , \" = \" , Value , \";\" ;\n",
+            "Ident = ? [_a-z] ? , { ? [0-9_a-z] ? } ;\n",
+            "Value = u32 | \"[\" , [ Value , { \",\" , Value } ] , \"]\" | Ident ;\n",
+            "u32 = \"0\" | ? [1-9] ? , { ? [0-9] ? } ;\n",
+        )
+    );
+}
+
+#[test]
+fn grammar_w3c() {
+    assert_eq!(
+        to_w3c(&Value::rules()),
+        concat!(
+            "Value ::= u32 | \"[\" (Value (\",\" Value)*)? \"]\" | Ident\n",
+            "u32 ::= \"0\" | [1-9] [0-9]*\n",
+            "Ident ::= [_a-z] [0-9_a-z]*\n",
+        )
+    );
+}
+
+#[test]
+fn grammar_json() {
+    assert_eq!(
+        to_json(&Ident::rules()),
+        concat!(
+            "[{\"name\":\"Ident\",\"node\":{\"type\":\"sequence\",\"items\":[",
+            "{\"type\":\"class\",\"ranges\":[[\"_\",\"_\"],[\"a\",\"z\"]]},",
+            "{\"type\":\"repeat\",\"item\":{\"type\":\"class\",\"ranges\":[[\"0\",\"9\"],[\"_\",\"_\"],[\"a\",\"z\"]]},\"min\":0,\"max\":null}",
+            "]}}]"
+        )
+    );
+}
+
+#[derive(Debug, Parse, Grammar)]
+#[peg("\\(<0>,<1>\\)")]
+pub struct Pair<A, B>(pub A, pub B);
+
+#[test]
+fn grammar_generic() {
+    assert_eq!(
+        to_ebnf(&Pair::<u8, bool>::rules()),
+        concat!(
+            "Pair = \"(\" , u8 , \",\" , bool , \")\" ;\n",
+            "u8 = \"0\" | ? [1-9] ? , { ? [0-9] ? } ;\n",
+            "bool = \"true\" | \"false\" ;\n",
+        )
+    );
+}
@@ -0,0 +1,178 @@
+use peggle::Parse;
+use peggle_derive::Parse;
+
+fn check_port(port: &u32) -> Result<(), String> {
+    if (1..=65535).contains(port) {
+        Ok(())
+    } else {
+        Err(format!("port {} is out of range", port))
+    }
+}
+
+#[derive(Debug, Parse)]
+#[peg("<host>:<port>")]
+pub struct Address {
+    #[peg("[a-z]+")]
+    pub host: String,
+    #[peg(validate = check_port)]
+    pub port: u32,
+}
+
+#[test]
+fn validate_field_success() {
+    match Address::parse("localhost:8080") {
+        Ok(Address { host, port }) => {
+            assert_eq!(host, "localhost");
+            assert_eq!(port, 8080);
+        }
+        Err(e) => panic!("err when should have been Ok: {:?}", e),
+    }
+}
+
+#[test]
+fn validate_field_failure() {
+    let Err(e) = Address::parse("localhost:70000") else {
+        panic!("Ok when should have been err")
+    };
+    assert_eq!(e.message.as_deref(), Some("port 70000 is out of range"));
+    assert_eq!(e.colno, 10);
+    assert_eq!(
+        e.to_string(),
+        "port 70000 is out of range at line 1, column 11"
+    );
+}
+
+fn tags_match(element: &Markup) -> Result<(), &'static str> {
+    match element {
+        Markup::Element(open, _, close) if open != close => Err("mismatched closing tag"),
+        _ => Ok(()),
+    }
+}
+
+#[derive(Debug, Parse)]
+pub enum Markup {
+    #[peg("\\<<0>\\><1>\\</<2>\\>")]
+    #[peg(validate = "tags_match")]
+    Element(
+        #[peg("[a-z]+")] String,
+        #[peg("[a-z]*")] String,
+        #[peg("[a-z]+")] String,
+    ),
+    #[peg("\\<<0>\\>[a-z]*\\</[a-z]+\\>")]
+    Unbalanced(#[peg("[a-z]+")] String),
+}
+
+#[test]
+fn validate_variant_success() {
+    let Ok(Markup::Element(open, text, close)) = Markup::parse("<b>bold</b>") else {
+        panic!("should have matched the first variant")
+    };
+    assert_eq!(open, "b");
+    assert_eq!(text, "bold");
+    assert_eq!(close, "b");
+}
+
+#[test]
+fn validate_variant_backtracks() {
+    let Ok(Markup::Unbalanced(open)) = Markup::parse("<b>bold</i>") else {
+        panic!("should have fallen through to the second variant")
+    };
+    assert_eq!(open, "b");
+}
+
+fn nonzero(value: &EvenNumber) -> Result<(), &'static str> {
+    if (value.0).0 == 0 {
+        Err("zero is not allowed")
+    } else {
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct Even(pub u32);
+
+impl TryFrom<u32> for Even {
+    type Error = String;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        if value.is_multiple_of(2) {
+            Ok(Even(value))
+        } else {
+            Err(format!("{} is odd", value))
+        }
+    }
+}
+
+#[derive(Debug, Parse)]
+#[peg(try_from = u32)]
+#[peg(validate = nonzero)]
+pub struct EvenNumber(pub Even);
+
+impl TryFrom<u32> for EvenNumber {
+    type Error = String;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        Even::try_from(value).map(EvenNumber)
+    }
+}
+
+#[test]
+fn try_from_type() {
+    match EvenNumber::parse("42") {
+        Ok(EvenNumber(Even(n))) => assert_eq!(n, 42),
+        Err(e) => panic!("err when should have been Ok: {:?}", e),
+    }
+
+    let Err(e) = EvenNumber::parse("7") else {
+        panic!("Ok when should have been err")
+    };
+    assert_eq!(e.message.as_deref(), Some("7 is odd"));
+
+    let Err(e) = EvenNumber::parse("0") else {
+        panic!("Ok when should have been err")
+    };
+    assert_eq!(e.message.as_deref(), Some("zero is not allowed"));
+}
+
+#[derive(Debug, Parse)]
+#[peg(map = |word: String| Word(word.to_uppercase()))]
+#[peg(expected = "a word")]
+pub struct Word(pub String);
+
+#[test]
+fn map_type() {
+    match Word::parse("hello") {
+        Ok(Word(word)) => assert_eq!(word, "HELLO"),
+        Err(e) => panic!("err when should have been Ok: {:?}", e),
+    }
+}
+
+fn widen(value: u32) -> i64 {
+    i64::from(value)
+}
+
+#[derive(Debug, Parse)]
+#[peg("<x>,<y>")]
+pub struct Point {
+    #[peg(try_from = u32)]
+    pub x: u8,
+    #[peg(map = "widen")]
+    pub y: i64,
+}
+
+#[test]
+fn field_conversions() {
+    match Point::parse("12,345") {
+        Ok(Point { x, y }) => {
+            assert_eq!(x, 12);
+            assert_eq!(y, 345);
+        }
+        Err(e) => panic!("err when should have been Ok: {:?}", e),
+    }
+
+    let Err(e) = Point::parse("300,1") else {
+        panic!("Ok when should have been err")
+    };
+    assert!(e.message.is_some());
+    assert_eq!(e.colno, 0);
+}
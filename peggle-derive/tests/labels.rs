@@ -0,0 +1,125 @@
+use peggle::Parse;
+use peggle_derive::Parse;
+
+#[derive(Debug, Parse)]
+#[peg("<0>")]
+pub struct Ident(#[peg("[a-z]+")] pub String);
+
+#[derive(Debug, Parse)]
+#[peg("<name:expected 'an identifier'> = <value:expected \"a number\">")]
+pub struct Assignment {
+    pub name: Ident,
+    pub value: u32,
+}
+
+#[test]
+fn label_first_field() {
+    let Err(e) = Assignment::parse("5 = 1") else {
+        panic!("Ok when should have been err")
+    };
+    assert_eq!(e.expected.as_deref(), Some("an identifier"));
+    assert_eq!(e.colno, 0);
+}
+
+#[test]
+fn label_later_field() {
+    let Err(e) = Assignment::parse("x = y") else {
+        panic!("Ok when should have been err")
+    };
+    assert_eq!(e.expected.as_deref(), Some("a number"));
+    assert_eq!(e.colno, 4);
+    assert_eq!(e.to_string(), "expected a number at line 1, column 5");
+}
+
+#[test]
+fn label_success() {
+    match Assignment::parse("x = 1") {
+        Ok(Assignment { name, value }) => {
+            assert_eq!(name.0, "x");
+            assert_eq!(value, 1);
+        }
+        Err(e) => panic!("err when should have been Ok: {:?}", e),
+    }
+}
+
+#[derive(Debug, Parse)]
+#[peg(expected = "a function call")]
+#[peg("<name>\\(\\)")]
+pub struct Call {
+    pub name: Ident,
+}
+
+#[test]
+fn label_type() {
+    let Err(e) = Call::parse("5()") else {
+        panic!("Ok when should have been err")
+    };
+    assert_eq!(e.expected.as_deref(), Some("a function call"));
+    assert_eq!(e.colno, 0);
+}
+
+#[derive(Debug, Parse)]
+pub enum Statement {
+    #[peg("let ~<0:expected 'a variable name'> = <1>;")]
+    Let(Ident, u32),
+    #[peg("<0>;")]
+    Call(Call),
+}
+
+#[test]
+fn label_propagates_from_field_type() {
+    let Err(e) = Statement::parse("5;") else {
+        panic!("Ok when should have been err")
+    };
+    assert_eq!(e.expected.as_deref(), Some("a function call"));
+    assert_eq!(
+        e.to_string(),
+        "expected a function call at line 1, column 1"
+    );
+}
+
+#[test]
+fn label_after_cut() {
+    let Err(e) = Statement::parse("let 5 = 1;") else {
+        panic!("Ok when should have been err")
+    };
+    assert!(e.cut);
+    assert_eq!(e.expected.as_deref(), Some("a variable name"));
+    assert_eq!(e.colno, 4);
+}
+
+#[derive(Debug, Parse)]
+#[peg(expected = "a value")]
+pub enum Value {
+    #[peg("\\[<0:expected 'a list element'>\\]")]
+    List(u32),
+    #[peg("<0>")]
+    Number(u32),
+}
+
+#[test]
+fn label_enum_type() {
+    let Err(e) = Value::parse("x") else {
+        panic!("Ok when should have been err")
+    };
+    assert_eq!(e.expected.as_deref(), Some("a value"));
+    assert_eq!(e.colno, 0);
+}
+
+#[test]
+fn label_furthest_preferred() {
+    let Err(e) = Value::parse("[x]") else {
+        panic!("Ok when should have been err")
+    };
+    assert_eq!(e.expected.as_deref(), Some("a list element"));
+    assert_eq!(e.colno, 1);
+}
+
+#[test]
+fn unlabeled_display() {
+    let Err(e) = Ident::parse("x\ny") else {
+        panic!("Ok when should have been err")
+    };
+    assert_eq!(e.expected, None);
+    assert_eq!(e.to_string(), "unexpected input at line 1, column 1");
+}
@@ -0,0 +1,152 @@
+// The derive raises its warnings as deprecations, so denying them makes any warning fail the build
+#![deny(deprecated)]
+
+use peggle::Parse;
+use peggle_derive::Parse;
+
+// Each of these types is close to something the derive warns about, so they check that patterns which are fine aren't
+// reported.
+
+/// A longer literal tried before its prefix isn't shadowed.
+#[derive(Debug, PartialEq, Parse)]
+#[peg("<0>")]
+pub struct Arrow(#[peg("-[>]|-")] String);
+
+#[test]
+fn longer_alternative_first() {
+    assert_eq!(Arrow::parse("->").ok(), Some(Arrow("->".to_string())));
+    assert_eq!(Arrow::parse("-").ok(), Some(Arrow("-".to_string())));
+}
+
+/// A repetition of something that can match empty input is fine when bounded.
+#[derive(Debug, PartialEq, Parse)]
+#[peg("<0>")]
+pub struct Padding(#[peg("( ?){0,4}x")] String);
+
+#[test]
+fn bounded_nullable_repetition() {
+    assert_eq!(Padding::parse("  x").ok(), Some(Padding("  x".to_string())));
+    assert!(Padding::parse("     x").is_err());
+}
+
+/// An escaped tilde ending a pattern is a literal, rather than a cut with nothing to commit to.
+#[derive(Debug, PartialEq, Parse)]
+#[peg("<0>\\~")]
+pub struct Approximate(u32);
+
+#[test]
+fn escaped_trailing_tilde() {
+    assert_eq!(Approximate::parse("12~").ok(), Some(Approximate(12)));
+}
+
+pub struct Dialect {
+    pub strict: bool,
+}
+
+fn strict_only(_: &Comparison, dialect: &Dialect) -> Result<(), &'static str> {
+    if dialect.strict {
+        Ok(())
+    } else {
+        Err("only strict comparisons are allowed")
+    }
+}
+
+/// A literal discriminant that validation may reject doesn't shadow later discriminants that begin with its text.
+#[derive(Debug, PartialEq, Parse)]
+#[peg(ctx = Dialect)]
+pub enum Comparison {
+    #[peg("\\<", validate = strict_only)]
+    Less,
+    #[peg("\\<=")]
+    LessEqual,
+}
+
+#[test]
+fn validated_discriminant_first() {
+    let strict = Dialect { strict: true };
+    assert_eq!(
+        Comparison::parse_with("<", &strict).ok(),
+        Some(Comparison::Less)
+    );
+
+    let lenient = Dialect { strict: false };
+    assert_eq!(
+        Comparison::parse_with("<=", &lenient).ok(),
+        Some(Comparison::LessEqual)
+    );
+}
+
+/// A type may refer to itself once input has been consumed.
+#[derive(Debug, PartialEq, Parse)]
+#[peg("<value>(\\+<rest>)?")]
+pub struct Sum {
+    value: u32,
+    rest: Option<Box<Sum>>,
+}
+
+#[test]
+fn right_recursion() {
+    let sum = Sum::parse("1+2+3").unwrap();
+    assert_eq!(sum.value, 1);
+    assert_eq!(sum.rest.as_ref().unwrap().value, 2);
+    assert_eq!(sum.rest.unwrap().rest.unwrap().value, 3);
+}
+
+/// A type may refer to itself after an optional prefix, provided the prefix is required when it does.
+#[derive(Debug, PartialEq, Parse)]
+pub enum Term {
+    #[peg("\\(<0>\\)")]
+    Paren(Box<Term>),
+    #[peg("<0>")]
+    Number(u32),
+}
+
+#[test]
+fn recursion_within_brackets() {
+    assert_eq!(
+        Term::parse("((4))").ok(),
+        Some(Term::Paren(Box::new(Term::Paren(Box::new(Term::Number(
+            4
+        ))))))
+    );
+}
+
+mod other {
+    use peggle_derive::Parse;
+
+    #[derive(Debug, PartialEq, Parse)]
+    #[peg("<0>")]
+    pub struct Expr(pub u32);
+}
+
+/// A field of another type sharing the derived type's name isn't recursion.
+#[derive(Debug, PartialEq, Parse)]
+#[peg("<0>!")]
+pub struct Expr(other::Expr);
+
+#[test]
+fn same_name_other_type() {
+    assert_eq!(Expr::parse("4!").ok(), Some(Expr(other::Expr(4))));
+}
+
+mod lists {
+    peggle_derive::grammar!("tests/grammars/lists.peg");
+}
+
+#[test]
+fn mutual_recursion_after_input() {
+    use lists::{Item, List};
+
+    assert_eq!(
+        List::parse("[1,[2],[]]").ok(),
+        Some(List {
+            items: vec![
+                Item::Number(1),
+                Item::Nested(Box::new(List {
+                    items: vec![Item::Number(2)]
+                })),
+                Item::Nested(Box::new(List { items: vec![] })),
+            ]
+        })
+    );
+}
@@ -0,0 +1,79 @@
+use std::io::Read;
+
+use peggle::{Index, Parse};
+use peggle_derive::Parse;
+
+#[derive(Debug, Parse)]
+#[peg("function <name>\\(\\)")]
+pub struct Function {
+    #[peg("[a-z]+")]
+    pub name: String,
+}
+
+#[test]
+fn literal_run_match() {
+    match Function::parse("function main()") {
+        Ok(Function { name }) => assert_eq!(name, "main"),
+        Err(e) => panic!("err when should have been Ok: {:?}", e),
+    }
+}
+
+#[test]
+fn literal_run_error_position() {
+    // The error is reported at the first character that differs, as when matching character by character
+    let Err(e) = Function::parse("funcsion main()") else {
+        panic!("Ok when should have been err")
+    };
+    assert_eq!(e.colno, 4);
+}
+
+#[derive(Debug, Parse)]
+#[peg("begin\nend<0>")]
+pub struct Block(#[peg("[a-z]*")] pub String);
+
+#[test]
+fn literal_run_newline_position() {
+    let (_, end) = Block::parse_at(Index::new("begin\nend")).unwrap();
+    assert_eq!((end.lineno, end.colno), (1, 3));
+
+    let Err(e) = Block::parse("begin\nend!") else {
+        panic!("Ok when should have been err")
+    };
+    assert_eq!((e.lineno, e.colno), (0, 0));
+}
+
+#[derive(Debug, Parse)]
+#[peg("caf\u{e9} <0>")]
+pub struct Order(#[peg("[a-z]+")] pub String);
+
+#[test]
+fn literal_run_non_ascii() {
+    assert!(Order::parse("café latte").is_ok());
+
+    let (_, end) = Order::parse_at(Index::new("café au")).unwrap();
+    assert_eq!(end.colno, 8);
+
+    let input: &[u8] = b"caf\xe9 noir";
+    assert!(<Order as Parse<[u8]>>::parse(input).is_ok());
+}
+
+/// A reader that returns a single byte at a time, so that literals straddle chunks
+struct Trickle<'a>(&'a [u8]);
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = buf.len().min(self.0.len()).min(1);
+        buf[..len].copy_from_slice(&self.0[..len]);
+        self.0 = &self.0[len..];
+        Ok(len)
+    }
+}
+
+#[test]
+fn literal_run_across_chunks() {
+    let reader = Trickle(b"function main()function init()");
+    let names: Vec<_> = <Function as Parse>::parse_reader(reader)
+        .map(|f| f.expect("function should have parsed").name)
+        .collect();
+    assert_eq!(names, vec!["main", "init"]);
+}
@@ -0,0 +1,63 @@
+use peggle::Parse;
+use peggle_derive::Parse;
+
+#[derive(Debug, PartialEq, Parse)]
+#[peg(longest)]
+pub enum Op {
+    #[peg("\\<")]
+    Lt,
+    #[peg("\\<=")]
+    Le,
+    #[peg("\\<\\<")]
+    Shl,
+    #[peg("\\<\\<=")]
+    ShlAssign,
+}
+
+#[test]
+fn longest_literal() {
+    assert_eq!(Op::parse("<").ok(), Some(Op::Lt));
+    assert_eq!(Op::parse("<=").ok(), Some(Op::Le));
+    assert_eq!(Op::parse("<<").ok(), Some(Op::Shl));
+    assert_eq!(Op::parse("<<=").ok(), Some(Op::ShlAssign));
+    assert!(Op::parse(">").is_err());
+}
+
+#[derive(Debug, PartialEq, Parse)]
+#[peg(longest)]
+pub enum Token {
+    #[peg("<0>")]
+    Ident(#[peg("[a-z]+")] String),
+    #[peg("if")]
+    If,
+    #[peg("<0>")]
+    Keyword(#[peg("if|else")] String),
+}
+
+#[test]
+fn longest_ties_prefer_first() {
+    assert_eq!(
+        Token::parse("iffy").ok(),
+        Some(Token::Ident("iffy".to_string()))
+    );
+    assert_eq!(
+        Token::parse("if").ok(),
+        Some(Token::Ident("if".to_string()))
+    );
+}
+
+#[derive(Debug, PartialEq, Parse)]
+#[peg(longest)]
+pub enum Number {
+    #[peg("<0>")]
+    Int(u32),
+    #[peg("<0>\\.<1>")]
+    Float(u32, u32),
+}
+
+#[test]
+fn longest_with_fields() {
+    assert_eq!(Number::parse("12").ok(), Some(Number::Int(12)));
+    assert_eq!(Number::parse("12.5").ok(), Some(Number::Float(12, 5)));
+    assert!(Number::parse("12.").is_err());
+}
@@ -0,0 +1,159 @@
+use peggle::Parse;
+use peggle_derive::Parse;
+
+#[derive(Debug, Default, PartialEq, Parse)]
+#[peg("<0>")]
+pub struct Ident(#[peg("[a-z]+")] pub String);
+
+#[derive(Debug, Parse)]
+pub enum Statement {
+    #[peg("let ~<0> = <1>;", recover = "[^;\n]*;")]
+    Let(Ident, u32),
+    #[peg("print ~<0>;")]
+    Print(Ident),
+}
+
+#[derive(Debug, Parse)]
+#[peg("(<statements>\n)*")]
+pub struct Program {
+    pub statements: Vec<Statement>,
+}
+
+#[test]
+fn recover_discriminant() {
+    let (program, errors) = Program::parse_recovering("let x = 1;\nlet y = z;\nprint y;\n");
+    let Some(program) = program else {
+        panic!(
+            "recovery should have produced a partial result: {:?}",
+            errors
+        )
+    };
+
+    assert_eq!(program.statements.len(), 3);
+    match &program.statements[1] {
+        Statement::Let(Ident(name), 0) => assert_eq!(name, "y"),
+        other => panic!("recovered statement had unexpected contents: {:?}", other),
+    }
+    assert!(matches!(program.statements[2], Statement::Print(_)));
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!((errors[0].lineno, errors[0].colno), (1, 8));
+}
+
+#[test]
+fn recover_multiple_errors() {
+    let (program, errors) = Program::parse_recovering("let 1 = 1;\nlet y = z;\nlet z = 3;\n");
+    assert_eq!(program.map(|p| p.statements.len()), Some(3));
+
+    let positions: Vec<_> = errors.iter().map(|e| (e.lineno, e.colno)).collect();
+    assert_eq!(positions, vec![(0, 4), (1, 8)]);
+}
+
+#[test]
+fn recover_only_when_requested() {
+    let Err(e) = Program::parse("let x = 1;\nlet y = z;\n") else {
+        panic!("Ok when should have been err")
+    };
+    assert_eq!((e.lineno, e.colno), (1, 8));
+}
+
+#[test]
+fn recover_pattern_mismatch() {
+    // The synchronization pattern can't match without a semicolon, so the error can't be recovered from
+    let (program, errors) = Statement::parse_recovering("let x = y");
+    assert!(program.is_none());
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].colno, 8);
+}
+
+#[test]
+fn recover_clean_input() {
+    let (program, errors) = Program::parse_recovering("let x = 1;\nprint x;\n");
+    assert_eq!(program.map(|p| p.statements.len()), Some(2));
+    assert!(errors.is_empty());
+}
+
+#[derive(Debug, Parse)]
+#[peg("(<items>;)*")]
+pub struct List {
+    #[peg(recover = "[^;]*")]
+    pub items: Vec<u32>,
+}
+
+#[test]
+fn recover_field() {
+    let (list, errors) = List::parse_recovering("1;x;3;");
+    assert_eq!(list.map(|l| l.items), Some(vec![1, 0, 3]));
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].colno, 2);
+}
+
+#[test]
+fn recover_trailing_input() {
+    let (list, errors) = List::parse_recovering("1;;");
+    assert_eq!(list.map(|l| l.items), Some(vec![1]));
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].colno, 2);
+}
+
+#[derive(Debug, Parse)]
+pub enum Entry {
+    #[peg("<0>=<1>")]
+    Pair(#[peg(recover = "[^=]+")] u32, u32),
+    #[peg("<0>=x")]
+    Word(Ident),
+}
+
+#[test]
+fn recover_discarded_with_failed_discriminant() {
+    let (entry, errors) = Entry::parse_recovering("ab=x");
+    assert!(matches!(entry, Some(Entry::Word(_))));
+    assert!(errors.is_empty());
+}
+
+#[derive(Debug, PartialEq, Parse)]
+#[peg("(<items>;)*end")]
+pub struct Terminated {
+    #[peg(recover = "[^;]*")]
+    pub items: Vec<u32>,
+}
+
+#[derive(Debug, PartialEq, Parse)]
+#[peg("<number>|<word>")]
+pub struct Either {
+    #[peg(recover = "[^;]*")]
+    pub number: Option<u32>,
+    pub word: Option<Ident>,
+}
+
+#[test]
+fn recover_nothing_from_valid_repetition() {
+    // The last iteration fails on "end", which ends the repetition rather than being an error
+    let (list, errors) = Terminated::parse_recovering("1;2;end");
+    assert_eq!(list, Terminated::parse("1;2;end").ok());
+    assert_eq!(list.map(|l| l.items), Some(vec![1, 2]));
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn recover_nothing_from_valid_alternative() {
+    // The first alternative fails, which is a cue to try the second rather than an error
+    let (either, errors) = Either::parse_recovering("abc");
+    assert_eq!(either, Either::parse("abc").ok());
+    assert_eq!(
+        either,
+        Some(Either {
+            number: None,
+            word: Some(Ident("abc".to_string()))
+        })
+    );
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn recover_where_repetition_fails() {
+    let (list, errors) = Terminated::parse_recovering("1;x;end");
+    assert_eq!(list.map(|l| l.items), Some(vec![1, 0]));
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].colno, 2);
+}
@@ -0,0 +1,108 @@
+use peggle::{Parse, Span};
+use peggle_derive::Parse;
+
+#[derive(Debug, Parse)]
+#[peg("<key>=<value>\n")]
+pub struct Record {
+    #[peg("[a-z]+")]
+    pub key: String,
+    pub value: u32,
+}
+
+#[test]
+fn parse_iter_records() {
+    let input = "a=1\nbb=22\nccc=333\n";
+    let records: Vec<_> = Record::parse_iter(input)
+        .map(|r| r.expect("record should have parsed"))
+        .collect();
+
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[1].0.key, "bb");
+    assert_eq!(records[1].0.value, 22);
+    assert_eq!(
+        records[1].1,
+        Span {
+            start: 4,
+            end: 10,
+            lineno: 1,
+            colno: 0
+        }
+    );
+    assert_eq!(&input[records[2].1.range()], "ccc=333\n");
+}
+
+#[test]
+fn parse_iter_stops_at_error() {
+    let results: Vec<_> = Record::parse_iter("a=1\nb=x\nc=3\n").collect();
+    assert_eq!(results.len(), 2);
+    assert!(results[0].is_ok());
+    match &results[1] {
+        Err(e) => assert_eq!((e.lineno, e.colno), (1, 2)),
+        Ok(_) => panic!("Ok when should have been err"),
+    }
+}
+
+#[test]
+fn parse_iter_empty_input() {
+    assert_eq!(Record::parse_iter("").count(), 0);
+}
+
+#[test]
+fn parse_prefix_remaining() {
+    match Record::parse_prefix("a=1\nrest") {
+        Ok((record, remaining)) => {
+            assert_eq!(record.key, "a");
+            assert_eq!(remaining, "rest");
+        }
+        Err(e) => panic!("err when should have been Ok: {:?}", e),
+    }
+    assert!(Record::parse_prefix("rest").is_err());
+}
+
+#[derive(Debug, Parse)]
+#[peg("<0>\\.<1>\\.<2>")]
+pub struct Version(pub u8, pub u8, pub u8);
+
+#[test]
+fn find_first() {
+    match Version::find("requires version 1.20.3 or later") {
+        Some((Version(1, 20, 3), span)) => assert_eq!(span.range(), 17..23),
+        other => panic!("wrong value found: {:?}", other),
+    }
+    assert!(Version::find("no version here").is_none());
+}
+
+#[test]
+fn find_all_versions() {
+    let input = "1.2.3 -> 4.5.6, then 7.8.9";
+    let found: Vec<_> = Version::find_all(input)
+        .map(|(v, span)| ((v.0, v.1, v.2), &input[span.range()]))
+        .collect();
+
+    assert_eq!(
+        found,
+        vec![
+            ((1, 2, 3), "1.2.3"),
+            ((4, 5, 6), "4.5.6"),
+            ((7, 8, 9), "7.8.9")
+        ]
+    );
+}
+
+#[derive(Debug, Parse)]
+#[peg("a*")]
+pub struct As {}
+
+#[test]
+fn find_all_empty_matches() {
+    let spans: Vec<_> = As::find_all("baab").map(|(_, span)| span.range()).collect();
+    assert_eq!(spans, vec![0..0, 1..3, 3..3, 4..4]);
+}
+
+#[test]
+fn find_all_bytes() {
+    let found: Vec<_> = u16::find_all(&b"\x00port 8080\x00\xFF443"[..])
+        .map(|(port, _)| port)
+        .collect();
+    assert_eq!(found, vec![8080, 443]);
+}
@@ -0,0 +1,123 @@
+use std::io::Read;
+
+use peggle::{Parse, ReadError};
+use peggle_derive::Parse;
+
+/// A reader that returns at most `step` bytes at a time, so that values straddle chunks
+struct Trickle<'a> {
+    data: &'a [u8],
+    step: usize,
+}
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = self.step.min(buf.len()).min(self.data.len());
+        buf[..len].copy_from_slice(&self.data[..len]);
+        self.data = &self.data[len..];
+        Ok(len)
+    }
+}
+
+#[derive(Debug, Parse)]
+#[peg("<key>=<value>\n")]
+pub struct Record {
+    #[peg("[a-zé]+")]
+    pub key: String,
+    pub value: u32,
+}
+
+#[test]
+fn stream_records() {
+    let reader = Trickle {
+        data: b"alpha=1\nbeta=22\ngamma=333\n",
+        step: 3,
+    };
+
+    let records: Vec<_> = <Record as Parse>::parse_reader(reader)
+        .map(|r| r.expect("record should have parsed"))
+        .map(|r| (r.key, r.value))
+        .collect();
+
+    assert_eq!(
+        records,
+        vec![
+            ("alpha".to_string(), 1),
+            ("beta".to_string(), 22),
+            ("gamma".to_string(), 333)
+        ]
+    );
+}
+
+#[test]
+fn stream_value_at_chunk_boundary() {
+    // With single bytes, `u32` must read past the end of each chunk to know the number has ended
+    let reader = Trickle {
+        data: b"a=12345\n",
+        step: 1,
+    };
+    let values: Vec<_> = <Record as Parse>::parse_reader(reader)
+        .map(|r| r.unwrap().value)
+        .collect();
+    assert_eq!(values, vec![12345]);
+}
+
+#[test]
+fn stream_split_utf8() {
+    let reader = Trickle {
+        data: "clé=1\n".as_bytes(),
+        step: 1,
+    };
+    let keys: Vec<_> = <Record as Parse>::parse_reader(reader)
+        .map(|r| r.unwrap().key)
+        .collect();
+    assert_eq!(keys, vec!["clé".to_string()]);
+}
+
+#[test]
+fn stream_error_position() {
+    let reader = Trickle {
+        data: b"a=1\nb=x\nc=3\n",
+        step: 2,
+    };
+    let mut records = <Record as Parse>::parse_reader(reader);
+
+    assert!(matches!(records.next(), Some(Ok(_))));
+    match records.next() {
+        Some(Err(ReadError::Parse(e))) => assert_eq!((e.lineno, e.colno), (1, 2)),
+        other => panic!("expected parse error, got {:?}", other),
+    }
+    assert!(records.next().is_none());
+}
+
+#[test]
+fn stream_truncated_record() {
+    let reader = Trickle {
+        data: b"a=1\nb=2",
+        step: 4,
+    };
+    let results: Vec<_> = <Record as Parse>::parse_reader(reader).collect();
+    assert_eq!(results.len(), 2);
+    assert!(matches!(results[1], Err(ReadError::Parse(_))));
+}
+
+#[test]
+fn stream_bytes() {
+    let reader = Trickle {
+        data: b"\xFF\xFE",
+        step: 1,
+    };
+    let chars: Vec<_> = <char as Parse<[u8]>>::parse_reader(reader)
+        .map(|c| c.unwrap())
+        .collect();
+    assert_eq!(chars, vec!['\u{FF}', '\u{FE}']);
+}
+
+#[test]
+fn stream_invalid_utf8() {
+    let reader = Trickle {
+        data: b"a=1\n\xFF",
+        step: 8,
+    };
+    let results: Vec<_> = <Record as Parse>::parse_reader(reader).collect();
+    assert!(matches!(results.last(), Some(Err(ReadError::Io(_)))));
+}
@@ -70,7 +70,7 @@ fn test_three() {
 
 #[derive(Debug, Parse)]
 #[peg("<0>hello<1>")]
-pub struct Test04(#[peg("why ")] String, #[peg(", world")] String);
+pub struct Test04(#[peg("why ")] pub String, #[peg(", world")] pub String);
 
 #[test]
 fn test_four() {
@@ -86,7 +86,7 @@ fn test_four() {
 
 #[derive(Debug, Parse)]
 #[peg("<0>+hello")]
-pub struct Test05(#[peg("why")] Vec<String>);
+pub struct Test05(#[peg("why")] pub Vec<String>);
 
 #[test]
 fn test_five() {
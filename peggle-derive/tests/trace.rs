@@ -0,0 +1,111 @@
+#![cfg(feature = "trace")]
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use peggle::trace::{set_tracer, Location, NullTracer, Tracer};
+use peggle::Parse;
+use peggle_derive::Parse;
+
+#[derive(Debug, PartialEq, Parse)]
+#[peg("<0>")]
+pub struct Ident(#[peg("[a-z]+")] String);
+
+#[derive(Debug, PartialEq, Parse)]
+#[peg("let <name>=<value>;")]
+pub struct Assignment {
+    name: Ident,
+    value: u32,
+}
+
+#[derive(Debug, PartialEq, Parse)]
+pub enum Statement {
+    #[peg("<0>")]
+    Assign(Assignment),
+    #[peg("print\\(<0>\\);")]
+    Print(Ident),
+}
+
+#[derive(Debug, PartialEq, Parse)]
+#[peg("(yes|no|maybe)!")]
+pub struct Answer;
+
+/// Records each event as a line of text.
+#[derive(Clone, Default)]
+struct Recorder(Rc<RefCell<Vec<String>>>);
+
+impl Tracer for Recorder {
+    fn enter(&mut self, rule: &str, pattern: &str, at: Location) {
+        self.0.borrow_mut().push(format!(
+            "{} enter {} {:?} {}",
+            at.depth, rule, pattern, at.colno
+        ));
+    }
+
+    fn exit(&mut self, rule: &str, matched: bool, at: Location) {
+        self.0.borrow_mut().push(format!(
+            "{} exit {} {} {}",
+            at.depth, rule, matched, at.colno
+        ));
+    }
+
+    fn backtrack(&mut self, pattern: &str, at: Location) {
+        self.0
+            .borrow_mut()
+            .push(format!("{} backtrack {:?} {}", at.depth, pattern, at.colno));
+    }
+}
+
+fn record(parse: impl FnOnce()) -> Vec<String> {
+    let recorder = Recorder::default();
+    set_tracer(Box::new(recorder.clone()));
+    parse();
+    set_tracer(Box::new(NullTracer));
+    recorder.0.take()
+}
+
+#[test]
+fn rules_entered_and_exited() {
+    let events = record(|| {
+        assert!(Statement::parse("print(x);").is_ok());
+    });
+
+    assert_eq!(
+        events,
+        vec![
+            "1 enter Statement \"<0> | print\\\\(<0>\\\\);\" 0",
+            "2 enter Assignment \"let <name>=<value>;\" 0",
+            "2 exit Assignment false 0",
+            "1 backtrack \"<0>\" 0",
+            "2 enter Ident \"<0>\" 6",
+            "2 exit Ident true 7",
+            "1 exit Statement true 9",
+        ]
+    );
+}
+
+#[test]
+fn alternatives_backtracked_from() {
+    let events = record(|| {
+        assert!(Answer::parse("maybe!").is_ok());
+    });
+
+    assert_eq!(
+        events,
+        vec![
+            "1 enter Answer \"(yes|no|maybe)!\" 0",
+            "1 backtrack \"yes\" 0",
+            "1 backtrack \"no\" 0",
+            "1 exit Answer true 6",
+        ]
+    );
+}
+
+#[test]
+fn failure_position() {
+    let events = record(|| {
+        assert!(Assignment::parse("let x=;").is_err());
+    });
+
+    assert_eq!(events.last().unwrap(), "1 exit Assignment false 6");
+}
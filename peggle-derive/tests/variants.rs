@@ -0,0 +1,99 @@
+use peggle::Parse;
+use peggle_derive::Parse;
+
+#[derive(Debug, PartialEq, Parse)]
+pub enum Shape {
+    #[peg("circle\\(<radius>\\)")]
+    Circle { radius: u32 },
+    #[peg("rect\\(<width>,<height>\\)")]
+    Rect { width: u32, height: u32 },
+    #[peg("point")]
+    Point {},
+}
+
+#[test]
+fn named_variant_fields() {
+    assert_eq!(
+        Shape::parse("circle(3)").ok(),
+        Some(Shape::Circle { radius: 3 })
+    );
+    assert_eq!(
+        Shape::parse("rect(4,5)").ok(),
+        Some(Shape::Rect {
+            width: 4,
+            height: 5
+        })
+    );
+    assert_eq!(Shape::parse("point").ok(), Some(Shape::Point {}));
+    assert!(Shape::parse("rect(4)").is_err());
+}
+
+#[derive(Debug, PartialEq, Parse)]
+pub enum Entry {
+    #[peg("<key>=<value>", recover = ";")]
+    Pair {
+        #[peg("[a-z]+")]
+        key: String,
+        value: u32,
+        #[peg(skip)]
+        line: usize,
+    },
+}
+
+#[test]
+fn named_variant_recovering() {
+    assert_eq!(
+        Entry::parse("x=1").ok(),
+        Some(Entry::Pair {
+            key: "x".to_string(),
+            value: 1,
+            line: 0
+        })
+    );
+}
+
+#[derive(Debug, PartialEq, Parse)]
+pub enum Keyword {
+    While,
+    For,
+    Return,
+}
+
+#[test]
+fn unit_variant_names() {
+    assert_eq!(Keyword::parse("While").ok(), Some(Keyword::While));
+    assert_eq!(Keyword::parse("Return").ok(), Some(Keyword::Return));
+    assert!(Keyword::parse("while").is_err());
+}
+
+#[derive(Debug, PartialEq, Parse)]
+#[peg(rename_all = "snake_case")]
+pub enum Builtin {
+    Len,
+    TypeOf,
+    IsEmpty,
+    #[peg("print!")]
+    Print,
+}
+
+#[test]
+fn unit_variant_rename_all() {
+    assert_eq!(Builtin::parse("len").ok(), Some(Builtin::Len));
+    assert_eq!(Builtin::parse("type_of").ok(), Some(Builtin::TypeOf));
+    assert_eq!(Builtin::parse("is_empty").ok(), Some(Builtin::IsEmpty));
+    assert_eq!(Builtin::parse("print!").ok(), Some(Builtin::Print));
+    assert!(Builtin::parse("TypeOf").is_err());
+}
+
+#[derive(Debug, PartialEq, Parse)]
+#[peg(rename_all = "SCREAMING-KEBAB-CASE")]
+pub enum Method {
+    Get,
+    PostForm,
+}
+
+#[test]
+fn unit_variant_kebab_case() {
+    assert_eq!(Method::parse("GET").ok(), Some(Method::Get));
+    assert_eq!(Method::parse("POST-FORM").ok(), Some(Method::PostForm));
+}
@@ -0,0 +1,274 @@
+use peggle::Parse;
+use peggle_derive::Parse;
+
+#[derive(Debug, Parse)]
+#[peg("[a-z]+", backend = "vm")]
+pub struct Ident {}
+
+#[derive(Debug, Parse)]
+#[peg("x(a|b){2,3}b", backtrack, backend = "vm")]
+pub struct Range {}
+
+#[derive(Debug, Parse)]
+#[peg("x(ab|a){2}b", backtrack, backend = "vm")]
+pub struct GroupChoice {}
+
+#[derive(Debug, Parse)]
+#[peg("a{2,}?a", backend = "vm")]
+pub struct Lazy {}
+
+#[test]
+fn vm_repetitions() {
+    assert!(Ident::parse("abc").is_ok());
+    assert!(Ident::parse("").is_err());
+    assert!(Ident::parse("ab1").is_err());
+
+    assert!(Range::parse("xabb").is_ok());
+    assert!(Range::parse("xaab").is_ok());
+    assert!(Range::parse("xab").is_err());
+    assert!(Range::parse("xababb").is_err());
+
+    // Only the number of repetitions is backtracked; the choice made within each repetition is not revisited
+    assert!(GroupChoice::parse("xabab").is_err());
+
+    assert!(Lazy::parse("aaa").is_ok());
+    assert!(Lazy::parse("aa").is_err());
+    assert!(Lazy::parse("aaaaa").is_err());
+}
+
+#[derive(Debug, Parse)]
+#[peg("(<first>,)*<last>,", backtrack, backend = "vm")]
+pub struct List {
+    pub first: Vec<u8>,
+    pub last: u8,
+}
+
+#[test]
+fn vm_fields_given_back() {
+    let list = List::parse("1,2,3,").unwrap();
+    assert_eq!(list.first, vec![1, 2]);
+    assert_eq!(list.last, 3);
+}
+
+#[derive(Debug, PartialEq, Parse)]
+#[peg("<0>")]
+pub struct Name(#[peg("[a-z]+")] pub String);
+
+#[derive(Debug, PartialEq, Parse)]
+#[peg(backend = "vm")]
+pub enum Statement {
+    #[peg("let ~<0:expected 'a variable name'> = <1>;")]
+    Let(Name, u32),
+    #[peg("return ~<0>;")]
+    Return(u32),
+    #[peg("<0>;")]
+    Expression(Name),
+}
+
+#[test]
+fn vm_cut() {
+    assert_eq!(
+        Statement::parse("let x = 5;").ok(),
+        Some(Statement::Let(Name("x".to_string()), 5))
+    );
+    assert_eq!(
+        Statement::parse("letter;").ok(),
+        Some(Statement::Expression(Name("letter".to_string())))
+    );
+
+    let Err(e) = Statement::parse("return x;") else {
+        panic!("committed alternative should have failed")
+    };
+    assert!(e.cut);
+    assert_eq!(e.colno, 7);
+}
+
+#[test]
+fn vm_labeled_field() {
+    let Err(e) = Statement::parse("let 5 = 6;") else {
+        panic!("committed alternative should have failed")
+    };
+    assert!(e.cut);
+    assert_eq!(e.colno, 4);
+    assert_eq!(e.expected.as_deref(), Some("a variable name"));
+}
+
+#[derive(Debug, Parse)]
+#[peg("\\(~[0-9]+\\)|\\[[0-9]+\\]", backend = "vm")]
+pub struct Bracketed {}
+
+#[test]
+fn vm_cut_within_choice() {
+    assert!(Bracketed::parse("(12)").is_ok());
+    assert!(Bracketed::parse("[12]").is_ok());
+
+    let Err(e) = Bracketed::parse("(12]") else {
+        panic!("committed alternative should have failed")
+    };
+    assert!(e.cut);
+    assert_eq!(e.colno, 3);
+}
+
+#[derive(Debug, Default, PartialEq, Parse)]
+#[peg("<0>", backend = "vm")]
+pub struct Word(#[peg("[a-z]+")] pub String);
+
+#[derive(Debug, PartialEq, Parse)]
+#[peg(backend = "vm")]
+pub enum Assignment {
+    #[peg("let ~<0> = <1>;", recover = "[^;\n]*;")]
+    Let(Word, u32),
+}
+
+#[derive(Debug, Parse)]
+#[peg("(<assignments>\n)*", backend = "vm")]
+pub struct Program {
+    pub assignments: Vec<Assignment>,
+}
+
+#[test]
+fn vm_recovery() {
+    let (program, errors) = Program::parse_recovering("let x = 1;\nlet y = z;\n");
+    let program = program.expect("recovery should have produced a partial result");
+    assert_eq!(
+        program.assignments,
+        vec![
+            Assignment::Let(Word("x".to_string()), 1),
+            Assignment::Let(Word("y".to_string()), 0)
+        ]
+    );
+    assert_eq!(errors.len(), 1);
+    assert_eq!((errors[0].lineno, errors[0].colno), (1, 8));
+}
+
+#[test]
+fn vm_bytes() {
+    let input: &[u8] = b"let x = 1;\n";
+    let program = <Program as Parse<[u8]>>::parse(input).unwrap();
+    assert_eq!(program.assignments.len(), 1);
+}
+
+/// Derives the same pattern with both backends, checking that they agree on each input
+/// Every string of up to `len` characters from `alphabet`.
+fn strings(alphabet: &[char], len: usize) -> Vec<String> {
+    let mut strings = vec![String::new()];
+    let mut start = 0;
+    for _ in 0..len {
+        let end = strings.len();
+        for idx in start..end {
+            for c in alphabet {
+                let string = format!("{}{}", strings[idx], c);
+                strings.push(string);
+            }
+        }
+        start = end;
+    }
+    strings
+}
+
+/// Checks that both backends agree on whether each input parses, and where those that don't fail.
+macro_rules! parity {
+    ($test_name:ident, $pegex:literal, $inputs:expr) => {
+        #[test]
+        fn $test_name() {
+            #[derive(Debug, Parse)]
+            #[peg($pegex, backtrack)]
+            pub struct Inline {}
+
+            #[derive(Debug, Parse)]
+            #[peg($pegex, backtrack, backend = "vm")]
+            pub struct Vm {}
+
+            for input in $inputs {
+                let input: &str = input.as_ref();
+                assert_eq!(
+                    Inline::parse(input).map_err(|e| (e.lineno, e.colno)).err(),
+                    Vm::parse(input).map_err(|e| (e.lineno, e.colno)).err(),
+                    "backends disagree on {:?}",
+                    input
+                );
+            }
+        }
+    };
+}
+
+parity!(
+    vm_nested_counted_repetitions,
+    "(a{1,3}b){2}a{2,3}ab",
+    [
+        "abababaab",
+        "aaabaabaaab",
+        "abab",
+        "ababaab",
+        "aaaabab",
+        "abaababaaaab",
+        "ababaaaab"
+    ]
+);
+parity!(
+    vm_lazy_counted_repetitions,
+    "(ab|c){2,3}?(ab){1,2}+c",
+    [
+        "ababc",
+        "cabc",
+        "abababc",
+        "cccabc",
+        "ababababc",
+        "cabab",
+        "cababc"
+    ]
+);
+parity!(
+    vm_counted_empty_iterations,
+    "(a?){2,4}b",
+    ["b", "ab", "aab", "aaaab", "aaaaab"]
+);
+parity!(
+    vm_lazy_repetition_errors,
+    "a{1,3}?ab",
+    strings(&['a', 'b'], 6)
+);
+parity!(
+    vm_nested_lazy_repetition_errors,
+    "((a|b)*?b){2}",
+    strings(&['a', 'b'], 8)
+);
+parity!(
+    vm_repeated_backtracking_errors,
+    "(a*ab)+",
+    strings(&['a', 'b'], 8)
+);
+parity!(
+    vm_possessive_repetition_errors,
+    "(ab)+?a(ab?){2,}+b",
+    strings(&['a', 'b'], 8)
+);
+parity!(
+    vm_cut_repetition_errors,
+    "(a~b|b)*(ab){1}|a",
+    strings(&['a', 'b'], 6)
+);
+
+#[derive(Debug, Parse)]
+#[peg("(<first>,){1,3}<last>,", backtrack, backend = "vm")]
+pub struct BoundedList {
+    pub first: Vec<u8>,
+    pub last: u8,
+}
+
+#[derive(Debug, Parse)]
+#[peg("[0-9]{2,1000}", backend = "vm")]
+pub struct Digits {}
+
+#[test]
+fn vm_counted_repetitions() {
+    let list = BoundedList::parse("1,2,3,").unwrap();
+    assert_eq!(list.first, vec![1, 2]);
+    assert_eq!(list.last, 3);
+    assert!(BoundedList::parse("1,").is_err());
+    assert!(BoundedList::parse("1,2,3,4,5,").is_err());
+
+    assert!(Digits::parse("1").is_err());
+    assert!(Digits::parse("0".repeat(1000).as_str()).is_ok());
+    assert!(Digits::parse("0".repeat(1001).as_str()).is_err());
+}
@@ -0,0 +1,414 @@
+//! Structured descriptions of the syntax accepted by parsed types, for documentation.
+//!
+//! Types deriving `Grammar` (alongside `Parse`) describe their peggle expression as a [`Rule`], in which fields refer
+//! to the rules of their own types. The rules of a type and everything it refers to can be rendered as EBNF
+//! ([`to_ebnf`]), as the notation of the W3C's XML specification ([`to_w3c`]), or as JSON for railroad-diagram
+//! generators ([`to_json`]).
+
+use std::fmt::Write;
+
+/// A type whose syntax can be described.
+pub trait Grammar {
+    /// Describes the syntax of this type.
+    fn rule() -> Rule;
+
+    /// Returns the rule of this type followed by the rules of every type it refers to (directly or indirectly),
+    /// each appearing once.
+    fn rules() -> Vec<Rule> {
+        let mut rules: Vec<Rule> = Vec::new();
+        let mut pending = vec![Self::rule()];
+
+        while let Some(rule) = pending.pop() {
+            if rules.iter().any(|r| r.name == rule.name) {
+                continue;
+            }
+
+            let mut references = Vec::new();
+            rule.node.references(&mut references);
+            pending.extend(references.into_iter().rev().map(|reference| reference()));
+            rules.push(rule);
+        }
+
+        rules
+    }
+}
+
+/// The syntax of a single type.
+#[derive(Clone, Debug)]
+pub struct Rule {
+    /// The name of the type
+    pub name: String,
+    /// What the type matches
+    pub node: Node,
+}
+
+/// An element of a rule's syntax.
+#[derive(Clone, Debug)]
+pub enum Node {
+    /// Literal text
+    Literal(String),
+    /// Any single character
+    Any,
+    /// A single character within any of the given inclusive ranges
+    Class(Vec<(char, char)>),
+    /// The rule of another type, such as that of a field
+    Reference(fn() -> Rule),
+    /// A type that can't describe its syntax, given by name
+    External(String),
+    /// Nodes that match one after the other (matching empty input if there are none)
+    Sequence(Vec<Node>),
+    /// Alternatives that are tried in order
+    Choice(Vec<Node>),
+    /// A node matched between `min` and `max` times (any number of times if `max` is `None`)
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+    },
+    /// A cut (`~`), which doesn't change what is matched
+    Cut,
+}
+
+impl Node {
+    /// Adds the rules referred to within this node to `references`, in the order they appear.
+    fn references(&self, references: &mut Vec<fn() -> Rule>) {
+        match self {
+            Node::Reference(rule) => references.push(*rule),
+            Node::Sequence(nodes) | Node::Choice(nodes) => {
+                for node in nodes {
+                    node.references(references);
+                }
+            }
+            Node::Repeat { node, .. } => node.references(references),
+            _ => (),
+        }
+    }
+
+    /// Whether rendering this node within a sequence requires it to be enclosed in parentheses.
+    fn is_compound(&self) -> bool {
+        match self {
+            Node::Sequence(nodes) | Node::Choice(nodes) => nodes.len() > 1,
+            _ => false,
+        }
+    }
+
+    /// The nodes of a sequence, leaving out cuts.
+    fn matched(nodes: &[Node]) -> impl Iterator<Item = &Node> {
+        nodes.iter().filter(|node| !matches!(node, Node::Cut))
+    }
+}
+
+/// Renders rules in ISO 14977 EBNF (`name = a , { b } | [ c ] ;`).
+///
+/// Character classes, which EBNF has no notation for, are given as special sequences (`? [a-z] ?`).
+pub fn to_ebnf(rules: &[Rule]) -> String {
+    let mut out = String::new();
+    for rule in rules {
+        let _ = writeln!(out, "{} = {} ;", rule.name, ebnf_node(&rule.node));
+    }
+    out
+}
+
+fn ebnf_node(node: &Node) -> String {
+    match node {
+        Node::Literal(text) => quoted(text),
+        Node::Any => "? any character ?".to_string(),
+        Node::Class(ranges) => format!("? {} ?", class(ranges, |c| c.to_string())),
+        Node::Reference(rule) => rule().name,
+        Node::External(name) => name.clone(),
+        Node::Sequence(nodes) => {
+            let items: Vec<_> = Node::matched(nodes).map(ebnf_item).collect();
+            match items.is_empty() {
+                true => "\"\"".to_string(),
+                false => items.join(" , "),
+            }
+        }
+        Node::Choice(nodes) => nodes.iter().map(ebnf_node).collect::<Vec<_>>().join(" | "),
+        Node::Repeat { node, min, max } => {
+            let mut items = Vec::new();
+            let inner = ebnf_node(node);
+            let item = match node.is_compound() || matches!(**node, Node::Repeat { .. }) {
+                true => format!("( {} )", inner),
+                false => inner.clone(),
+            };
+            match min {
+                0 => (),
+                1 => items.push(item),
+                _ => items.push(format!("{} * {}", min, item)),
+            }
+            match max.map(|max| max - min) {
+                None => items.push(format!("{{ {} }}", inner)),
+                Some(0) => (),
+                Some(1) => items.push(format!("[ {} ]", inner)),
+                Some(optional) => items.push(format!("{} * [ {} ]", optional, inner)),
+            }
+            items.join(" , ")
+        }
+        Node::Cut => "\"\"".to_string(),
+    }
+}
+
+fn ebnf_item(node: &Node) -> String {
+    match node.is_compound() {
+        true => format!("( {} )", ebnf_node(node)),
+        false => ebnf_node(node),
+    }
+}
+
+/// Renders rules in the notation of the W3C's XML specification (`name ::= a b* | c?`).
+pub fn to_w3c(rules: &[Rule]) -> String {
+    let mut out = String::new();
+    for rule in rules {
+        let _ = writeln!(out, "{} ::= {}", rule.name, w3c_node(&rule.node));
+    }
+    out
+}
+
+fn w3c_node(node: &Node) -> String {
+    match node {
+        Node::Literal(text) => quoted(text),
+        Node::Any => "[#x0-#x10FFFF]".to_string(),
+        Node::Class(ranges) => class(ranges, w3c_char),
+        Node::Reference(rule) => rule().name,
+        Node::External(name) => name.clone(),
+        Node::Sequence(nodes) => {
+            let items: Vec<_> = Node::matched(nodes).map(w3c_item).collect();
+            match items.is_empty() {
+                true => "\"\"".to_string(),
+                false => items.join(" "),
+            }
+        }
+        Node::Choice(nodes) => nodes.iter().map(w3c_node).collect::<Vec<_>>().join(" | "),
+        Node::Repeat { node, min, max } => {
+            let item = match node.is_compound() || matches!(**node, Node::Repeat { .. }) {
+                true => format!("({})", w3c_node(node)),
+                false => w3c_node(node),
+            };
+            let mut items = vec![item.clone(); *min];
+            match max.map(|max| max - min) {
+                None if *min > 0 => {
+                    items.pop();
+                    items.push(format!("{}+", item));
+                }
+                None => items.push(format!("{}*", item)),
+                Some(optional) => items.extend(vec![format!("{}?", item); optional]),
+            }
+            items.join(" ")
+        }
+        Node::Cut => "\"\"".to_string(),
+    }
+}
+
+fn w3c_item(node: &Node) -> String {
+    match node.is_compound() {
+        true => format!("({})", w3c_node(node)),
+        false => w3c_node(node),
+    }
+}
+
+/// Writes a character within a W3C character class, using its code point if it isn't plainly printable.
+fn w3c_char(c: char) -> String {
+    match c {
+        '-' | ']' | '[' | '^' | '#' => format!("#x{:X}", c as u32),
+        c if c.is_ascii_graphic() => c.to_string(),
+        c => format!("#x{:X}", c as u32),
+    }
+}
+
+/// Renders rules as a JSON array of `{"name": ..., "node": ...}` objects, for railroad-diagram generators.
+///
+/// Each node is an object with a `type` of `literal` (with `text`), `any`, `class` (with `ranges`, a list of
+/// `[start, end]` pairs), `reference` (with the `name` of a rule), `external` (with a `name`), `sequence` or
+/// `choice` (with a list of `items`), or `repeat` (with an `item`, `min` and `max`, which is `null` if unbounded).
+/// Cuts are left out of sequences.
+pub fn to_json(rules: &[Rule]) -> String {
+    let rules: Vec<_> = rules
+        .iter()
+        .map(|rule| {
+            format!(
+                "{{\"name\":{},\"node\":{}}}",
+                json_string(&rule.name),
+                json_node(&rule.node)
+            )
+        })
+        .collect();
+    format!("[{}]", rules.join(","))
+}
+
+fn json_node(node: &Node) -> String {
+    match node {
+        Node::Literal(text) => format!("{{\"type\":\"literal\",\"text\":{}}}", json_string(text)),
+        Node::Any => "{\"type\":\"any\"}".to_string(),
+        Node::Class(ranges) => {
+            let ranges: Vec<_> = ranges
+                .iter()
+                .map(|(start, end)| {
+                    format!(
+                        "[{},{}]",
+                        json_string(&start.to_string()),
+                        json_string(&end.to_string())
+                    )
+                })
+                .collect();
+            format!("{{\"type\":\"class\",\"ranges\":[{}]}}", ranges.join(","))
+        }
+        Node::Reference(rule) => format!(
+            "{{\"type\":\"reference\",\"name\":{}}}",
+            json_string(&rule().name)
+        ),
+        Node::External(name) => {
+            format!("{{\"type\":\"external\",\"name\":{}}}", json_string(name))
+        }
+        Node::Sequence(nodes) | Node::Choice(nodes) => {
+            let kind = match node {
+                Node::Sequence(_) => "sequence",
+                _ => "choice",
+            };
+            let items: Vec<_> = match node {
+                Node::Sequence(_) => Node::matched(nodes).map(json_node).collect(),
+                _ => nodes.iter().map(json_node).collect(),
+            };
+            format!("{{\"type\":\"{}\",\"items\":[{}]}}", kind, items.join(","))
+        }
+        Node::Repeat { node, min, max } => format!(
+            "{{\"type\":\"repeat\",\"item\":{},\"min\":{},\"max\":{}}}",
+            json_node(node),
+            min,
+            max.map_or("null".to_string(), |max| max.to_string())
+        ),
+        Node::Cut => "{\"type\":\"sequence\",\"items\":[]}".to_string(),
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Quotes literal text, using single quotes if the text contains a double quote.
+fn quoted(text: &str) -> String {
+    match text.contains('"') {
+        true => format!("'{}'", text),
+        false => format!("\"{}\"", text),
+    }
+}
+
+/// Writes a bracketed character class, such as `[a-z_]`.
+fn class(ranges: &[(char, char)], write_char: impl Fn(char) -> String) -> String {
+    let mut out = String::from("[");
+    for &(start, end) in ranges {
+        out.push_str(&write_char(start));
+        if end != start {
+            out.push('-');
+            out.push_str(&write_char(end));
+        }
+    }
+    out.push(']');
+    out
+}
+
+impl<T: Grammar> Grammar for Box<T> {
+    #[inline]
+    fn rule() -> Rule {
+        T::rule()
+    }
+}
+
+impl Grammar for bool {
+    fn rule() -> Rule {
+        Rule {
+            name: "bool".to_string(),
+            node: Node::Choice(vec![
+                Node::Literal("true".to_string()),
+                Node::Literal("false".to_string()),
+            ]),
+        }
+    }
+}
+
+impl Grammar for char {
+    fn rule() -> Rule {
+        Rule {
+            name: "char".to_string(),
+            node: Node::Any,
+        }
+    }
+}
+
+impl Grammar for String {
+    fn rule() -> Rule {
+        Rule {
+            name: "String".to_string(),
+            node: Node::Repeat {
+                node: Box::new(Node::Any),
+                min: 0,
+                max: None,
+            },
+        }
+    }
+}
+
+/// Describes an unsigned integer without leading zeros (`0 | [1-9] [0-9]*`).
+fn unsigned_node() -> Node {
+    Node::Choice(vec![
+        Node::Literal("0".to_string()),
+        Node::Sequence(vec![
+            Node::Class(vec![('1', '9')]),
+            Node::Repeat {
+                node: Box::new(Node::Class(vec![('0', '9')])),
+                min: 0,
+                max: None,
+            },
+        ]),
+    ])
+}
+
+macro_rules! unsigned_grammar {
+    ($($ty:ty),*) => {$(
+        impl Grammar for $ty {
+            fn rule() -> Rule {
+                Rule {
+                    name: stringify!($ty).to_string(),
+                    node: unsigned_node(),
+                }
+            }
+        }
+    )*};
+}
+
+macro_rules! signed_grammar {
+    ($($ty:ty),*) => {$(
+        impl Grammar for $ty {
+            fn rule() -> Rule {
+                Rule {
+                    name: stringify!($ty).to_string(),
+                    node: Node::Sequence(vec![
+                        Node::Repeat {
+                            node: Box::new(Node::Literal("-".to_string())),
+                            min: 0,
+                            max: Some(1),
+                        },
+                        unsigned_node(),
+                    ]),
+                }
+            }
+        }
+    )*};
+}
+
+unsigned_grammar!(u8, u16, u32, u64, u128, usize);
+signed_grammar!(i8, i16, i32, i64, i128, isize);
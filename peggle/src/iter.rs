@@ -0,0 +1,133 @@
+use std::marker::PhantomData;
+use std::ops::Range;
+
+use crate::{Index, Input, Parse, ParseError};
+
+/// The location of a parsed value within its input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Span {
+    /// The offset of the value's start from the start of the input (in units of the input, such as bytes)
+    pub start: usize,
+    /// The offset just past the value's end from the start of the input
+    pub end: usize,
+    /// The line number at which the value starts
+    pub lineno: usize,
+    /// The column number at which the value starts
+    pub colno: usize,
+}
+
+impl Span {
+    /// Returns the span between two indices over the same input of the given total length.
+    #[inline]
+    pub(crate) fn between<I: ?Sized + Input>(
+        input_len: usize,
+        start: Index<'_, I>,
+        end: Index<'_, I>,
+    ) -> Self {
+        Self {
+            start: input_len - start.remaining.len(),
+            end: input_len - end.remaining.len(),
+            lineno: start.lineno,
+            colno: start.colno,
+        }
+    }
+
+    /// The range of offsets covered by the span, suitable for indexing into the input.
+    #[inline]
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// An iterator over consecutive values parsed from an input (see [`Parse::parse_iter`]).
+pub struct ParseIter<'a, T, I: ?Sized + Input = str> {
+    input_len: usize,
+    /// The index to parse the next value from, or `None` once an error has been returned
+    index: Option<Index<'a, I>>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<'a, T, I: ?Sized + Input> ParseIter<'a, T, I> {
+    pub fn new(input: &'a I) -> Self {
+        Self {
+            input_len: input.len(),
+            index: Some(Index::new(input)),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, T: Parse<I>, I: ?Sized + Input> Iterator for ParseIter<'a, T, I> {
+    type Item = Result<(T, Span), ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.index.take()?;
+        if index.remaining.is_empty() {
+            return None;
+        }
+
+        match T::parse_at(index) {
+            // A value that consumes no input would be returned indefinitely
+            Ok((_, end)) if end.remaining.len() == index.remaining.len() => {
+                Some(Err(ParseError::from_index(end)))
+            }
+            Ok((value, end)) => {
+                self.index = Some(end);
+                Some(Ok((value, Span::between(self.input_len, index, end))))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// An iterator over the non-overlapping values found anywhere within an input (see [`Parse::find_all`]).
+pub struct FindIter<'a, T, I: ?Sized + Input = str> {
+    input_len: usize,
+    /// The index to search for the next value from, or `None` once the input has been searched
+    index: Option<Index<'a, I>>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<'a, T, I: ?Sized + Input> FindIter<'a, T, I> {
+    pub fn new(input: &'a I) -> Self {
+        Self {
+            input_len: input.len(),
+            index: Some(Index::new(input)),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, T: Parse<I>, I: ?Sized + Input> Iterator for FindIter<'a, T, I> {
+    type Item = (T, Span);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut index = self.index.take()?;
+
+        loop {
+            if let Ok((value, end)) = T::parse_at(index) {
+                // Searching resumes after the value, or after the next character if the value was empty
+                let mut next = end;
+                if end.remaining.len() == index.remaining.len() && next.next().is_none() {
+                    self.index = None;
+                } else {
+                    self.index = Some(next);
+                }
+
+                return Some((value, Span::between(self.input_len, index, end)));
+            }
+
+            index.next()?;
+        }
+    }
+}
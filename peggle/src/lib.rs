@@ -1,25 +1,481 @@
-/// Keeps track of the current parse location of a string input.
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
-pub struct Index<'a> {
+pub mod grammar;
+mod iter;
+mod read;
+#[cfg(feature = "trace")]
+pub mod trace;
+pub mod vm;
+
+pub use iter::{FindIter, ParseIter, Span};
+pub use read::{ParseReader, ReadError, ReadInput};
+
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
+use std::fmt;
+use std::time::Instant;
+
+/// A type of input that can be parsed, such as a string or a slice of bytes.
+///
+/// Input is read one character at a time: strings yield their Unicode characters, while byte slices yield each byte
+/// as the character of the same value (U+0000 to U+00FF, as in Latin-1). Patterns such as `\xFF` or `[\x80-\xFF]`
+/// therefore match individual bytes of a byte slice.
+pub trait Input: fmt::Debug + Ord {
+    /// Returns the first character of the input, along with its length in units of the input (e.g. bytes).
+    fn first_char(&self) -> Option<(char, usize)>;
+
+    /// The length of the input in units of the input.
+    fn len(&self) -> usize;
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the first `len` units of the input, or `None` if `len` is out of bounds or splits a character.
+    fn prefix(&self, len: usize) -> Option<&Self>;
+
+    /// Returns the input following the first `len` units, or `None` if `len` is out of bounds or splits a character.
+    fn skip(&self, len: usize) -> Option<&Self>;
+
+    /// Returns the input following `literal` if the input begins with it, or `None` otherwise.
+    #[inline]
+    fn strip_literal(&self, literal: &str) -> Option<&Self> {
+        let mut rest = self;
+        for c in literal.chars() {
+            match rest.first_char() {
+                Some((first, len)) if first == c => rest = rest.skip(len)?,
+                _ => return None,
+            }
+        }
+
+        Some(rest)
+    }
+}
+
+impl Input for str {
+    #[inline]
+    fn first_char(&self) -> Option<(char, usize)> {
+        self.chars().next().map(|c| (c, c.len_utf8()))
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        str::len(self)
+    }
+
+    #[inline]
+    fn prefix(&self, len: usize) -> Option<&Self> {
+        self.get(..len)
+    }
+
+    #[inline]
+    fn skip(&self, len: usize) -> Option<&Self> {
+        self.get(len..)
+    }
+
+    #[inline]
+    fn strip_literal(&self, literal: &str) -> Option<&Self> {
+        self.strip_prefix(literal)
+    }
+}
+
+impl Input for [u8] {
+    #[inline]
+    fn first_char(&self) -> Option<(char, usize)> {
+        self.first().map(|&b| (char::from(b), 1))
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        <[u8]>::len(self)
+    }
+
+    #[inline]
+    fn prefix(&self, len: usize) -> Option<&Self> {
+        self.get(..len)
+    }
+
+    #[inline]
+    fn skip(&self, len: usize) -> Option<&Self> {
+        self.get(len..)
+    }
+
+    #[inline]
+    fn strip_literal(&self, literal: &str) -> Option<&Self> {
+        literal
+            .chars()
+            .try_fold(self, |rest, c| match rest.split_first() {
+                Some((&b, tail)) if char::from(b) == c => Some(tail),
+                _ => None,
+            })
+    }
+}
+
+/// A value that can be parsed as an [`Input`], such as a `String` (parsed as `str`) or a `Vec<u8>` (parsed as `[u8]`).
+///
+/// Parsing functions accept any such value, so `Parse::parse(&line)` parses a `String` as its text.
+pub trait AsInput {
+    /// The type of input the value is parsed as.
+    type Input: ?Sized + Input;
+
+    fn as_input(&self) -> &Self::Input;
+}
+
+impl<I: ?Sized + Input> AsInput for I {
+    type Input = I;
+
+    #[inline]
+    fn as_input(&self) -> &I {
+        self
+    }
+}
+
+impl AsInput for String {
+    type Input = str;
+
+    #[inline]
+    fn as_input(&self) -> &str {
+        self
+    }
+}
+
+impl AsInput for Vec<u8> {
+    type Input = [u8];
+
+    #[inline]
+    fn as_input(&self) -> &[u8] {
+        self
+    }
+}
+
+impl<const N: usize> AsInput for [u8; N] {
+    type Input = [u8];
+
+    #[inline]
+    fn as_input(&self) -> &[u8] {
+        self
+    }
+}
+
+/// Reports an event of parsing a derived type to the current thread's tracer (see the `trace` module), or does
+/// nothing unless the `trace` feature is enabled. Used by generated code.
+#[cfg(feature = "trace")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __trace {
+    (enter, $rule:expr, $pattern:expr, $index:expr) => {
+        $crate::trace::enter($rule, $pattern, &$index)
+    };
+    (exit, $rule:expr, $index:expr, $result:expr) => {
+        $crate::trace::exit($rule, &$index, &$result)
+    };
+    (backtrack, $pattern:expr, $index:expr) => {
+        $crate::trace::backtrack($pattern, &$index)
+    };
+}
+
+#[cfg(not(feature = "trace"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __trace {
+    ($($event:tt)*) => {};
+}
+
+/// The default maximum depth to which derived values may be nested within each other (see [`Index::descend`]).
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// Keeps track of the current parse location of an input (a string by default).
+///
+/// Column numbers count units of the input, which for strings are bytes of UTF-8.
+#[derive(Debug)]
+pub struct Index<'a, I: ?Sized + Input = str> {
     /// The remaining data
-    pub remaining: &'a str,
+    pub remaining: &'a I,
     pub lineno: usize,
     pub colno: usize,
+    /// State shared with every other index over the same parse
+    state: Option<&'a ParseState>,
+    /// User-defined context supplied to the parse (see [`Parse::parse_with`])
+    ctx: Option<&'a dyn Any>,
+    /// Whether `remaining` has been restricted to a subset of the input, such that its end isn't the input's end
+    bounded: bool,
+    /// How many derived values are being parsed within each other at this position
+    depth: usize,
+    /// The depth past which parsing fails (see [`Index::with_max_depth`])
+    max_depth: usize,
+}
+
+impl<I: ?Sized + Input> Clone for Index<'_, I> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
 }
 
-impl<'a> Index<'a> {
+impl<I: ?Sized + Input> Copy for Index<'_, I> {}
+
+impl<'a, I: ?Sized + Input> Index<'a, I> {
     #[inline]
-    pub fn new(string: &'a str) -> Self {
+    pub fn new(string: &'a I) -> Self {
         Self {
             remaining: string,
             lineno: 0,
             colno: 0,
+            state: None,
+            ctx: None,
+            bounded: false,
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+
+    /// Creates an index over the given string that shares the given parse state.
+    #[inline]
+    pub fn with_state(string: &'a I, state: &'a ParseState) -> Self {
+        Self {
+            state: Some(state),
+            ..Self::new(string)
+        }
+    }
+
+    /// Returns an index over `remaining` that otherwise keeps this index's position and state.
+    ///
+    /// This is used to restrict parsing to a subset of the input, such as the part matched by a field's pattern.
+    #[inline]
+    pub fn with_remaining(self, remaining: &'a I) -> Self {
+        Self {
+            remaining,
+            bounded: true,
+            ..self
+        }
+    }
+
+    /// Returns an index that carries the given user-defined context.
+    #[inline]
+    pub fn with_context(self, ctx: &'a dyn Any) -> Self {
+        Self {
+            ctx: Some(ctx),
+            ..self
+        }
+    }
+
+    /// Returns an index that fails to parse values nested more than `max_depth` deep (see [`Index::descend`]).
+    ///
+    /// The default of [`DEFAULT_MAX_DEPTH`] is low enough that parsing won't overflow the stack of a typical thread
+    /// (even in debug builds, which use much more stack); a higher limit may need a thread with a larger stack.
+    #[inline]
+    pub fn with_max_depth(self, max_depth: usize) -> Self {
+        Self { max_depth, ..self }
+    }
+
+    /// How many derived values are being parsed within each other at this position.
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns an index for parsing a value nested within the one being parsed, failing with an error of kind
+    /// [`ErrorKind::DepthLimit`] should that exceed the maximum depth.
+    ///
+    /// Derived implementations of [`Parse`] descend on entry, so that deeply nested input (such as `((((...`
+    /// for a recursive type) fails to parse rather than overflowing the stack. The error is marked as occurring
+    /// after a cut, so that no other alternatives are tried once the limit is reached.
+    #[inline]
+    pub fn descend(self) -> Result<Self, ParseError> {
+        if self.depth >= self.max_depth {
+            return Err(ParseError::from_index(self)
+                .with_kind(ErrorKind::DepthLimit)
+                .with_message(format!(
+                    "input is nested more than {} levels deep",
+                    self.max_depth
+                ))
+                .with_cut());
+        }
+
+        Ok(Self {
+            depth: self.depth + 1,
+            ..self
+        })
+    }
+
+    /// Returns an index at the depth of the value being parsed, once the value nested within it (see
+    /// [`Index::descend`]) has been parsed.
+    #[inline]
+    pub fn ascend(self) -> Self {
+        Self {
+            depth: self.depth.saturating_sub(1),
+            ..self
+        }
+    }
+
+    /// Spends a step of the parse's budget (see [`ParseState::with_fuel`]), failing with an error of kind
+    /// [`ErrorKind::FuelExhausted`] or [`ErrorKind::Timeout`] if the budget has run out.
+    ///
+    /// Derived implementations of [`Parse`] take a step on entry and on each iteration of a repetition, while
+    /// reading a character with [`Index::next`] takes a step of its own (returning `None` once the budget has run
+    /// out). The error is marked as occurring after a cut, so that no other alternatives are tried.
+    #[inline]
+    pub fn step(&self) -> Result<(), ParseError> {
+        self.spend();
+        self.check_budget()
+    }
+
+    /// Fails with an error of kind [`ErrorKind::FuelExhausted`] or [`ErrorKind::Timeout`] if the parse's budget has
+    /// run out, without spending any of it.
+    #[inline]
+    pub fn check_budget(&self) -> Result<(), ParseError> {
+        match self.state.and_then(ParseState::exhausted) {
+            Some(kind) => {
+                let message = match kind {
+                    ErrorKind::Timeout => "parsing took longer than allowed",
+                    _ => "parsing took more steps than allowed",
+                };
+                Err(ParseError::from_index(*self)
+                    .with_kind(kind)
+                    .with_message(message)
+                    .with_cut())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Spends a step of the parse's budget, returning whether there was one to spend.
+    #[inline]
+    fn spend(&self) -> bool {
+        match self.state {
+            Some(state) => state.spend(),
+            None => true,
+        }
+    }
+
+    /// Returns the user-defined context supplied to the parse, if there is one of type `C`.
+    #[inline]
+    pub fn context<C: Any>(&self) -> Option<&'a C> {
+        self.ctx.and_then(|ctx| ctx.downcast_ref())
+    }
+
+    /// Whether errors are being recovered from (see [`Parse::parse_recovering`]).
+    #[inline]
+    pub fn is_recovering(&self) -> bool {
+        self.state.is_some_and(|s| s.recovered.is_some())
+    }
+
+    /// Whether a field with a recovery pattern that failed from this index with `err` should recover from it.
+    ///
+    /// Only committed failures are recovered from: those after a cut (`~`), and those at a position where the input
+    /// as a whole failed to parse in an earlier attempt (see [`Parse::parse_recovering`]). Any other failure may yet
+    /// be followed by another alternative or the end of a repetition, so is left to backtrack as usual.
+    #[inline]
+    pub fn recovers_from(&self, err: &ParseError) -> bool {
+        self.state.is_some_and(|s| {
+            s.recovered.is_some()
+                && (err.cut
+                    || s.recovery_points.iter().any(|&point| {
+                        point == (self.lineno, self.colno) || point == (err.lineno, err.colno)
+                    }))
+        })
+    }
+
+    /// Records an error that was recovered from, if errors are being recovered from.
+    #[inline]
+    pub fn record_error(&self, err: ParseError) {
+        if let Some(recovered) = self.state.and_then(|s| s.recovered.as_ref()) {
+            recovered.borrow_mut().push(err);
+        }
+    }
+
+    /// The number of errors recovered from so far.
+    #[inline]
+    pub fn recovered_count(&self) -> usize {
+        self.state
+            .and_then(|s| s.recovered.as_ref())
+            .map_or(0, |r| r.borrow().len())
+    }
+
+    /// Discards any errors recovered from after the first `count`, such as when the alternative that recovered
+    /// from them failed to match.
+    #[inline]
+    pub fn truncate_recovered(&self, count: usize) {
+        if let Some(recovered) = self.state.and_then(|s| s.recovered.as_ref()) {
+            recovered.borrow_mut().truncate(count);
+        }
+    }
+
+    /// Removes and returns the errors recovered from after the first `count`, such as to set them aside while other
+    /// alternatives are tried (they may be recorded again with [`Index::record_error`]).
+    #[inline]
+    pub fn split_recovered(&self, count: usize) -> Vec<ParseError> {
+        match self.state.and_then(|s| s.recovered.as_ref()) {
+            Some(recovered) => {
+                let mut recovered = recovered.borrow_mut();
+                let count = count.min(recovered.len());
+                recovered.split_off(count)
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// Whether more input was needed to finish parsing an incomplete chunk of input (see [`Parse::parse_reader`]).
+    #[inline]
+    pub fn needs_more(&self) -> bool {
+        self.state.is_some_and(|s| s.needs_more.get())
+    }
+
+    /// Notes that the end of the remaining input was reached, which signals that more input is needed if the
+    /// input is an incomplete chunk.
+    #[inline]
+    fn reached_end(&self) {
+        if let Some(state) = self.state.filter(|s| s.partial && !self.bounded) {
+            state.needs_more.set(true);
         }
     }
 
     #[inline]
     pub fn peek(&self) -> Option<char> {
-        self.remaining.chars().next()
+        match self.remaining.first_char() {
+            Some((c, _)) => Some(c),
+            None => {
+                self.reached_end();
+                None
+            }
+        }
+    }
+
+    /// Advances past `literal` if the remaining input begins with it, returning whether it did.
+    ///
+    /// This is equivalent to (but faster than) matching each character of `literal` in turn: should the input not
+    /// begin with `literal`, the index is left at the first character that differs from it.
+    #[inline]
+    pub fn consume(&mut self, literal: &str) -> bool {
+        let Some(rest) = self.remaining.strip_literal(literal) else {
+            // Each character matched before the one that differs costs a step, as reading it would
+            for c in literal.chars() {
+                if self.peek() != Some(c) || self.next().is_none() {
+                    return false;
+                }
+            }
+            return true;
+        };
+
+        // A literal matched at once costs a single step of the parse's budget
+        if !self.spend() {
+            return false;
+        }
+
+        match literal.rfind('\n') {
+            Some(last_newline) => {
+                self.lineno += literal.matches('\n').count();
+                let line_start = self
+                    .remaining
+                    .strip_literal(&literal[..=last_newline])
+                    .expect("the start of a matched literal should also match");
+                self.colno = line_start.len() - rest.len();
+            }
+            None => self.colno += self.remaining.len() - rest.len(),
+        }
+
+        self.remaining = rest;
+        true
     }
 
     #[inline]
@@ -27,12 +483,9 @@ impl<'a> Index<'a> {
         let mut peeked = ['\0'; N];
         // TODO: replace with `try_from_fn` once stable (https://doc.rust-lang.org/std/array/fn.try_from_fn.html)
 
-        let mut chars = self.remaining.chars();
+        let mut chars = *self;
         for p in peeked.iter_mut() {
-            let Some(c) = chars.next() else {
-                return None
-            };
-            *p = c;
+            *p = chars.next()?;
         }
 
         Some(peeked)
@@ -44,10 +497,7 @@ impl<'a> Index<'a> {
         // TODO: replace with `try_from_fn` once stable (https://doc.rust-lang.org/std/array/fn.try_from_fn.html)
 
         for n in all_next.iter_mut() {
-            let Some(c) = self.next() else {
-                return None
-            };
-            *n = c;
+            *n = self.next()?;
         }
         Some(all_next)
     }
@@ -58,13 +508,145 @@ impl<'a> Index<'a> {
     }
 }
 
-impl<'a> Iterator for Index<'a> {
+// Indices are compared by position alone, regardless of their parse state
+impl<I: ?Sized + Input> PartialEq for Index<'_, I> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        (self.remaining, self.lineno, self.colno) == (other.remaining, other.lineno, other.colno)
+    }
+}
+
+impl<I: ?Sized + Input> Eq for Index<'_, I> {}
+
+impl<I: ?Sized + Input> PartialOrd for Index<'_, I> {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<I: ?Sized + Input> Ord for Index<'_, I> {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.remaining, self.lineno, self.colno).cmp(&(other.remaining, other.lineno, other.colno))
+    }
+}
+
+/// State shared between every [`Index`] used over the course of a single parse.
+#[derive(Debug, Default)]
+pub struct ParseState {
+    /// The errors recovered from so far, if error recovery is enabled
+    recovered: Option<RefCell<Vec<ParseError>>>,
+    /// The (line, column) positions at which earlier attempts to parse the input failed, from which fields with
+    /// recovery patterns may recover even without a cut
+    recovery_points: Vec<(usize, usize)>,
+    /// Whether the input is an incomplete chunk that more input may follow
+    partial: bool,
+    /// Set when a partial input's end was reached, such that parsing must be retried once more input is available
+    needs_more: Cell<bool>,
+    /// The number of steps parsing may still take, if limited
+    fuel: Option<Cell<u64>>,
+    /// The time by which parsing must have finished, if limited
+    deadline: Option<Instant>,
+    /// Set once the budget has run out, to the kind of error reported for it
+    exhausted: Cell<Option<ErrorKind>>,
+}
+
+impl ParseState {
+    /// Creates parse state for input that is an incomplete chunk if `partial` is set.
+    #[inline]
+    pub fn streaming(partial: bool) -> Self {
+        Self {
+            partial,
+            ..Self::default()
+        }
+    }
+
+    /// Creates parse state that enables error recovery.
+    #[inline]
+    pub fn recovering() -> Self {
+        Self {
+            recovered: Some(RefCell::new(Vec::new())),
+            ..Self::default()
+        }
+    }
+
+    /// Limits parsing to the given number of steps, after which it fails with an error of kind
+    /// [`ErrorKind::FuelExhausted`] (see [`Index::step`] for what counts as a step).
+    #[inline]
+    pub fn with_fuel(self, steps: u64) -> Self {
+        Self {
+            fuel: Some(Cell::new(steps)),
+            ..self
+        }
+    }
+
+    /// Limits parsing to finishing by `deadline`, after which it fails with an error of kind [`ErrorKind::Timeout`].
+    ///
+    /// The time is checked whenever a derived value is parsed and on each iteration of a repetition, so a single
+    /// hand-written implementation of [`Parse`] that runs for a long time can overrun the deadline.
+    #[inline]
+    pub fn with_deadline(self, deadline: Instant) -> Self {
+        Self {
+            deadline: Some(deadline),
+            ..self
+        }
+    }
+
+    /// The number of steps parsing may still take, if limited with [`ParseState::with_fuel`].
+    #[inline]
+    pub fn remaining_fuel(&self) -> Option<u64> {
+        self.fuel.as_ref().map(Cell::get)
+    }
+
+    /// Spends a step of fuel, returning whether the budget had any left.
+    #[inline]
+    fn spend(&self) -> bool {
+        if self.exhausted.get().is_some() {
+            return false;
+        }
+
+        if let Some(fuel) = &self.fuel {
+            match fuel.get().checked_sub(1) {
+                Some(left) => fuel.set(left),
+                None => {
+                    self.exhausted.set(Some(ErrorKind::FuelExhausted));
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Returns the kind of error to report if the budget has run out, checking the deadline if there is one.
+    #[inline]
+    fn exhausted(&self) -> Option<ErrorKind> {
+        if self.exhausted.get().is_none() && self.deadline.is_some_and(|d| Instant::now() >= d) {
+            self.exhausted.set(Some(ErrorKind::Timeout));
+        }
+        self.exhausted.get()
+    }
+
+    /// Consumes the state, returning the errors recovered from during parsing.
+    #[inline]
+    pub fn into_errors(self) -> Vec<ParseError> {
+        self.recovered.map(RefCell::into_inner).unwrap_or_default()
+    }
+}
+
+impl<I: ?Sized + Input> Iterator for Index<'_, I> {
     type Item = char;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let c = self.remaining.chars().next()?;
-        let len = c.len_utf8();
-        self.remaining = self.remaining.get(len..)?;
+        if !self.spend() {
+            return None;
+        }
+
+        let Some((c, len)) = self.remaining.first_char() else {
+            self.reached_end();
+            return None;
+        };
+        self.remaining = self.remaining.skip(len)?;
 
         if c == '\n' {
             self.lineno += 1;
@@ -77,46 +659,136 @@ impl<'a> Iterator for Index<'a> {
     }
 }
 
+/// What caused a [`ParseError`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The input didn't match what was expected
+    #[default]
+    Syntax,
+    /// Values were nested within each other beyond the maximum depth (see [`Index::with_max_depth`])
+    DepthLimit,
+    /// Parsing took more steps than allowed (see [`ParseState::with_fuel`])
+    FuelExhausted,
+    /// Parsing didn't finish by its deadline (see [`ParseState::with_deadline`])
+    Timeout,
+    /// A type that requires a user-defined context (with `#[peg(ctx = ...)]`) was parsed without a context of that
+    /// type (see [`Parse::parse_with`])
+    MissingContext,
+}
+
 /// Represents an error that occurred during the parsing of a string input.
 #[derive(Debug)]
 pub struct ParseError {
     pub lineno: usize,
     pub colno: usize,
+    /// What caused the error
+    pub kind: ErrorKind,
+    /// Set if the error occurred after a cut (`~`) committed the parser to the alternative it was matching.
+    /// Such errors are returned as-is rather than causing any other alternatives to be tried.
+    pub cut: bool,
+    /// A description of what was expected at the error's position, if the failing element was labeled
+    /// (e.g. `<name:expected 'an identifier'>` or `#[peg(expected = "an identifier")]`).
+    pub expected: Option<String>,
+    /// A message describing why the input was rejected, such as from a failed `#[peg(validate = ...)]` check.
+    pub message: Option<String>,
 }
 
 impl ParseError {
     /// Crates an error containing the given index's line/column information.
-    pub fn from_index(idx: Index<'_>) -> Self {
+    pub fn from_index<I: ?Sized + Input>(idx: Index<'_, I>) -> Self {
         Self {
             lineno: idx.lineno,
             colno: idx.colno,
+            kind: ErrorKind::Syntax,
+            cut: false,
+            expected: None,
+            message: None,
+        }
+    }
+
+    /// Sets what caused the error.
+    #[inline]
+    pub fn with_kind(mut self, kind: ErrorKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Marks the error as having occurred after a cut.
+    #[inline]
+    pub fn with_cut(mut self) -> Self {
+        self.cut = true;
+        self
+    }
+
+    /// Sets a description of what was expected at the error's position.
+    #[inline]
+    pub fn with_expected(mut self, expected: impl Into<String>) -> Self {
+        self.expected = Some(expected.into());
+        self
+    }
+
+    /// Sets a message describing why the input was rejected.
+    #[inline]
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    /// Whether the error describes what went wrong (with an expected label or message), making it more useful to
+    /// report than an error that merely gives a position.
+    #[inline]
+    pub fn is_descriptive(&self) -> bool {
+        self.expected.is_some() || self.message.is_some()
+    }
+
+    /// Returns whichever of the two errors occurred furthest into the input, preferring `other` if both occurred
+    /// at the same position.
+    #[inline]
+    pub fn furthest(self, other: Self) -> Self {
+        if (self.lineno, self.colno) > (other.lineno, other.colno) {
+            self
+        } else {
+            other
         }
     }
 }
 
-pub trait Parse: Sized {
-    fn parse_at(index: Index<'_>) -> Result<(Self, Index<'_>), ParseError>;
+impl fmt::Display for ParseError {
+    /// Formats the error with 1-based line and column numbers.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.message, &self.expected) {
+            (Some(message), _) => write!(f, "{}", message)?,
+            (None, Some(expected)) => write!(f, "expected {}", expected)?,
+            (None, None) => write!(f, "unexpected input")?,
+        }
+
+        write!(f, " at line {}, column {}", self.lineno + 1, self.colno + 1)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// A type that can be parsed from an input of type `I` (a string by default).
+pub trait Parse<I: ?Sized + Input = str>: Sized {
+    fn parse_at(index: Index<'_, I>) -> Result<(Self, Index<'_, I>), ParseError>;
 
     #[inline]
-    fn parse_raw_at(index: Index<'_>) -> Result<(&str, Index<'_>), ParseError> {
+    fn parse_raw_at(index: Index<'_, I>) -> Result<(&I, Index<'_, I>), ParseError> {
         let (_, new_index) = Self::parse_at(index)?;
 
         let consumed_len = index.remaining.len() - new_index.remaining.len();
         let consumed = index
             .remaining
-            .get(..consumed_len)
+            .prefix(consumed_len)
             .ok_or(ParseError::from_index(index))?;
 
         Ok((consumed, new_index))
     }
 
     #[inline]
-    fn parse(input: &str) -> Result<Self, ParseError> {
-        let idx = Index {
-            remaining: input,
-            lineno: 0,
-            colno: 0,
-        };
+    fn parse<S: ?Sized + AsInput<Input = I>>(input: &S) -> Result<Self, ParseError> {
+        let idx = Index::new(input.as_input());
 
         let (ret, remaining) = Self::parse_at(idx)?;
         if remaining.remaining.is_empty() {
@@ -125,18 +797,144 @@ pub trait Parse: Sized {
             Err(ParseError::from_index(idx))
         }
     }
+
+    /// Parses the entire input with the given user-defined context, which is made available to every value parsed
+    /// through [`Index::context`].
+    ///
+    /// Parsing only has shared access to the context, so anything that should be updated while parsing (such as a
+    /// symbol table) must use interior mutability, e.g. `RefCell<HashSet<String>>`.
+    #[inline]
+    fn parse_with<S: ?Sized + AsInput<Input = I>, C: Any>(
+        input: &S,
+        ctx: &C,
+    ) -> Result<Self, ParseError> {
+        let idx = Index::new(input.as_input()).with_context(ctx);
+
+        let (ret, remaining) = Self::parse_at(idx)?;
+        if remaining.remaining.is_empty() {
+            Ok(ret)
+        } else {
+            Err(ParseError::from_index(idx))
+        }
+    }
+
+    /// Parses the entire input sharing the given state, such as one that limits how long parsing may take:
+    ///
+    /// ```ignore
+    /// let state = ParseState::default().with_fuel(100_000);
+    /// let request = Request::parse_with_state(input, &state)?;
+    /// ```
+    #[inline]
+    fn parse_with_state<S: ?Sized + AsInput<Input = I>>(
+        input: &S,
+        state: &ParseState,
+    ) -> Result<Self, ParseError> {
+        let idx = Index::with_state(input.as_input(), state);
+
+        let (ret, remaining) = Self::parse_at(idx)?;
+        if remaining.remaining.is_empty() {
+            Ok(ret)
+        } else {
+            Err(ParseError::from_index(idx))
+        }
+    }
+
+    /// Parses a value from the start of the input, returning it along with the input that remains after it.
+    #[inline]
+    fn parse_prefix<S: ?Sized + AsInput<Input = I>>(input: &S) -> Result<(Self, &I), ParseError> {
+        Self::parse_at(Index::new(input.as_input()))
+            .map(|(ret, remaining)| (ret, remaining.remaining))
+    }
+
+    /// Parses consecutive values from the input, along with the span each was parsed from.
+    ///
+    /// The iterator ends once the entire input has been parsed, or after the first error.
+    #[inline]
+    fn parse_iter<S: ?Sized + AsInput<Input = I>>(input: &S) -> ParseIter<'_, Self, I> {
+        ParseIter::new(input.as_input())
+    }
+
+    /// Finds the first position within the input from which a value can be parsed, returning the value and the
+    /// span it was parsed from.
+    #[inline]
+    fn find<S: ?Sized + AsInput<Input = I>>(input: &S) -> Option<(Self, Span)> {
+        Self::find_all(input).next()
+    }
+
+    /// Finds every non-overlapping value within the input, from start to end, along with their spans.
+    ///
+    /// Searching resumes after the end of each value found (or after the next character, for empty values).
+    #[inline]
+    fn find_all<S: ?Sized + AsInput<Input = I>>(input: &S) -> FindIter<'_, Self, I> {
+        FindIter::new(input.as_input())
+    }
+
+    /// Parses the entire input, recovering from errors wherever a `#[peg(recover = "...")]` pattern allows.
+    ///
+    /// Returns the best-effort parsed value (if parsing could continue past every error) along with every error
+    /// encountered, in the order they occurred. The value is returned even if trailing input remained unparsed,
+    /// in which case an error is reported at the start of that input.
+    ///
+    /// Input that [`Parse::parse`] accepts is parsed identically, without errors. Otherwise, recovery only takes
+    /// place where a failure is committed (see [`Index::recovers_from`]): the input is first parsed recovering only
+    /// from failures after a cut, and each time the input as a whole fails to parse, it's parsed again with fields
+    /// also recovering from failures at the position of that error. This continues until parsing succeeds, or
+    /// fails at a position it already failed at.
+    fn parse_recovering<S: ?Sized + AsInput<Input = I>>(
+        input: &S,
+    ) -> (Option<Self>, Vec<ParseError>) {
+        let input = input.as_input();
+        let mut recovery_points = Vec::new();
+        loop {
+            let state = ParseState {
+                recovery_points: recovery_points.clone(),
+                ..ParseState::recovering()
+            };
+            let idx = Index::with_state(input, &state);
+
+            let (ret, err) = match Self::parse_at(idx) {
+                Ok((ret, remaining)) if remaining.remaining.is_empty() => {
+                    return (Some(ret), state.into_errors())
+                }
+                Ok((ret, remaining)) => (Some(ret), ParseError::from_index(remaining)),
+                Err(e) => (None, e),
+            };
+
+            let point = (err.lineno, err.colno);
+            if err.kind != ErrorKind::Syntax || recovery_points.contains(&point) {
+                idx.record_error(err);
+                return (ret, state.into_errors());
+            }
+            recovery_points.push(point);
+        }
+    }
+
+    /// Parses consecutive values from a reader, reading only as much input as each value requires.
+    ///
+    /// Input is read in chunks; should a value reach the end of the chunk read so far, more input is read and the
+    /// value is parsed again. The iterator ends once all input has been consumed, or after the first error.
+    ///
+    /// As the reader doesn't determine the type of input, it must be named for types that parse several, e.g.
+    /// `<Record as Parse>::parse_reader(file)` (which parses `str`) or `<Record as Parse<[u8]>>::parse_reader(socket)`.
+    #[inline]
+    fn parse_reader<R: std::io::Read>(reader: R) -> ParseReader<Self, R, I>
+    where
+        I: ReadInput,
+    {
+        ParseReader::new(reader)
+    }
 }
 
-impl<T: Parse> Parse for Box<T> {
+impl<I: ?Sized + Input, T: Parse<I>> Parse<I> for Box<T> {
     #[inline]
-    fn parse_at(index: Index<'_>) -> Result<(Self, Index<'_>), ParseError> {
+    fn parse_at(index: Index<'_, I>) -> Result<(Self, Index<'_, I>), ParseError> {
         T::parse_at(index).map(|(val, idx)| (Box::new(val), idx))
     }
 }
 
-impl Parse for bool {
+impl<I: ?Sized + Input> Parse<I> for bool {
     #[inline]
-    fn parse_at(mut index: Index<'_>) -> Result<(Self, Index<'_>), ParseError> {
+    fn parse_at(mut index: Index<'_, I>) -> Result<(Self, Index<'_, I>), ParseError> {
         match index.next_multiple() {
             Some(['t', 'r', 'u', 'e']) => Ok((true, index)),
             Some(['f', 'a', 'l', 's']) if index.next() == Some('e') => Ok((true, index)),
@@ -145,102 +943,101 @@ impl Parse for bool {
     }
 }
 
-impl Parse for u8 {
+impl<I: ?Sized + Input> Parse<I> for u8 {
     #[inline]
-    fn parse_at(index: Index<'_>) -> Result<(Self, Index<'_>), ParseError> {
+    fn parse_at(index: Index<'_, I>) -> Result<(Self, Index<'_, I>), ParseError> {
         parse_unsigned(index)
     }
 }
 
-impl Parse for u16 {
+impl<I: ?Sized + Input> Parse<I> for u16 {
     #[inline]
-    fn parse_at(index: Index<'_>) -> Result<(Self, Index<'_>), ParseError> {
+    fn parse_at(index: Index<'_, I>) -> Result<(Self, Index<'_, I>), ParseError> {
         parse_unsigned(index)
     }
 }
 
-impl Parse for u32 {
+impl<I: ?Sized + Input> Parse<I> for u32 {
     #[inline]
-    fn parse_at(index: Index<'_>) -> Result<(Self, Index<'_>), ParseError> {
+    fn parse_at(index: Index<'_, I>) -> Result<(Self, Index<'_, I>), ParseError> {
         parse_unsigned(index)
     }
 }
 
-impl Parse for u64 {
+impl<I: ?Sized + Input> Parse<I> for u64 {
     #[inline]
-    fn parse_at(index: Index<'_>) -> Result<(Self, Index<'_>), ParseError> {
+    fn parse_at(index: Index<'_, I>) -> Result<(Self, Index<'_, I>), ParseError> {
         parse_unsigned(index)
     }
 }
 
-impl Parse for u128 {
+impl<I: ?Sized + Input> Parse<I> for u128 {
     #[inline]
-    fn parse_at(index: Index<'_>) -> Result<(Self, Index<'_>), ParseError> {
+    fn parse_at(index: Index<'_, I>) -> Result<(Self, Index<'_, I>), ParseError> {
         parse_unsigned(index)
     }
 }
 
-impl Parse for usize {
+impl<I: ?Sized + Input> Parse<I> for usize {
     #[inline]
-    fn parse_at(index: Index<'_>) -> Result<(Self, Index<'_>), ParseError> {
+    fn parse_at(index: Index<'_, I>) -> Result<(Self, Index<'_, I>), ParseError> {
         parse_unsigned(index)
     }
 }
 
-impl Parse for i8 {
+impl<I: ?Sized + Input> Parse<I> for i8 {
     #[inline]
-    fn parse_at(index: Index<'_>) -> Result<(Self, Index<'_>), ParseError> {
+    fn parse_at(index: Index<'_, I>) -> Result<(Self, Index<'_, I>), ParseError> {
         parse_signed(index)
     }
 }
 
-impl Parse for i16 {
+impl<I: ?Sized + Input> Parse<I> for i16 {
     #[inline]
-    fn parse_at(index: Index<'_>) -> Result<(Self, Index<'_>), ParseError> {
+    fn parse_at(index: Index<'_, I>) -> Result<(Self, Index<'_, I>), ParseError> {
         parse_signed(index)
     }
 }
 
-impl Parse for i32 {
+impl<I: ?Sized + Input> Parse<I> for i32 {
     #[inline]
-    fn parse_at(index: Index<'_>) -> Result<(Self, Index<'_>), ParseError> {
+    fn parse_at(index: Index<'_, I>) -> Result<(Self, Index<'_, I>), ParseError> {
         parse_signed(index)
     }
 }
 
-impl Parse for i64 {
+impl<I: ?Sized + Input> Parse<I> for i64 {
     #[inline]
-    fn parse_at(index: Index<'_>) -> Result<(Self, Index<'_>), ParseError> {
+    fn parse_at(index: Index<'_, I>) -> Result<(Self, Index<'_, I>), ParseError> {
         parse_signed(index)
     }
 }
 
-impl Parse for i128 {
+impl<I: ?Sized + Input> Parse<I> for i128 {
     #[inline]
-    fn parse_at(index: Index<'_>) -> Result<(Self, Index<'_>), ParseError> {
+    fn parse_at(index: Index<'_, I>) -> Result<(Self, Index<'_, I>), ParseError> {
         parse_signed(index)
     }
 }
 
-impl Parse for isize {
+impl<I: ?Sized + Input> Parse<I> for isize {
     #[inline]
-    fn parse_at(index: Index<'_>) -> Result<(Self, Index<'_>), ParseError> {
+    fn parse_at(index: Index<'_, I>) -> Result<(Self, Index<'_, I>), ParseError> {
         parse_signed(index)
     }
 }
 
-impl Parse for String {
+impl<I: ?Sized + Input> Parse<I> for String {
     #[inline]
-    fn parse_at(mut index: Index<'_>) -> Result<(Self, Index<'_>), ParseError> {
-        let s = index.remaining.to_string();
-        index.advance_to_end();
+    fn parse_at(mut index: Index<'_, I>) -> Result<(Self, Index<'_, I>), ParseError> {
+        let s = index.by_ref().collect();
         Ok((s, index))
     }
 }
 
-impl Parse for char {
+impl<I: ?Sized + Input> Parse<I> for char {
     #[inline]
-    fn parse_at(mut index: Index<'_>) -> Result<(Self, Index<'_>), ParseError> {
+    fn parse_at(mut index: Index<'_, I>) -> Result<(Self, Index<'_, I>), ParseError> {
         index
             .next()
             .map(|c| (c, index))
@@ -248,9 +1045,12 @@ impl Parse for char {
     }
 }
 
-fn parse_unsigned<I: num::Unsigned + num::CheckedAdd + num::CheckedMul + From<u8>>(
-    mut index: Index<'_>,
-) -> Result<(I, Index<'_>), ParseError> {
+fn parse_unsigned<
+    I: ?Sized + Input,
+    N: num::Unsigned + num::CheckedAdd + num::CheckedMul + From<u8>,
+>(
+    mut index: Index<'_, I>,
+) -> Result<(N, Index<'_, I>), ParseError> {
     let mut value = None;
 
     // Edge case: 0 (no leading zeros are allowed)
@@ -258,15 +1058,15 @@ fn parse_unsigned<I: num::Unsigned + num::CheckedAdd + num::CheckedMul + From<u8
         index.next();
         return match index.peek() {
             Some('0'..='9') => Err(ParseError::from_index(index)),
-            _ => Ok((I::zero(), index)),
+            _ => Ok((N::zero(), index)),
         };
     }
 
     // Match characters 0-9, checking for overflow
     while let Some(c @ '0'..='9') = index.peek() {
-        let c_val = I::from((c as u8) - b'0');
+        let c_val = N::from((c as u8) - b'0');
         value = value
-            .unwrap_or(I::zero())
+            .unwrap_or(N::zero())
             .checked_mul(&10u8.into())
             .and_then(|i| i.checked_add(&c_val));
         if value.is_none() {
@@ -283,9 +1083,12 @@ fn parse_unsigned<I: num::Unsigned + num::CheckedAdd + num::CheckedMul + From<u8
         .map(|i| (i, index))
 }
 
-fn parse_signed<I: num::Signed + num::CheckedAdd + num::CheckedSub + num::CheckedMul + From<i8>>(
-    mut index: Index<'_>,
-) -> Result<(I, Index<'_>), ParseError> {
+fn parse_signed<
+    I: ?Sized + Input,
+    N: num::Signed + num::CheckedAdd + num::CheckedSub + num::CheckedMul + From<i8>,
+>(
+    mut index: Index<'_, I>,
+) -> Result<(N, Index<'_, I>), ParseError> {
     let mut value = None;
 
     let mut is_negative = false;
@@ -300,14 +1103,14 @@ fn parse_signed<I: num::Signed + num::CheckedAdd + num::CheckedSub + num::Checke
         index.next();
         return match index.peek() {
             Some('0'..='9') => Err(ParseError::from_index(index)),
-            _ => Ok((I::zero(), index)),
+            _ => Ok((N::zero(), index)),
         };
     }
 
     // Match characters 0-9, checking for overflow
     while let Some(c @ '0'..='9') = index.peek() {
-        let c_val = I::from((c as i8) - ('0' as i8));
-        value = value.unwrap_or(I::zero()).checked_mul(&10i8.into());
+        let c_val = N::from((c as i8) - ('0' as i8));
+        value = value.unwrap_or(N::zero()).checked_mul(&10i8.into());
 
         value = if is_negative {
             value.and_then(|i| i.checked_sub(&c_val))
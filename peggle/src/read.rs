@@ -0,0 +1,195 @@
+use std::fmt;
+use std::io::{self, Read};
+use std::marker::PhantomData;
+
+use crate::{Index, Input, Parse, ParseError, ParseState};
+
+/// The number of bytes requested from the reader each time more input is needed.
+const CHUNK_SIZE: usize = 8192;
+
+/// An [`Input`] that can be read incrementally from a stream of bytes.
+pub trait ReadInput: Input {
+    /// Interprets as much of the given bytes as forms complete input.
+    ///
+    /// An incomplete sequence at the end of the bytes (such as part of a UTF-8 character) is left out of the input,
+    /// as it may be completed by bytes yet to be read. Returns `None` if the bytes are invalid for the input type.
+    fn from_bytes(bytes: &[u8]) -> Option<&Self>;
+}
+
+impl ReadInput for str {
+    #[inline]
+    fn from_bytes(bytes: &[u8]) -> Option<&Self> {
+        match std::str::from_utf8(bytes) {
+            Ok(s) => Some(s),
+            Err(e) if e.error_len().is_none() => {
+                std::str::from_utf8(&bytes[..e.valid_up_to()]).ok()
+            }
+            Err(_) => None,
+        }
+    }
+}
+
+impl ReadInput for [u8] {
+    #[inline]
+    fn from_bytes(bytes: &[u8]) -> Option<&Self> {
+        Some(bytes)
+    }
+}
+
+/// An error that occurred while parsing values from a reader.
+#[derive(Debug)]
+pub enum ReadError {
+    /// Reading from the reader failed, or it produced data that isn't valid for the input type
+    Io(io::Error),
+    /// A value failed to parse
+    Parse(ParseError),
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadError::Io(e) => write!(f, "failed to read input: {}", e),
+            ReadError::Parse(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ReadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReadError::Io(e) => Some(e),
+            ReadError::Parse(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for ReadError {
+    #[inline]
+    fn from(e: io::Error) -> Self {
+        ReadError::Io(e)
+    }
+}
+
+impl From<ParseError> for ReadError {
+    #[inline]
+    fn from(e: ParseError) -> Self {
+        ReadError::Parse(e)
+    }
+}
+
+/// An iterator over the values parsed from a reader (see [`Parse::parse_reader`]).
+pub struct ParseReader<T, R, I: ?Sized = str> {
+    reader: R,
+    /// Input that has been read but not yet parsed
+    buf: Vec<u8>,
+    /// Whether the reader has reached the end of its input
+    eof: bool,
+    /// Whether an error has been returned, ending the iterator
+    failed: bool,
+    lineno: usize,
+    colno: usize,
+    _marker: PhantomData<fn(&I) -> T>,
+}
+
+impl<T, R: Read, I: ?Sized + ReadInput> ParseReader<T, R, I> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buf: Vec::new(),
+            eof: false,
+            failed: false,
+            lineno: 0,
+            colno: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Appends the next chunk of the reader's input to the buffer, noting when the reader has no more input.
+    fn read_chunk(&mut self) -> io::Result<()> {
+        let len = self.buf.len();
+        self.buf.resize(len + CHUNK_SIZE, 0);
+
+        let res = loop {
+            match self.reader.read(&mut self.buf[len..]) {
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                res => break res,
+            }
+        };
+
+        let read = *res.as_ref().unwrap_or(&0);
+        self.buf.truncate(len + read);
+        self.eof = read == 0;
+        res.map(|_| ())
+    }
+
+    fn fail<E: Into<ReadError>>(&mut self, err: E) -> Option<Result<T, ReadError>> {
+        self.failed = true;
+        Some(Err(err.into()))
+    }
+}
+
+impl<T: Parse<I>, R: Read, I: ?Sized + ReadInput> Iterator for ParseReader<T, R, I> {
+    type Item = Result<T, ReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        loop {
+            if self.buf.is_empty() && !self.eof {
+                if let Err(e) = self.read_chunk() {
+                    return self.fail(e);
+                }
+                continue;
+            }
+
+            let Some(input) = I::from_bytes(&self.buf) else {
+                return self.fail(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "input is not valid for its type",
+                ));
+            };
+
+            if self.eof {
+                if input.is_empty() && self.buf.is_empty() {
+                    return None;
+                } else if input.len() < self.buf.len() {
+                    return self.fail(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "input ended partway through a character",
+                    ));
+                }
+            }
+
+            let state = ParseState::streaming(!self.eof);
+            let mut index = Index::with_state(input, &state);
+            index.lineno = self.lineno;
+            index.colno = self.colno;
+
+            let res = T::parse_at(index);
+            if index.needs_more() {
+                // The value may continue past the input read so far, so it must be parsed again with more input
+                if let Err(e) = self.read_chunk() {
+                    return self.fail(e);
+                }
+                continue;
+            }
+
+            return match res {
+                Ok((_, end)) if end.remaining.len() == input.len() => {
+                    // A value that consumes no input would be returned indefinitely
+                    self.fail(ParseError::from_index(end))
+                }
+                Ok((value, end)) => {
+                    let consumed = input.len() - end.remaining.len();
+                    self.lineno = end.lineno;
+                    self.colno = end.colno;
+                    self.buf.drain(..consumed);
+                    Some(Ok(value))
+                }
+                Err(e) => self.fail(e),
+            };
+        }
+    }
+}
@@ -0,0 +1,155 @@
+//! Tracing of how derived types parse their input, enabled by the `trace` feature.
+//!
+//! With the feature enabled, the code generated by `#[derive(Parse)]` reports to a [`Tracer`] whenever it begins
+//! parsing a type, finishes parsing one (whether or not it matched), and gives up on an alternative to try the next
+//! one. Each thread has its own tracer, which ignores every event until replaced with [`set_tracer`]. Installing a
+//! [`PrintTracer`] with `set_tracer(Box::new(PrintTracer))` writes them to standard error as an indented tree:
+//!
+//! ```text
+//! Statement "<0> | print\(<0>\);" at 1:1
+//!   Assignment "let ~<name> = <value>;" at 1:1
+//!   Assignment failed at 1:1
+//!   backtracking from "<0>" at 1:1
+//!   Ident "<0>" at 1:7
+//!   Ident matched at 1:8
+//! Statement matched at 1:10
+//! ```
+//!
+//! Backtracking within a pattern compiled for the `vm` backend isn't reported, only the types it parses.
+
+use std::cell::RefCell;
+use std::io::Write;
+
+use crate::{Index, Input, ParseError};
+
+/// Where in the input a traced event occurred.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Location {
+    /// The (0-based) line number
+    pub lineno: usize,
+    /// The (0-based) column number
+    pub colno: usize,
+    /// How many derived values are being parsed within each other (see [`Index::depth`]), starting from 1 for the
+    /// outermost
+    pub depth: usize,
+}
+
+impl Location {
+    fn of<I: ?Sized + Input>(index: &Index<'_, I>) -> Self {
+        Self {
+            lineno: index.lineno,
+            colno: index.colno,
+            depth: index.depth(),
+        }
+    }
+}
+
+/// Receives the events of parsing derived types.
+pub trait Tracer {
+    /// Called when a derived type begins to be parsed, with its name and peggle expression (the expressions of an
+    /// enum's discriminants are separated by ` | `).
+    fn enter(&mut self, rule: &str, pattern: &str, at: Location);
+
+    /// Called when a derived type has been parsed, whether it `matched` (ending at `at`) or failed (at the position
+    /// of its error).
+    fn exit(&mut self, rule: &str, matched: bool, at: Location);
+
+    /// Called when an alternative fails to match (at `at`) and the next alternative is to be tried instead. The
+    /// alternative is given as a peggle expression, and belongs to the most recently entered type.
+    fn backtrack(&mut self, pattern: &str, at: Location);
+}
+
+/// A tracer that ignores every event, which is each thread's tracer until another is installed.
+#[derive(Debug, Default)]
+pub struct NullTracer;
+
+impl Tracer for NullTracer {
+    fn enter(&mut self, _rule: &str, _pattern: &str, _at: Location) {}
+
+    fn exit(&mut self, _rule: &str, _matched: bool, _at: Location) {}
+
+    fn backtrack(&mut self, _pattern: &str, _at: Location) {}
+}
+
+/// A tracer that writes each event to standard error on a line of its own, indented by its depth.
+#[derive(Debug, Default)]
+pub struct PrintTracer;
+
+impl PrintTracer {
+    fn print(at: Location, event: std::fmt::Arguments<'_>) {
+        let indent = "  ".repeat(at.depth.saturating_sub(1));
+        let _ = writeln!(
+            std::io::stderr(),
+            "{}{} at {}:{}",
+            indent,
+            event,
+            at.lineno + 1,
+            at.colno + 1
+        );
+    }
+}
+
+impl Tracer for PrintTracer {
+    fn enter(&mut self, rule: &str, pattern: &str, at: Location) {
+        Self::print(at, format_args!("{} \"{}\"", rule, pattern));
+    }
+
+    fn exit(&mut self, rule: &str, matched: bool, at: Location) {
+        let outcome = if matched { "matched" } else { "failed" };
+        Self::print(at, format_args!("{} {}", rule, outcome));
+    }
+
+    fn backtrack(&mut self, pattern: &str, at: Location) {
+        // Alternatives are tried within the type being parsed, so are indented beneath it
+        let at = Location {
+            depth: at.depth + 1,
+            ..at
+        };
+        Self::print(at, format_args!("backtracking from \"{}\"", pattern));
+    }
+}
+
+thread_local! {
+    static TRACER: RefCell<Box<dyn Tracer>> = RefCell::new(Box::new(NullTracer));
+}
+
+/// Replaces the current thread's tracer, returning the one it replaced.
+pub fn set_tracer(tracer: Box<dyn Tracer>) -> Box<dyn Tracer> {
+    TRACER.with(|t| std::mem::replace(&mut *t.borrow_mut(), tracer))
+}
+
+fn with_tracer(f: impl FnOnce(&mut dyn Tracer)) {
+    TRACER.with(|t| {
+        // A tracer that itself parses a derived type isn't traced
+        if let Ok(mut tracer) = t.try_borrow_mut() {
+            f(tracer.as_mut());
+        }
+    });
+}
+
+#[doc(hidden)]
+pub fn enter<I: ?Sized + Input>(rule: &str, pattern: &str, index: &Index<'_, I>) {
+    with_tracer(|t| t.enter(rule, pattern, Location::of(index)));
+}
+
+#[doc(hidden)]
+pub fn exit<T, I: ?Sized + Input>(
+    rule: &str,
+    index: &Index<'_, I>,
+    result: &Result<(T, Index<'_, I>), ParseError>,
+) {
+    let at = match result {
+        Ok((_, end)) => Location::of(end),
+        Err(err) => Location {
+            lineno: err.lineno,
+            colno: err.colno,
+            depth: index.depth(),
+        },
+    };
+    with_tracer(|t| t.exit(rule, result.is_ok(), at));
+}
+
+#[doc(hidden)]
+pub fn backtrack<I: ?Sized + Input>(pattern: &str, index: &Index<'_, I>) {
+    with_tracer(|t| t.backtrack(pattern, Location::of(index)));
+}
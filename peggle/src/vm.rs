@@ -0,0 +1,334 @@
+//! A small interpreter for peggle expressions compiled into instruction tables.
+//!
+//! Types derived with `#[peg(backend = "vm")]` don't expand their peggle expression into nested blocks and loops;
+//! the expression is instead compiled into a static table of [`Inst`]ructions that is run by [`run`]. This makes
+//! for much less generated code (and so faster builds) at the cost of a little speed when parsing.
+//!
+//! Matching is done by a backtracking machine: [`Inst::Choice`] and [`Inst::Split`] save the current position to
+//! return to should something later fail, and [`Inst::Commit`] discards positions saved since the most recent
+//! choice once one of its alternatives has matched. Repetitions without an upper bound end with [`Inst::Loop`],
+//! which only repeats if the iteration consumed some input, while other repetitions count their iterations with
+//! [`Inst::Counter`], [`Inst::Count`] and [`Inst::PopCounter`] so that their bounds don't affect the size of the
+//! program. Fields are parsed by the derived code itself, which [`run`] calls back into with the number of the
+//! field to be parsed.
+
+use crate::{Index, Input, ParseError};
+
+/// A single step of a compiled peggle expression.
+///
+/// Instructions that jump elsewhere in the program refer to the position of their target within the table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Inst {
+    /// Matches a single character
+    Char(char),
+    /// Matches a run of literal characters
+    Literal(&'static str),
+    /// Matches any single character
+    Any,
+    /// Matches a character within any of the given inclusive ranges
+    Class(&'static [(char, char)]),
+    /// Parses the numbered field, replacing its error with one describing what was `expected` if labeled
+    Field {
+        field: usize,
+        expected: Option<&'static str>,
+    },
+    /// Saves the current position as an alternative to resume from at `target`, then continues with the next
+    /// instruction
+    Choice(usize),
+    /// Saves the current position to give a repetition's match back at, resuming from `target`
+    Split(usize),
+    /// Discards every position saved since (and including) the most recent `Choice`, then jumps to `target`
+    Commit(usize),
+    /// Discards positions like `Commit`, then jumps back to `target` to repeat if input has been consumed since the
+    /// discarded `Choice` was saved, continuing with the next instruction otherwise (as an iteration that consumed
+    /// nothing would match the same way forever)
+    Loop(usize),
+    /// Jumps to `target`
+    Jump(usize),
+    /// Starts counting the iterations of a repetition, from zero
+    Counter,
+    /// Discards positions like `Loop` and counts the iteration that has just matched, then jumps back to `target` to
+    /// repeat unless `max` iterations have matched, or the iteration consumed nothing once more than `min` have
+    /// (continuing with the next instruction otherwise)
+    Count {
+        target: usize,
+        min: usize,
+        max: usize,
+    },
+    /// Fails unless at least the given number of iterations have been counted, then stops counting them
+    PopCounter(usize),
+    /// Takes the current position as that of the failure being backtracked from, so that a repetition that fails
+    /// reports the end of its last matched iteration (as the inline backend does) rather than where an iteration or
+    /// the rest of the sequence failed
+    Rewind,
+    /// Commits to the alternative being matched, so that a failure returns an error rather than backtracking
+    Cut,
+    /// Fails to match, backtracking to the most recently saved position
+    Fail,
+    /// Ends the program, having matched
+    Match,
+}
+
+/// A field value parsed while running a program.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Capture {
+    /// The number of the field, as given in [`Inst::Field`]
+    pub field: usize,
+    /// The value returned by the field callback, identifying where the value was stored
+    pub value: usize,
+}
+
+/// The result of running a program against some input.
+#[derive(Debug)]
+pub enum Outcome<'a, I: ?Sized + Input> {
+    /// The program matched, ending at `end`. `captures` holds the field values that are part of the match (leaving
+    /// out any parsed in attempts that were backtracked over), in the order they were parsed.
+    Matched {
+        end: Index<'a, I>,
+        captures: Vec<Capture>,
+    },
+    /// The program failed to match; the index is the position at which the last attempt failed
+    Failed(Index<'a, I>),
+    /// The program failed after a cut (or a field returned an error after a cut), so `error` should be returned
+    /// rather than trying anything else. `captures` holds the field values parsed up to that point, so that they
+    /// may be used for a partial result when recovering from errors.
+    Cut {
+        error: ParseError,
+        captures: Vec<Capture>,
+    },
+}
+
+/// The callback through which fields are parsed, returning a value identifying the parsed value along with the
+/// index following it.
+pub type FieldParser<'f, 'a, I> =
+    dyn FnMut(usize, Index<'a, I>) -> Result<(usize, Index<'a, I>), ParseError> + 'f;
+
+/// Why a position was saved.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FrameKind {
+    Choice,
+    Split,
+    Cut,
+}
+
+/// A position saved to backtrack to.
+struct Frame<'a, I: ?Sized + Input> {
+    kind: FrameKind,
+    target: usize,
+    index: Index<'a, I>,
+    captures: usize,
+    /// How many repetitions were counting their iterations, and the count of the innermost one. Only the innermost
+    /// count can change while the frame is saved, as each iteration discards the positions saved within it.
+    counters: usize,
+    count: usize,
+}
+
+/// Runs `program` from `index`.
+///
+/// Fields are parsed by calling `fields` with the field number and the index to parse from; the value it identifies
+/// the parsed value with is passed back in the [`Outcome`]'s captures. Descriptive errors from fields that fail to
+/// parse are kept in `error` if they're further along than the one already there, so that the most relevant one can
+/// be reported should the whole program fail.
+pub fn run<'a, I: ?Sized + Input>(
+    program: &[Inst],
+    index: Index<'a, I>,
+    error: &mut Option<ParseError>,
+    fields: &mut FieldParser<'_, 'a, I>,
+) -> Outcome<'a, I> {
+    let mut pc = 0;
+    let mut curr = index;
+    let mut failed_at = index;
+    let mut stack: Vec<Frame<'a, I>> = Vec::new();
+    let mut captures: Vec<Capture> = Vec::new();
+    let mut counters: Vec<usize> = Vec::new();
+
+    loop {
+        let matched = match program[pc] {
+            Inst::Char(c) => {
+                let matched = curr.peek() == Some(c);
+                if matched {
+                    curr.next();
+                }
+                matched
+            }
+            Inst::Literal(literal) => curr.consume(literal),
+            Inst::Any => curr.next().is_some(),
+            Inst::Class(ranges) => match curr.peek() {
+                Some(c) if ranges.iter().any(|&(start, end)| start <= c && c <= end) => {
+                    curr.next();
+                    true
+                }
+                _ => false,
+            },
+            Inst::Field { field, expected } => match fields(field, curr) {
+                Ok((value, end)) => {
+                    captures.push(Capture { field, value });
+                    curr = end;
+                    true
+                }
+                Err(err) => {
+                    if err.cut {
+                        return Outcome::Cut {
+                            error: err,
+                            captures,
+                        };
+                    }
+
+                    let err = match expected {
+                        Some(expected) => ParseError::from_index(curr).with_expected(expected),
+                        None => err,
+                    };
+
+                    if matches!(stack.last(), Some(frame) if frame.kind == FrameKind::Cut) {
+                        return Outcome::Cut {
+                            error: err.with_cut(),
+                            captures,
+                        };
+                    }
+
+                    if err.is_descriptive() {
+                        *error = Some(match error.take() {
+                            Some(prev) => prev.furthest(err),
+                            None => err,
+                        });
+                    }
+                    false
+                }
+            },
+            Inst::Choice(target) | Inst::Split(target) => {
+                let kind = match program[pc] {
+                    Inst::Choice(_) => FrameKind::Choice,
+                    _ => FrameKind::Split,
+                };
+                stack.push(Frame {
+                    kind,
+                    target,
+                    index: curr,
+                    captures: captures.len(),
+                    counters: counters.len(),
+                    count: counters.last().copied().unwrap_or_default(),
+                });
+                true
+            }
+            Inst::Commit(target) => {
+                while let Some(frame) = stack.pop() {
+                    if frame.kind == FrameKind::Choice {
+                        break;
+                    }
+                }
+                pc = target;
+                continue;
+            }
+            Inst::Loop(target) => {
+                if let Err(error) = curr.step() {
+                    return Outcome::Cut { error, captures };
+                }
+
+                let mut start = None;
+                while let Some(frame) = stack.pop() {
+                    if frame.kind == FrameKind::Choice {
+                        start = Some(frame.index);
+                        break;
+                    }
+                }
+
+                if start == Some(curr) {
+                    pc += 1;
+                } else {
+                    pc = target;
+                }
+                continue;
+            }
+            Inst::Jump(target) => {
+                pc = target;
+                continue;
+            }
+            Inst::Counter => {
+                counters.push(0);
+                true
+            }
+            Inst::Count { target, min, max } => {
+                if let Err(error) = curr.step() {
+                    return Outcome::Cut { error, captures };
+                }
+
+                let mut start = None;
+                while let Some(frame) = stack.pop() {
+                    if frame.kind == FrameKind::Choice {
+                        start = Some(frame.index);
+                        break;
+                    }
+                }
+
+                let count = counters.last_mut().expect("`Count` follows a `Counter`");
+                *count += 1;
+                if *count == max || (start == Some(curr) && *count > min) {
+                    pc += 1;
+                } else {
+                    pc = target;
+                }
+                continue;
+            }
+            Inst::PopCounter(min) => {
+                let count = counters.pop().expect("`PopCounter` follows a `Counter`");
+                count >= min
+            }
+            Inst::Rewind => {
+                failed_at = curr;
+                true
+            }
+            Inst::Cut => {
+                stack.push(Frame {
+                    kind: FrameKind::Cut,
+                    target: pc,
+                    index: curr,
+                    captures: captures.len(),
+                    counters: counters.len(),
+                    count: counters.last().copied().unwrap_or_default(),
+                });
+                true
+            }
+            Inst::Fail => false,
+            Inst::Match => {
+                return Outcome::Matched {
+                    end: curr,
+                    captures,
+                }
+            }
+        };
+
+        if matched {
+            pc += 1;
+            continue;
+        }
+
+        // A failure passed on by `Fail` (or by too few iterations) keeps the position of the failure that caused it
+        if !matches!(program[pc], Inst::Fail | Inst::PopCounter(_)) {
+            failed_at = curr;
+        }
+
+        match stack.pop() {
+            Some(frame) if frame.kind == FrameKind::Cut => {
+                return Outcome::Cut {
+                    error: ParseError::from_index(failed_at).with_cut(),
+                    captures,
+                }
+            }
+            Some(frame) => {
+                // Backtracking takes a step, as giving back a repetition's matches does for the inline backend
+                if let Err(error) = frame.index.step() {
+                    return Outcome::Cut { error, captures };
+                }
+                pc = frame.target;
+                curr = frame.index;
+                captures.truncate(frame.captures);
+                // Counters started since may have finished, so the innermost is restored rather than only reset
+                counters.truncate(frame.counters.saturating_sub(1));
+                if frame.counters > 0 {
+                    counters.push(frame.count);
+                }
+            }
+            None => return Outcome::Failed(failed_at),
+        }
+    }
+}